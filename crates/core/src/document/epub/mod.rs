@@ -9,6 +9,7 @@ use anyhow::{Error, format_err};
 use regex::Regex;
 use crate::framebuffer::Pixmap;
 use crate::helpers::{Normalize, decode_entities, safe_slice};
+use crate::font::{Font, FontOpener, FontFamily};
 use crate::document::{Document, Location, TextLocation, TocEntry, BoundedText, chapter_from_uri};
 use crate::unit::pt_to_px;
 use crate::geom::{Rectangle, Boundary, Edge, CycleDir};
@@ -22,11 +23,14 @@ use super::html::style::StyleSheet;
 use super::html::css::CssParser;
 use super::html::xml::XmlParser;
 use crate::document::BYTES_PER_PAGE;
+use crate::metadata::ReadingDirection;
 
 const VIEWER_STYLESHEET: &str = "css/epub.css";
 const USER_STYLESHEET: &str = "css/epub-user.css";
 
 type UriCache = FxHashMap<String, usize>;
+// The faces of each font family, indexed by *(italic, bold)*.
+type FamilyFaces = Vec<(String, FxHashMap<(bool, bool), PathBuf>)>;
 
 impl ResourceFetcher for ZipArchive<File> {
     fn fetch(&mut self, name: &str) -> Result<Vec<u8>, Error> {
@@ -45,7 +49,10 @@ pub struct EpubDocument {
     spine: Vec<Chunk>,
     cache: FxHashMap<usize, Vec<Page>>,
     ignore_document_css: bool,
+    document_fonts: bool,
     extra_css: Option<String>,
+    // Number of words of the spine items counted so far.
+    word_counts: Vec<usize>,
 }
 
 #[derive(Debug)]
@@ -127,10 +134,132 @@ impl EpubDocument {
             spine,
             cache: FxHashMap::default(),
             ignore_document_css: false,
+            document_fonts: false,
+            word_counts: Vec::new(),
             extra_css: None,
         })
     }
 
+    // The style sheets listed in the manifest or linked from the spine documents, and the
+    // style elements of the latter, with the directory their relative URLs start from.
+    fn document_style_sheets(&mut self) -> Vec<(PathBuf, String)> {
+        let mut paths = self.info.root().find("manifest").map(|manifest| {
+            manifest.children()
+                    .filter(|child| child.attribute("media-type") == Some("text/css"))
+                    .filter_map(|child| child.attribute("href"))
+                    .map(|href| {
+                        let href = decode_entities(href);
+                        let href = percent_decode_str(&href).decode_utf8_lossy();
+                        self.parent.join(href.as_ref())
+                    }).collect::<Vec<PathBuf>>()
+        }).unwrap_or_default();
+        let mut sheets = Vec::new();
+
+        for chunk in &self.spine {
+            let mut text = String::new();
+            if let Ok(mut zf) = self.archive.by_name(&chunk.path) {
+                zf.read_to_string(&mut text).ok();
+            }
+            let spine_dir = Path::new(&chunk.path).parent()
+                                                  .unwrap_or_else(|| Path::new(""))
+                                                  .to_path_buf();
+            let root = XmlParser::new(&text).parse();
+            if let Some(head) = root.root().find("head") {
+                for child in head.children() {
+                    if child.tag_name() == Some("link") && child.attribute("rel") == Some("stylesheet") {
+                        if let Some(href) = child.attribute("href") {
+                            let path = spine_dir.join(href).normalize();
+                            if !paths.contains(&path) {
+                                paths.push(path);
+                            }
+                        }
+                    } else if child.tag_name() == Some("style") {
+                        sheets.push((spine_dir.clone(), child.text()));
+                    }
+                }
+            }
+        }
+
+        for path in paths {
+            let mut text = String::new();
+            if let Some(mut zf) = path.to_str().and_then(|name| self.archive.by_name(name).ok()) {
+                zf.read_to_string(&mut text).ok();
+            }
+            let css_dir = path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+            sheets.push((css_dir, text));
+        }
+
+        sheets
+    }
+
+    // Assembles each family declared by the `@font-face` rules of the document's style sheets
+    // that has a regular face. The missing faces fall back on the closest existing ones.
+    fn document_font_families(&mut self) -> Vec<(String, FontFamily)> {
+        let font_face = Regex::new(r"(?s)@font-face\s*\{([^}]*)\}").unwrap();
+        let family_re = Regex::new(r#"font-family\s*:\s*["']?([^;"']+)"#).unwrap();
+        let src_re = Regex::new(r#"src\s*:[^;]*?url\(\s*["']?([^)"']+)"#).unwrap();
+        let style_re = Regex::new(r"font-style\s*:\s*(\w+)").unwrap();
+        let weight_re = Regex::new(r"font-weight\s*:\s*(\w+)").unwrap();
+
+        let mut families: FamilyFaces = Vec::new();
+
+        for (css_dir, text) in self.document_style_sheets() {
+            for caps in font_face.captures_iter(&text) {
+                let block = &caps[1];
+                if let (Some(family), Some(src)) = (family_re.captures(block), src_re.captures(block)) {
+                    let italic = style_re.captures(block)
+                                         .map_or(false, |c| &c[1] == "italic" || &c[1] == "oblique");
+                    let bold = weight_re.captures(block)
+                                        .map_or(false, |c| &c[1] == "bold" || &c[1] == "bolder" ||
+                                                           c[1].parse::<u16>().map_or(false, |w| w >= 600));
+                    let src = percent_decode_str(&src[1]).decode_utf8_lossy().into_owned();
+                    let path = css_dir.join(src).normalize();
+                    let name = family[1].trim().to_string();
+                    let index = families.iter().position(|(n, _)| *n == name).unwrap_or_else(|| {
+                        families.push((name, FxHashMap::default()));
+                        families.len() - 1
+                    });
+                    families[index].1.entry((italic, bold)).or_insert(path);
+                }
+            }
+        }
+
+        let opener = match FontOpener::new() {
+            Ok(opener) => opener,
+            Err(_) => return Vec::new(),
+        };
+        let archive = &mut self.archive;
+
+        families.into_iter().filter_map(|(name, faces)| {
+            let mut open = |keys: &[(bool, bool)]| -> Option<Font> {
+                let path = keys.iter().find_map(|key| faces.get(key))?;
+                let buf = archive.fetch(path.to_str()?).ok()?;
+                opener.open_buffer(buf)
+                      .map_err(|e| eprintln!("Can't open '{}': {:#}.", path.display(), e))
+                      .ok()
+            };
+            let family = FontFamily {
+                regular: open(&[(false, false)])?,
+                italic: open(&[(true, false), (false, false)])?,
+                bold: open(&[(false, true), (false, false)])?,
+                bold_italic: open(&[(true, true), (true, false), (false, true), (false, false)])?,
+            };
+            Some((name, family))
+        }).collect()
+    }
+
+    // Ignoring the document's style sheets also ignores the font faces they declare.
+    fn update_document_fonts(&mut self) {
+        if self.document_fonts && !self.ignore_document_css {
+            if !self.engine.has_document_font_families() {
+                let families = self.document_font_families();
+                self.engine.set_document_font_families(families);
+            }
+        } else {
+            self.engine.clear_document_font_families();
+        }
+    }
+
     fn offset(&self, index: usize) -> usize {
         self.spine.iter().take(index).map(|c| c.size).sum()
     }
@@ -321,6 +450,24 @@ impl EpubDocument {
         }
     }
 
+    fn word_counts(&mut self) -> &[usize] {
+        while self.count_words_step() {}
+        &self.word_counts
+    }
+
+    fn normalize_uri(&self, offset: usize, uri: &str) -> Option<String> {
+        let (index, _) = self.vertebra_coordinates(offset)?;
+        let path = &self.spine[index].path;
+        if uri.starts_with('#') {
+            Some(format!("{}{}", path, uri))
+        } else {
+            let parent = Path::new(path).parent()
+                              .unwrap_or_else(|| Path::new(""));
+            Some(parent.join(uri).normalize()
+                       .to_string_lossy().into_owned())
+        }
+    }
+
     fn cache_uris(&mut self, node: NodeRef, name: &str, start_offset: usize, cache: &mut UriCache) {
         if let Some(id) = node.attribute("id") {
             let location = start_offset + node.offset();
@@ -795,18 +942,7 @@ impl Document for EpubDocument {
             },
             Location::LocalUri(offset, ref uri) => {
                 let mut cache = FxHashMap::default();
-                let normalized_uri: String = {
-                    let (index, _) = self.vertebra_coordinates(offset)?;
-                    let path = &self.spine[index].path;
-                    if uri.starts_with('#') {
-                        format!("{}{}", path, uri)
-                    } else {
-                        let parent = Path::new(path).parent()
-                                          .unwrap_or_else(|| Path::new(""));
-                        parent.join(uri).normalize()
-                              .to_string_lossy().into_owned()
-                    }
-                };
+                let normalized_uri = self.normalize_uri(offset, uri)?;
                 self.resolve_link(&normalized_uri, &mut cache)
             },
             Location::Uri(ref uri) => {
@@ -816,6 +952,26 @@ impl Document for EpubDocument {
         }
     }
 
+    // The URIs share one cache, so that each file is parsed once.
+    fn resolve_locations(&mut self, locs: Vec<Location>) -> Vec<Option<usize>> {
+        self.engine.load_fonts();
+        let mut cache = FxHashMap::default();
+        locs.into_iter().map(|loc| {
+            match loc {
+                Location::LocalUri(offset, ref uri) => {
+                    let normalized_uri = self.normalize_uri(offset, uri)?;
+                    cache.get(&normalized_uri).cloned()
+                         .or_else(|| self.resolve_link(&normalized_uri, &mut cache))
+                },
+                Location::Uri(ref uri) => {
+                    cache.get(uri).cloned()
+                         .or_else(|| self.resolve_link(uri, &mut cache))
+                },
+                _ => self.resolve_location(loc),
+            }
+        }).collect()
+    }
+
     fn words(&mut self, loc: Location) -> Option<(Vec<BoundedText>, usize)> {
         if self.spine.is_empty() {
             return None;
@@ -943,6 +1099,13 @@ impl Document for EpubDocument {
 
     fn set_ignore_document_css(&mut self, ignore: bool) {
         self.ignore_document_css = ignore;
+        self.update_document_fonts();
+        self.cache.clear();
+    }
+
+    fn use_document_fonts(&mut self, enable: bool) {
+        self.document_fonts = enable;
+        self.update_document_fonts();
         self.cache.clear();
     }
 
@@ -964,7 +1127,7 @@ impl Document for EpubDocument {
            and offset points at "world"
            return ("p.indent", "span.bold", "hello, world",  "llo, <span class=\"bold\">world</span></p>")
     */
-    fn get_node_data_at(&mut self, offset: usize, chunk_size: usize) -> Option<(String, String, String, String)> {
+    fn get_node_data_at(&mut self, offset: usize, chunk_size: usize) -> Option<(String, String, String, String, Vec<String>)> {
         let (index, start_offset) = self.vertebra_coordinates(offset)?;
         let mut text = String::new();
         {
@@ -1008,14 +1171,66 @@ impl Document for EpubDocument {
             "".to_string()
         };
 
+        let ancestors = div.ancestor_elements()
+                           .filter_map(|n| {
+                               let tag = n.tag_name()?;
+                               Some(match n.attribute("class").map(str::trim).filter(|cls| !cls.is_empty()) {
+                                   Some(cls) => format!("{}.{}", tag, re.replace_all(cls, ".")),
+                                   None => tag.to_string(),
+                               })
+                           })
+                           .take_while(|sel| sel != "html")
+                           .collect::<Vec<String>>();
+
         if chunk_size > 0 {
             let offset = div.offset();
             let a = if offset > chunk_size / 2 { offset - chunk_size / 2 } else { 0 };
             let b = (offset + chunk_size).min(text.len());
             let html = safe_slice(&text, a, b).trim();
-            Some((div_selector, span_selector, div.text(), html.to_string()))
+            Some((div_selector, span_selector, div.text(), html.to_string(), ancestors))
+        } else {
+            Some((div_selector, span_selector, "".to_string(), "".to_string(), ancestors))
+        }
+    }
+
+    fn footnote(&mut self, loc: Location) -> Option<String> {
+        let uri = match loc {
+            Location::LocalUri(offset, ref uri) => self.normalize_uri(offset, uri)?,
+            Location::Uri(ref uri) => uri.clone(),
+            _ => return None,
+        };
+        let frag_index = uri.find('#')?;
+        let (name, id) = (&uri[..frag_index], &uri[frag_index+1..]);
+
+        let mut text = String::new();
+        {
+            let mut zf = self.archive.by_name(name).ok()?;
+            zf.read_to_string(&mut text).ok()?;
+        }
+        let root = XmlParser::new(&text).parse();
+        let node = root.root().find_by_id(id)?;
+
+        let is_note = |n: &NodeRef| {
+            n.tag_name() == Some("aside") ||
+            n.attribute("epub:type").map_or(false, |t| t.contains("note")) ||
+            n.attribute("role").map_or(false, |r| r.starts_with("doc-") && r.ends_with("note"))
+        };
+
+        if !is_note(&node) && !node.ancestors().any(|n| is_note(&n)) {
+            return None;
+        }
+
+        let block = if node.is_block() {
+            node
         } else {
-            Some((div_selector, span_selector, "".to_string(), "".to_string()))
+            node.ancestors().find(|n| n.is_block())?
+        };
+
+        let note = block.text().split_whitespace().collect::<Vec<&str>>().join(" ");
+        if note.is_empty() {
+            None
+        } else {
+            Some(decode_entities(&note).into_owned())
         }
     }
 
@@ -1038,6 +1253,45 @@ impl Document for EpubDocument {
         true
     }
 
+    // The counts don't depend on the layout, they're computed once from the sources.
+    fn count_words_step(&mut self) -> bool {
+        let index = self.word_counts.len();
+        let path = match self.spine.get(index) {
+            Some(chunk) => chunk.path.clone(),
+            None => return false,
+        };
+        let mut text = String::new();
+        let count = self.archive.by_name(&path).ok()
+                        .and_then(|mut zf| zf.read_to_string(&mut text).ok())
+                        .and_then(|_| {
+                            let root = XmlParser::new(&text).parse();
+                            root.root().find("body").map(|body| body.text().split_whitespace().count())
+                        })
+                        .unwrap_or(0);
+        self.word_counts.push(count);
+        index + 1 < self.spine.len()
+    }
+
+    fn word_count(&mut self) -> Option<usize> {
+        Some(self.word_counts().iter().sum())
+    }
+
+    fn word_offset(&mut self, location: usize) -> Option<usize> {
+        let (index, start_offset) = self.vertebra_coordinates(location)?;
+        let size = self.spine[index].size.max(1);
+        let counts = self.word_counts();
+        let within = counts[index] * (location - start_offset).min(size) / size;
+        Some(counts[..index].iter().sum::<usize>() + within)
+    }
+
+    fn reading_direction(&self) -> ReadingDirection {
+        match self.info.root().find("spine")
+                  .and_then(|spine| spine.attribute("page-progression-direction")) {
+            Some("rtl") => ReadingDirection::Rtl,
+            _ => ReadingDirection::Ltr,
+        }
+    }
+
     fn has_synthetic_page_numbers(&self) -> bool {
         true
     }
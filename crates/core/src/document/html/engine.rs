@@ -9,11 +9,12 @@ use percent_encoding::percent_decode_str;
 use septem::Roman;
 use crate::helpers::{Normalize, decode_entities};
 use crate::framebuffer::{Framebuffer, Pixmap};
-use crate::font::{FontOpener, FontFamily};
+use crate::font::{Font, FontOpener, FontFamily};
 use crate::document::{Document, Location};
 use crate::document::pdf::PdfOpener;
 use crate::unit::{mm_to_px, pt_to_px};
-use crate::geom::{Point, Vec2, Rectangle, Edge};
+use crate::geom::{Point, Vec2, Rectangle, Edge, BorderSpec};
+use crate::color::GRAY08;
 use crate::settings::{HYPHEN_PENALTY, STRETCH_TOLERANCE};
 use crate::settings::{DEFAULT_FONT_SIZE, DEFAULT_MARGIN_WIDTH, DEFAULT_TEXT_ALIGN, DEFAULT_LINE_HEIGHT};
 use super::parse::{parse_display, parse_edge, parse_float, parse_text_align, parse_text_indent};
@@ -24,7 +25,7 @@ use super::parse::{parse_line_height, parse_vertical_align, parse_color, parse_l
 use super::dom::{NodeRef, NodeData, ElementData, TextData, WRAPPER_TAG_NAME};
 use super::layout::{StyleData, InlineMaterial, TextMaterial, ImageMaterial};
 use super::layout::{GlueMaterial, PenaltyMaterial, ChildArtifact, SiblingStyle, LoopContext};
-use super::layout::{RootData, DrawState, DrawCommand, TextCommand, ImageCommand, FontKind, Fonts};
+use super::layout::{RootData, DrawState, DrawCommand, TextCommand, ImageCommand, FontKind, FontStyle, FontWeight, Fonts};
 use super::layout::{TextAlign, ParagraphElement, TextElement, ImageElement, Display, Float};
 use super::layout::{WordSpacing, ListStyleType, LineStats};
 use super::layout::{hyph_lang, collapse_margins, DEFAULT_HYPH_LANG, HYPHENATION_PATTERNS};
@@ -35,6 +36,10 @@ use super::xml::XmlExt;
 const DEFAULT_DPI: u16 = 300;
 const DEFAULT_WIDTH: u32 = 1404;
 const DEFAULT_HEIGHT: u32 = 1872;
+// Label of the placeholder of an image without alternate text that can't be decoded.
+const MISSING_IMAGE_LABEL: &str = "Missing image";
+// Border thickness, in points, of the placeholder of an image.
+const PLACEHOLDER_THICKNESS: f32 = 0.5;
 
 pub type Page = Vec<DrawCommand>;
 
@@ -46,6 +51,8 @@ pub trait ResourceFetcher {
 pub struct Engine {
     // The fonts used for each CSS font family.
     fonts: Option<Fonts>,
+    // The lowercase names of the families declared by the document, indexed like `Fonts::document`.
+    document_families: Vec<String>,
     // The penalty for lines ending with a hyphen.
     hyphen_penalty: i32,
     // The stretching/shrinking allowed for word spaces.
@@ -71,6 +78,7 @@ impl Engine {
 
         Engine {
             fonts: None,
+            document_families: Vec::new(),
             hyphen_penalty: HYPHEN_PENALTY,
             stretch_tolerance: STRETCH_TOLERANCE,
             margin,
@@ -85,10 +93,16 @@ impl Engine {
     #[inline]
     pub fn load_fonts(&mut self) {
         if self.fonts.is_none() {
-            self.fonts = default_fonts().ok();
+            self.fonts = default_fonts(Path::new("")).ok();
         }
     }
 
+    // The tests don't depend on the current directory.
+    #[cfg(test)]
+    pub fn load_fonts_from(&mut self, dir: &Path) {
+        self.fonts = default_fonts(dir).ok();
+    }
+
     pub fn set_hyphen_penalty(&mut self, hyphen_penalty: i32) {
         self.hyphen_penalty = hyphen_penalty;
     }
@@ -125,6 +139,38 @@ impl Engine {
         }
     }
 
+    pub fn set_document_font_families(&mut self, families: Vec<(String, FontFamily)>) {
+        self.load_fonts();
+        if let Some(fonts) = self.fonts.as_mut() {
+            let (names, families): (Vec<String>, Vec<FontFamily>) = families.into_iter()
+                                                                            .map(|(name, family)| (name.to_lowercase(), family))
+                                                                            .unzip();
+            self.document_families = names;
+            fonts.document = families;
+        }
+    }
+
+    pub fn clear_document_font_families(&mut self) {
+        self.document_families.clear();
+        if let Some(fonts) = self.fonts.as_mut() {
+            fonts.document.clear();
+        }
+    }
+
+    #[inline]
+    pub fn has_document_font_families(&self) -> bool {
+        !self.document_families.is_empty()
+    }
+
+    // The first family of the list declared by the document, or the generic family of the list.
+    fn font_kind(&self, value: &str) -> Option<FontKind> {
+        value.split(',')
+             .map(|name| name.trim().trim_matches(|c| c == '"' || c == '\'').to_lowercase())
+             .find_map(|name| self.document_families.iter().position(|family| *family == name))
+             .map(FontKind::Document)
+             .or_else(|| parse_font_kind(value))
+    }
+
     pub fn set_margin_width(&mut self, width: i32) {
         self.margin = Edge::uniform(mm_to_px(width as f32, self.dpi).round() as i32);
     }
@@ -208,7 +254,7 @@ impl Engine {
                                     .unwrap_or(parent_style.vertical_align);
 
         style.font_kind = props.get("font-family")
-                               .and_then(|value| parse_font_kind(value))
+                               .and_then(|value| self.font_kind(value))
                                .unwrap_or(parent_style.font_kind);
 
         style.font_style = props.get("font-style")
@@ -623,7 +669,7 @@ impl Engine {
                                     .unwrap_or(0);
 
                 style.font_kind = props.get("font-family")
-                                       .and_then(|value| parse_font_kind(value))
+                                       .and_then(|value| self.font_kind(value))
                                        .unwrap_or(parent_style.font_kind);
 
                 style.color = props.get("color")
@@ -676,6 +722,10 @@ impl Engine {
                                                                   .into_owned())
                         }).unwrap_or_default();
 
+                        let alt = attributes.get("alt")
+                                            .map(|alt| decode_entities(alt.trim()).into_owned())
+                                            .filter(|alt| !alt.is_empty());
+
                         style.float = props.get("float").and_then(|value| parse_float(value));
 
                         let is_block = style.display == Display::Block;
@@ -692,6 +742,7 @@ impl Engine {
                         inlines.push(InlineMaterial::Image(ImageMaterial {
                             offset: *offset,
                             path,
+                            alt,
                             style,
                         }));
                         if is_block {
@@ -763,9 +814,10 @@ impl Engine {
 
         for (index, mater) in inlines.iter().enumerate() {
             match mater {
-                InlineMaterial::Image(ImageMaterial { offset, path, style }) => {
+                InlineMaterial::Image(ImageMaterial { offset, path, alt, style }) => {
                     let (mut width, mut height) = (style.width, style.height);
                     let mut scale = 1.0;
+                    let mut missing = true;
                     let dpi = self.dpi;
 
                     if let Ok(buf) = resource_fetcher.fetch(path) {
@@ -780,30 +832,44 @@ impl Engine {
                                     width = (height as f32 * w / h).round() as i32;
                                 }
                                 scale = width as f32 / w;
+                                missing = false;
                             }
                         }
+                    }
 
-                        if width * height > 0 {
-                            let element = ImageElement {
-                                    offset: *offset,
-                                    width,
-                                    height,
-                                    scale,
-                                    vertical_align: style.vertical_align,
-                                    display: style.display,
-                                    margin: style.margin,
-                                    float: style.float,
-                                    path: path.clone(),
-                                    uri: style.uri.clone(),
-                            };
-                            if style.float.is_none() {
-                                items.push(ParagraphItem::Box {
-                                    width,
-                                    data: ParagraphElement::Image(element),
-                                });
-                            } else {
-                                floats.push(element);
-                            }
+                    // The missing dimensions of a placeholder are those of its label.
+                    if missing {
+                        let (label_width, label_height) = self.placeholder_dims(alt.as_deref());
+                        if width == 0 {
+                            width = label_width.min(line_width);
+                        }
+                        if height == 0 {
+                            height = label_height;
+                        }
+                    }
+
+                    if width * height > 0 {
+                        let element = ImageElement {
+                                offset: *offset,
+                                width,
+                                height,
+                                scale,
+                                vertical_align: style.vertical_align,
+                                display: style.display,
+                                margin: style.margin,
+                                float: style.float,
+                                path: path.clone(),
+                                alt: alt.clone(),
+                                uri: style.uri.clone(),
+                                missing,
+                        };
+                        if style.float.is_none() {
+                            items.push(ParagraphItem::Box {
+                                width,
+                                data: ParagraphElement::Image(element),
+                            });
+                        } else {
+                            floats.push(element);
                         }
                     }
                 },
@@ -1096,7 +1162,9 @@ impl Engine {
                     rect,
                     scale: element.scale,
                     path: element.path,
+                    alt: element.alt,
                     uri: element.uri,
+                    missing: element.missing,
                 }));
             }
         }
@@ -1383,7 +1451,9 @@ impl Engine {
                                     rect,
                                     scale,
                                     path: element.path.clone(),
+                                    alt: element.alt.clone(),
                                     uri: element.uri.clone(),
+                                    missing: element.missing,
                                 }));
                             },
                             _ => (),
@@ -1681,6 +1751,36 @@ impl Engine {
         merged_items
     }
 
+    fn placeholder_font(&mut self, scale_factor: f32) -> &mut Font {
+        let font_size = (scale_factor * self.font_size * 64.0) as u32;
+        let font = self.fonts.as_mut().unwrap()
+                       .get_mut(FontKind::Serif, FontStyle::Italic, FontWeight::Normal);
+        font.set_size(font_size, self.dpi);
+        font
+    }
+
+    // Dimensions of the placeholder of an image that can't be decoded.
+    fn placeholder_dims(&mut self, alt: Option<&str>) -> (i32, i32) {
+        let padding = pt_to_px(self.font_size / 2.0, self.dpi).round() as i32;
+        let font = self.placeholder_font(1.0);
+        let plan = font.plan(alt.unwrap_or(MISSING_IMAGE_LABEL), None, None);
+        (plan.width + 2 * padding, font.line_height() + 2 * padding)
+    }
+
+    // Frames the alternate text of the image, or a generic label, in the given rectangle.
+    fn render_placeholder(&mut self, fb: &mut Pixmap, rect: Rectangle, alt: Option<&str>, scale_factor: f32) {
+        let thickness = pt_to_px(scale_factor * PLACEHOLDER_THICKNESS, self.dpi).round().max(1.0) as u16;
+        fb.draw_rectangle_outline(&rect, &BorderSpec { thickness, color: GRAY08 });
+        let padding = pt_to_px(scale_factor * self.font_size / 2.0, self.dpi).round() as i32;
+        let font = self.placeholder_font(scale_factor);
+        let mut plan = font.plan(alt.unwrap_or(MISSING_IMAGE_LABEL), None, None);
+        font.crop_right(&mut plan, rect.width() as i32 - 2 * padding);
+        let x_height = font.x_heights.0 as i32;
+        let pt = pt!(rect.min.x + (rect.width() as i32 - plan.width) / 2,
+                     rect.min.y + (rect.height() as i32 + x_height) / 2);
+        font.render(fb, GRAY08, &plan, pt);
+    }
+
     pub fn render_page(&mut self, page: &[DrawCommand], scale_factor: f32, resource_fetcher: &mut dyn ResourceFetcher) -> Option<Pixmap> {
         let width = (self.dims.0 as f32 * scale_factor) as u32;
         let height = (self.dims.1 as f32 * scale_factor) as u32;
@@ -1700,6 +1800,12 @@ impl Engine {
                     font.set_size(font_size, self.dpi);
                     font.render(&mut fb, *color, &plan, position);
                 },
+                DrawCommand::Image(ImageCommand { position, rect, alt, missing: true, .. }) => {
+                    let position = Point::from(scale_factor * Vec2::from(*position));
+                    let size = scale_factor * Vec2::new(rect.width() as f32, rect.height() as f32);
+                    let rect = rect![position, position + pt!(size.x as i32, size.y as i32)];
+                    self.render_placeholder(&mut fb, rect, alt.as_deref(), scale_factor);
+                },
                 DrawCommand::Image(ImageCommand { position, path, scale, .. }) => {
                     if let Ok(buf) = resource_fetcher.fetch(path) {
                         if let Some((pixmap, _)) = PdfOpener::new().and_then(|opener| {
@@ -1746,56 +1852,57 @@ fn format_list_prefix(kind: ListStyleType, index: usize) -> Option<String> {
     }
 }
 
-fn default_fonts() -> Result<Fonts, Error> {
+fn default_fonts(dir: &Path) -> Result<Fonts, Error> {
     let opener = FontOpener::new()?;
     let mut fonts = Fonts {
-        serif: if Path::new("fonts/book-serif-Regular.ttf").exists() {
+        serif: if dir.join("fonts/book-serif-Regular.ttf").exists() {
             FontFamily {
-                regular: opener.open("fonts/book-serif-Regular.ttf")?,
-                italic: opener.open("fonts/book-serif-Italic.ttf")?,
-                bold: opener.open("fonts/book-serif-Bold.ttf")?,
-                bold_italic: opener.open("fonts/book-serif-BoldItalic.ttf")?,
+                regular: opener.open(dir.join("fonts/book-serif-Regular.ttf"))?,
+                italic: opener.open(dir.join("fonts/book-serif-Italic.ttf"))?,
+                bold: opener.open(dir.join("fonts/book-serif-Bold.ttf"))?,
+                bold_italic: opener.open(dir.join("fonts/book-serif-BoldItalic.ttf"))?,
             }
         } else {
             FontFamily {
-                regular: opener.open("fonts/LibertinusSerif-Regular.otf")?,
-                italic: opener.open("fonts/LibertinusSerif-Italic.otf")?,
-                bold: opener.open("fonts/LibertinusSerif-Bold.otf")?,
-                bold_italic: opener.open("fonts/LibertinusSerif-BoldItalic.otf")?,
+                regular: opener.open(dir.join("fonts/LibertinusSerif-Regular.otf"))?,
+                italic: opener.open(dir.join("fonts/LibertinusSerif-Italic.otf"))?,
+                bold: opener.open(dir.join("fonts/LibertinusSerif-Bold.otf"))?,
+                bold_italic: opener.open(dir.join("fonts/LibertinusSerif-BoldItalic.otf"))?,
             }
         },
-        sans_serif: if Path::new("fonts/book-sans-Regular.ttf").exists() {
+        sans_serif: if dir.join("fonts/book-sans-Regular.ttf").exists() {
             FontFamily {
-                regular: opener.open("fonts/book-sans-Regular.ttf")?,
-                italic: opener.open("fonts/book-sans-Italic.ttf")?,
-                bold: opener.open("fonts/book-sans-Bold.ttf")?,
-                bold_italic: opener.open("fonts/book-sans-BoldItalic.ttf")?,
+                regular: opener.open(dir.join("fonts/book-sans-Regular.ttf"))?,
+                italic: opener.open(dir.join("fonts/book-sans-Italic.ttf"))?,
+                bold: opener.open(dir.join("fonts/book-sans-Bold.ttf"))?,
+                bold_italic: opener.open(dir.join("fonts/book-sans-BoldItalic.ttf"))?,
             }
         } else {
             FontFamily {
-                regular: opener.open("fonts/NotoSans-Regular.ttf")?,
-                italic: opener.open("fonts/NotoSans-Italic.ttf")?,
-                bold: opener.open("fonts/NotoSans-Bold.ttf")?,
-                bold_italic: opener.open("fonts/NotoSans-BoldItalic.ttf")?,
+                regular: opener.open(dir.join("fonts/NotoSans-Regular.ttf"))?,
+                italic: opener.open(dir.join("fonts/NotoSans-Italic.ttf"))?,
+                bold: opener.open(dir.join("fonts/NotoSans-Bold.ttf"))?,
+                bold_italic: opener.open(dir.join("fonts/NotoSans-BoldItalic.ttf"))?,
             }
         },
-        monospace: if Path::new("fonts/book-monospace-Regular.ttf").exists() {
+        monospace: if dir.join("fonts/book-monospace-Regular.ttf").exists() {
             FontFamily {
-                regular: opener.open("fonts/book-monospace-Regular.ttf")?,
-                italic: opener.open("fonts/book-monospace-Italic.ttf")?,
-                bold: opener.open("fonts/book-monospace-Regular.ttf")?,
-                bold_italic: opener.open("fonts/book-monospace-Italic.ttf")?,
+                regular: opener.open(dir.join("fonts/book-monospace-Regular.ttf"))?,
+                italic: opener.open(dir.join("fonts/book-monospace-Italic.ttf"))?,
+                bold: opener.open(dir.join("fonts/book-monospace-Regular.ttf"))?,
+                bold_italic: opener.open(dir.join("fonts/book-monospace-Italic.ttf"))?,
             }
         } else {
             FontFamily {
-                regular: opener.open("fonts/SourceCodeVariable-Roman.otf")?,
-                italic: opener.open("fonts/SourceCodeVariable-Italic.otf")?,
-                bold: opener.open("fonts/SourceCodeVariable-Roman.otf")?,
-                bold_italic: opener.open("fonts/SourceCodeVariable-Italic.otf")?,
+                regular: opener.open(dir.join("fonts/SourceCodeVariable-Roman.otf"))?,
+                italic: opener.open(dir.join("fonts/SourceCodeVariable-Italic.otf"))?,
+                bold: opener.open(dir.join("fonts/SourceCodeVariable-Roman.otf"))?,
+                bold_italic: opener.open(dir.join("fonts/SourceCodeVariable-Italic.otf"))?,
             }
         },
-        cursive: opener.open("fonts/Parisienne-Regular.ttf")?,
-        fantasy: opener.open("fonts/Delius-Regular.ttf")?,
+        cursive: opener.open(dir.join("fonts/Parisienne-Regular.ttf"))?,
+        fantasy: opener.open(dir.join("fonts/Delius-Regular.ttf"))?,
+        document: Vec::new(),
     };
     fonts.monospace.bold.set_variations(&["wght=600"]);
     fonts.monospace.bold_italic.set_variations(&["wght=600"]);
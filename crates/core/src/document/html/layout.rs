@@ -235,6 +235,7 @@ pub struct TextMaterial {
 pub struct ImageMaterial {
     pub offset: usize,
     pub path: String,
+    pub alt: Option<String>,
     pub style: StyleData,
 }
 
@@ -259,6 +260,8 @@ pub enum FontKind {
     Monospace,
     Cursive,
     Fantasy,
+    // A family declared by the document's `@font-face` rules.
+    Document(usize),
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -279,6 +282,7 @@ pub struct Fonts {
     pub monospace: FontFamily,
     pub cursive: Font,
     pub fantasy: Font,
+    pub document: Vec<FontFamily>,
 }
 
 impl Fonts {
@@ -310,6 +314,15 @@ impl Fonts {
             },
             FontKind::Cursive => &mut self.cursive,
             FontKind::Fantasy => &mut self.fantasy,
+            FontKind::Document(index) => {
+                let family = if index < self.document.len() { &mut self.document[index] } else { &mut self.serif };
+                match (font_style, font_weight) {
+                    (FontStyle::Normal, FontWeight::Normal) => &mut family.regular,
+                    (FontStyle::Normal, FontWeight::Bold) => &mut family.bold,
+                    (FontStyle::Italic, FontWeight::Normal) => &mut family.italic,
+                    (FontStyle::Italic, FontWeight::Bold) => &mut family.bold_italic,
+                }
+            },
         }
     }
 }
@@ -349,7 +362,10 @@ pub struct ImageElement {
     pub margin: Edge,
     pub float: Option<Float>,
     pub path: String,
+    pub alt: Option<String>,
     pub uri: Option<String>,
+    // The image couldn't be decoded and is replaced by a placeholder.
+    pub missing: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -381,8 +397,10 @@ pub struct ImageCommand {
     pub position: Point,
     pub scale: f32,
     pub path: String,
+    pub alt: Option<String>,
     pub uri: Option<String>,
     pub rect: Rectangle,
+    pub missing: bool,
 }
 
 impl DrawCommand {
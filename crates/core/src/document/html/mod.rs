@@ -94,6 +94,11 @@ impl HtmlDocument {
         }
     }
 
+    #[cfg(test)]
+    pub fn load_fonts_from(&mut self, dir: &Path) {
+        self.engine.load_fonts_from(dir);
+    }
+
     pub fn update(&mut self, text: &str) {
         self.size = text.len();
         self.content = XmlParser::new(text).parse();
@@ -283,6 +288,16 @@ impl Document for HtmlDocument {
         None
     }
 
+    fn word_count(&mut self) -> Option<usize> {
+        self.content.root().find("body")
+            .map(|body| body.text().split_whitespace().count())
+    }
+
+    fn word_offset(&mut self, location: usize) -> Option<usize> {
+        let count = self.word_count()?;
+        Some(count * location.min(self.size) / self.size.max(1))
+    }
+
     fn chapter_relative<'a>(&mut self, _offset: usize, _dir: CycleDir, _toc: &'a [TocEntry]) -> Option<&'a TocEntry> {
         None
     }
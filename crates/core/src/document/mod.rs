@@ -11,7 +11,7 @@ use std::process::Command;
 use std::path::Path;
 use std::fs::{self, File};
 use std::ffi::OsStr;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::os::unix::fs::FileExt;
 use anyhow::{Error, format_err};
 use regex::Regex;
@@ -22,12 +22,14 @@ use fxhash::FxHashMap;
 use unicode_normalization::UnicodeNormalization;
 use unicode_normalization::char::{is_combining_mark};
 use serde::{Serialize, Deserialize};
+use chrono::{NaiveDateTime, Duration};
 use self::djvu::DjvuOpener;
 use self::pdf::PdfOpener;
 use self::epub::EpubDocument;
 use self::html::HtmlDocument;
 use crate::geom::{Boundary, CycleDir};
-use crate::metadata::{TextAlign, Annotation};
+use crate::helpers::encode_entities;
+use crate::metadata::{TextAlign, ReadingDirection, Annotation};
 use crate::framebuffer::Pixmap;
 use crate::settings::INTERNAL_CARD_ROOT;
 use crate::device::CURRENT_DEVICE;
@@ -110,9 +112,36 @@ pub trait Document: Send+Sync {
     fn set_hyphen_penalty(&mut self, hyphen_penalty: i32);
     fn set_stretch_tolerance(&mut self, stretch_tolerance: f32);
     fn set_ignore_document_css(&mut self, ignore: bool);
+
+    // Whether the text styled with the families embedded in the document uses their fonts.
+    fn use_document_fonts(&mut self, _enable: bool) {
+    }
     fn set_extra_css(&mut self, _text: &str) {
     }
-    fn get_node_data_at(&mut self, _offset: usize, _chunk_size: usize) -> Option<(String, String, String, String)> {
+    // Returns the block and wrapper selectors, the text and the HTML around the given offset,
+    // and the selectors of the ancestors of the block, nearest first.
+    fn get_node_data_at(&mut self, _offset: usize, _chunk_size: usize) -> Option<(String, String, String, String, Vec<String>)> {
+        None
+    }
+    // Returns the text of the note targeted by the given link, if it's a footnote.
+    fn footnote(&mut self, _loc: Location) -> Option<String> {
+        None
+    }
+    // Counts the words of the next part of the document that wasn't counted yet,
+    // and returns whether parts remain. Counting in steps lets the document be shared meanwhile.
+    fn count_words_step(&mut self) -> bool {
+        false
+    }
+    // Returns the number of words of the document.
+    fn word_count(&mut self) -> Option<usize> {
+        None
+    }
+    // Returns the approximate number of words that precede the given location.
+    fn word_offset(&mut self, _location: usize) -> Option<usize> {
+        None
+    }
+    // Returns the printed page number of the given page, if the document defines one.
+    fn page_label(&self, _location: usize) -> Option<String> {
         None
     }
 
@@ -122,6 +151,10 @@ pub trait Document: Send+Sync {
 
     fn is_reflowable(&self) -> bool;
 
+    fn reading_direction(&self) -> ReadingDirection {
+        ReadingDirection::Ltr
+    }
+
     fn has_synthetic_page_numbers(&self) -> bool {
         false
     }
@@ -167,6 +200,11 @@ pub trait Document: Send+Sync {
             _ => None,
         }
     }
+
+    // Resolves several locations at once, which lets documents share the work between them.
+    fn resolve_locations(&mut self, locs: Vec<Location>) -> Vec<Option<usize>> {
+        locs.into_iter().map(|loc| self.resolve_location(loc)).collect()
+    }
 }
 
 pub fn file_kind<P: AsRef<Path>>(path: P) -> Option<String> {
@@ -280,26 +318,72 @@ impl From<TocLocation> for Location {
     }
 }
 
-pub fn toc_as_html(toc: &[TocEntry], chap_index: usize) -> String {
+// Prefix of the links of the table of contents that collapse or expand an entry.
+pub const TOGGLE_TOC_ENTRY_URI: &str = "@toggle-toc-entry:";
+
+// Returns the entries with children that don't lead to the chapter at `chap_index`.
+pub fn toc_collapsed_entries(toc: &[TocEntry], chap_index: usize) -> BTreeSet<usize> {
+    let mut collapsed = BTreeSet::new();
+    toc_collapsed_entries_aux(toc, chap_index, &mut collapsed);
+    collapsed
+}
+
+fn toc_collapsed_entries_aux(toc: &[TocEntry], chap_index: usize, collapsed: &mut BTreeSet<usize>) -> bool {
+    let mut found = false;
+    for entry in toc {
+        let inside = toc_collapsed_entries_aux(&entry.children, chap_index, collapsed);
+        if entry.index == chap_index || inside {
+            found = true;
+        } else if !entry.children.is_empty() {
+            collapsed.insert(entry.index);
+        }
+    }
+    found
+}
+
+// Indices of the entries leading to the given chapter, the chapter included.
+pub fn toc_chapter_path(toc: &[TocEntry], chap_index: usize) -> Vec<usize> {
+    let mut path = Vec::new();
+    toc_chapter_path_aux(toc, chap_index, &mut path);
+    path
+}
+
+fn toc_chapter_path_aux(toc: &[TocEntry], chap_index: usize, path: &mut Vec<usize>) -> bool {
+    for entry in toc {
+        if entry.index == chap_index || toc_chapter_path_aux(&entry.children, chap_index, path) {
+            path.push(entry.index);
+            return true;
+        }
+    }
+    false
+}
+
+pub fn toc_as_html(toc: &[TocEntry], chap_index: usize, collapsed: &BTreeSet<usize>) -> String {
     let mut buf = format!(
         "<html>\n\t<head>\n\t\t<title>Table of Contents</title>\n\t\t\
         <link rel=\"stylesheet\" type=\"text/css\" href=\"{}\"/>\n\t\
         </head>\n\t<body>\n",
         if Path::new("css/toc-user.css").exists() {"css/toc-user.css"} else {"css/toc.css"});
-    toc_as_html_aux(toc, chap_index, 0, &mut buf);
+    toc_as_html_aux(toc, chap_index, collapsed, 0, &mut buf);
     buf.push_str("\t</body>\n</html>");
     buf
 }
 
-pub fn toc_as_html_aux(toc: &[TocEntry], chap_index: usize, depth: usize, buf: &mut String) {
+pub fn toc_as_html_aux(toc: &[TocEntry], chap_index: usize, collapsed: &BTreeSet<usize>, depth: usize, buf: &mut String) {
     buf.push_str(&"\t".repeat(depth + 2));
     buf.push_str("<ul>\n");
     for entry in toc {
         buf.push_str(&"\t".repeat(depth + 3));
+        buf.push_str("<li>");
+        if !entry.children.is_empty() {
+            let marker = if collapsed.contains(&entry.index) { "+" } else { "−" };
+            buf.push_str(&format!("<a class=\"toggle\" href=\"{}{}\">{}</a> ",
+                                  TOGGLE_TOC_ENTRY_URI, entry.index, marker));
+        }
         match entry.location {
-            Location::Exact(n) => buf.push_str(&format!("<li><a href=\"@{}\">", n)),
-            Location::Uri(ref uri) => buf.push_str(&format!("<li><a href=\"@{}\">", uri)),
-            _ => buf.push_str("<li><a href=\"#\">"),
+            Location::Exact(n) => buf.push_str(&format!("<a href=\"@{}\">", n)),
+            Location::Uri(ref uri) => buf.push_str(&format!("<a href=\"@{}\">", uri)),
+            _ => buf.push_str("<a href=\"#\">"),
         }
         let title = entry.title.replace('<', "&lt;").replace('>', "&gt;");
         if entry.index == chap_index {
@@ -308,14 +392,43 @@ pub fn toc_as_html_aux(toc: &[TocEntry], chap_index: usize, depth: usize, buf: &
             buf.push_str(&title);
         }
         buf.push_str("</a></li>\n");
-        if !entry.children.is_empty() {
-            toc_as_html_aux(&entry.children, chap_index, depth + 1, buf);
+        if !entry.children.is_empty() && !collapsed.contains(&entry.index) {
+            toc_as_html_aux(&entry.children, chap_index, collapsed, depth + 1, buf);
         }
     }
     buf.push_str(&"\t".repeat(depth + 2));
     buf.push_str("</ul>\n");
 }
 
+// Prefixes of the links of the annotations page that act on an annotation instead of going to it.
+pub const EDIT_NOTE_URI: &str = "@edit-note:";
+pub const REMOVE_ANNOTATION_URI: &str = "@remove-annotation:";
+
+// Static locations are written *page.word*, dynamic ones as a plain offset.
+fn text_location_as_uri(tl: TextLocation) -> String {
+    match tl {
+        TextLocation::Static(page, word) => format!("{}.{}", page, word),
+        TextLocation::Dynamic(offset) => format!("{}", offset),
+    }
+}
+
+fn text_location_from_uri(text: &str) -> Option<TextLocation> {
+    if let Some((page, word)) = text.split_once('.') {
+        Some(TextLocation::Static(page.parse().ok()?, word.parse().ok()?))
+    } else {
+        text.parse().ok().map(TextLocation::Dynamic)
+    }
+}
+
+pub fn selection_as_uri(sel: [TextLocation; 2]) -> String {
+    format!("{}-{}", text_location_as_uri(sel[0]), text_location_as_uri(sel[1]))
+}
+
+pub fn selection_from_uri(text: &str) -> Option<[TextLocation; 2]> {
+    let (start, end) = text.split_once('-')?;
+    Some([text_location_from_uri(start)?, text_location_from_uri(end)?])
+}
+
 pub fn annotations_as_html(annotations: &[Annotation], active_range: Option<(TextLocation, TextLocation)>) -> String {
     let mut buf = "<html>\n\t<head>\n\t\t<title>Annotations</title>\n\t\t\
                    <link rel=\"stylesheet\" type=\"text/css\" href=\"css/annotations.css\"/>\n\t\
@@ -331,10 +444,13 @@ pub fn annotations_as_html(annotations: &[Annotation], active_range: Option<(Tex
             }
             text = format!("<b>{}</b>", text);
         }
+        let sel = selection_as_uri(annot.selection);
+        let actions = format!("<span class=\"actions\"><a href=\"{}{}\">edit</a> · <a href=\"{}{}\">delete</a></span>",
+                              EDIT_NOTE_URI, sel, REMOVE_ANNOTATION_URI, sel);
         if note.is_empty() {
-            buf.push_str(&format!("\t\t<li><a href=\"@{}\">{}</a></li>\n", start.location(), text));
+            buf.push_str(&format!("\t\t<li><a href=\"@{}\">{}</a> {}</li>\n", start.location(), text, actions));
         } else {
-            buf.push_str(&format!("\t\t<li><a href=\"@{}\"><i>{}</i> — {}</a></li>\n", start.location(), note, text));
+            buf.push_str(&format!("\t\t<li><a href=\"@{}\"><i>{}</i> — {}</a> {}</li>\n", start.location(), note, text, actions));
         }
     }
     buf.push_str("\t\t</ul>\n");
@@ -342,17 +458,42 @@ pub fn annotations_as_html(annotations: &[Annotation], active_range: Option<(Tex
     buf
 }
 
-pub fn bookmarks_as_html(bookmarks: &BTreeSet<usize>, index: usize, synthetic: bool) -> String {
+// Each annotation comes with the title of the chapter it belongs to.
+pub fn annotations_as_markdown(title: &str, annotations: &[(String, Annotation)]) -> String {
+    let mut buf = format!("# {}\n", title);
+    let mut current_chapter = None;
+    for (chapter, annot) in annotations {
+        if current_chapter != Some(chapter) {
+            if !chapter.is_empty() {
+                buf.push_str(&format!("\n## {}\n", chapter));
+            }
+            current_chapter = Some(chapter);
+        }
+        buf.push('\n');
+        for line in annot.text.lines() {
+            buf.push_str(&format!("> {}\n", line));
+        }
+        if !annot.note.is_empty() {
+            buf.push_str(&format!("\n{}\n", annot.note));
+        }
+    }
+    buf
+}
+
+pub fn bookmarks_as_html(bookmarks: &BTreeMap<usize, String>, index: usize, synthetic: bool) -> String {
     let mut buf = "<html>\n\t<head>\n\t\t<title>Bookmarks</title>\n\t\t\
                    <link rel=\"stylesheet\" type=\"text/css\" href=\"css/bookmarks.css\"/>\n\t\
                    </head>\n\t<body>\n".to_string();
     buf.push_str("\t\t<ul>\n");
-    for bkm in bookmarks {
+    for (bkm, label) in bookmarks {
         let mut text = if synthetic {
             format!("{:.1}", *bkm as f64 / BYTES_PER_PAGE)
         } else {
             format!("{}", bkm + 1)
         };
+        if !label.is_empty() {
+            text = format!("{} — <i>{}</i>", text, encode_entities(label));
+        }
         if *bkm == index {
             text = format!("<b>{}</b>", text);
         }
@@ -363,6 +504,31 @@ pub fn bookmarks_as_html(bookmarks: &BTreeSet<usize>, index: usize, synthetic: b
     buf
 }
 
+pub fn reading_stats_as_html(sessions: &[(NaiveDateTime, NaiveDateTime, usize)]) -> String {
+    let mut buf = "<html>\n\t<head>\n\t\t<title>Reading Statistics</title>\n\t\t\
+                   <link rel=\"stylesheet\" type=\"text/css\" href=\"css/reading-stats.css\"/>\n\t\
+                   </head>\n\t<body>\n".to_string();
+    let total_time = sessions.iter().fold(Duration::zero(), |acc, (start, end, _)| acc + (*end - *start));
+    let total_turns: usize = sessions.iter().map(|(_, _, turns)| turns).sum();
+    let minutes = total_time.num_minutes();
+    buf.push_str(&format!("\t\t<p>Sessions: {}<br/>\n\t\tTime: {}h {:02}m<br/>\n\t\tPages turned: {}",
+                          sessions.len(), minutes / 60, minutes % 60, total_turns));
+    if minutes > 0 {
+        buf.push_str(&format!("<br/>\n\t\tSpeed: {:.0} pages per hour", 60.0 * total_turns as f32 / minutes as f32));
+    }
+    buf.push_str("</p>\n");
+    buf.push_str("\t\t<ul>\n");
+    for (start, end, turns) in sessions.iter().rev() {
+        let minutes = (*end - *start).num_minutes();
+        buf.push_str(&format!("\t\t<li>{} — {}h {:02}m, {} page{}</li>\n",
+                              start.format("%Y-%m-%d %H:%M"), minutes / 60, minutes % 60,
+                              turns, if *turns != 1 { "s" } else { "" }));
+    }
+    buf.push_str("\t\t</ul>\n");
+    buf.push_str("\t</body>\n</html>");
+    buf
+}
+
 #[inline]
 fn chapter(index: usize, pages_count: usize, toc: &[TocEntry]) -> Option<(&TocEntry, f32, f32)> {
     let mut chap = None;
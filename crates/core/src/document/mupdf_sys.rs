@@ -69,6 +69,7 @@ extern {
     pub fn fz_drop_pixmap(ctx: *mut FzContext, pixmap: *mut FzPixmap);
     pub fn mp_load_page(ctx: *mut FzContext, doc: *mut FzDocument, page_idx: libc::c_int) -> *mut FzPage;
     pub fn fz_drop_page(ctx: *mut FzContext, page: *mut FzPage);
+    pub fn mp_page_label(ctx: *mut FzContext, page: *mut FzPage, buf: *mut libc::c_char, size: libc::c_int) -> *const libc::c_char;
     pub fn fz_bound_page(ctx: *mut FzContext, page: *mut FzPage) -> FzRect;
     pub fn fz_run_page(ctx: *mut FzContext, page: *mut FzPage, dev: *mut FzDevice, mat: FzMatrix, cookie: *mut FzCookie);
     pub fn mp_load_links(ctx: *mut FzContext, page: *mut FzPage) -> *mut FzLink;
@@ -34,6 +34,8 @@ pub struct PdfOpener(Rc<PdfContext>);
 pub struct PdfDocument {
     ctx: Rc<PdfContext>,
     doc: *mut FzDocument,
+    // Number of words of the pages counted so far.
+    word_counts: Vec<usize>,
 }
 
 pub struct PdfPage<'a> {
@@ -68,6 +70,7 @@ impl PdfOpener {
                 Some(PdfDocument {
                     ctx: self.0.clone(),
                     doc,
+                    word_counts: Vec::new(),
                 })
             }
         }
@@ -88,6 +91,7 @@ impl PdfOpener {
                 Some(PdfDocument {
                     ctx: self.0.clone(),
                     doc,
+                    word_counts: Vec::new(),
                 })
             }
         }
@@ -106,6 +110,12 @@ unsafe impl Send for PdfDocument {}
 unsafe impl Sync for PdfDocument {}
 
 impl PdfDocument {
+    // Extracting the text of every page is slow: the counts are kept for the lifetime of the document.
+    fn word_counts(&mut self) -> &[usize] {
+        while self.count_words_step() {}
+        &self.word_counts
+    }
+
     pub fn page(&self, index: usize) -> Option<PdfPage> {
         unsafe {
             let page = mp_load_page(self.ctx.0, self.doc, index as libc::c_int);
@@ -229,6 +239,31 @@ impl Document for PdfDocument {
         self.page(index).and_then(|page| page.links()).map(|links| (links, index))
     }
 
+    fn count_words_step(&mut self) -> bool {
+        let index = self.word_counts.len();
+        if index >= self.pages_count() {
+            return false;
+        }
+        let count = self.page(index).and_then(|page| page.words()).map_or(0, |words| words.len());
+        self.word_counts.push(count);
+        index + 1 < self.pages_count()
+    }
+
+    fn word_count(&mut self) -> Option<usize> {
+        Some(self.word_counts().iter().sum())
+    }
+
+    fn word_offset(&mut self, location: usize) -> Option<usize> {
+        let counts = self.word_counts();
+        Some(counts[..location.min(counts.len())].iter().sum())
+    }
+
+    // MuPDF falls back to the page number when the document doesn't define any labels.
+    fn page_label(&self, location: usize) -> Option<String> {
+        self.page(location).and_then(|page| page.label())
+            .filter(|label| !label.is_empty() && *label != (location + 1).to_string())
+    }
+
     fn title(&self) -> Option<String> {
         self.metadata(FZ_META_INFO_TITLE)
     }
@@ -471,6 +506,18 @@ impl<'a> PdfPage<'a> {
         }
     }
 
+    pub fn label(&self) -> Option<String> {
+        unsafe {
+            let mut buf: [libc::c_char; 64] = [0; 64];
+            let label = mp_page_label(self.ctx.0, self.page, buf.as_mut_ptr(), buf.len() as libc::c_int);
+            if label.is_null() {
+                None
+            } else {
+                Some(CStr::from_ptr(label).to_string_lossy().into_owned())
+            }
+        }
+    }
+
     pub fn dims(&self) -> (f32, f32) {
         unsafe {
             let bounds = fz_bound_page(self.ctx.0, self.page);
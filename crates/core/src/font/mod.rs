@@ -1084,6 +1084,8 @@ pub struct Font {
     // lowercase and uppercase x heights
     pub x_heights: (u32, u32),
     space_codepoint: u32,
+    // The font data, when owned by the font.
+    _data: Option<Vec<u8>>,
 }
 
 impl FontOpener {
@@ -1112,10 +1114,17 @@ impl FontOpener {
             let x_heights = (0, 0);
             let space_codepoint = FT_Get_Char_Index(face, ' ' as libc::c_ulong);
             Ok(Font { lib: self.0.clone(), face, font,
-                      size: 0, dpi: 0, ellipsis, x_heights, space_codepoint })
+                      size: 0, dpi: 0, ellipsis, x_heights, space_codepoint, _data: None })
         }
     }
 
+    // The buffer is kept alive as long as the font, since FreeType doesn't copy it.
+    pub fn open_buffer(&self, buf: Vec<u8>) -> Result<Font, Error> {
+        let mut font = self.open_memory(&buf)?;
+        font._data = Some(buf);
+        Ok(font)
+    }
+
     pub fn open_memory(&self, buf: &[u8]) -> Result<Font, Error> {
         unsafe {
             let mut face = ptr::null_mut();
@@ -1128,7 +1137,7 @@ impl FontOpener {
             let x_heights = (0, 0);
             let space_codepoint = FT_Get_Char_Index(face, ' ' as libc::c_ulong);
             Ok(Font { lib: self.0.clone(), face, font,
-                      size: 0, dpi: 0, ellipsis, x_heights, space_codepoint })
+                      size: 0, dpi: 0, ellipsis, x_heights, space_codepoint, _data: None })
         }
     }
 }
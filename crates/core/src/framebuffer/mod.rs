@@ -16,6 +16,9 @@ pub use self::kobo1::KoboFramebuffer1;
 pub use self::kobo2::KoboFramebuffer2;
 pub use self::image::Pixmap;
 
+// Pixels at least this light are considered part of the page background.
+const BACKGROUND_THRESHOLD: f32 = 200.0;
+
 #[derive(Debug, Copy, Clone)]
 pub struct Display {
     pub dims: (u32, u32),
@@ -137,8 +140,9 @@ pub trait Framebuffer {
         }
     }
 
-    fn draw_framed_pixmap_contrast(&mut self, pixmap: &Pixmap, rect: &Rectangle, pt: Point, exponent: f32, gray: f32) {
-        if (exponent - 1.0).abs() < f32::EPSILON {
+    // When `background` is given, the background pixels are set to it and the darker ones are scaled accordingly.
+    fn draw_framed_pixmap_contrast(&mut self, pixmap: &Pixmap, rect: &Rectangle, pt: Point, exponent: f32, gray: f32, background: Option<f32>) {
+        if (exponent - 1.0).abs() < f32::EPSILON && background.is_none() {
             self.draw_framed_pixmap(pixmap, rect, pt);
             return;
         }
@@ -149,14 +153,21 @@ pub trait Framebuffer {
                 let px = x - rect.min.x + pt.x;
                 let py = y - rect.min.y + pt.y;
                 let raw_color = pixmap.get_pixel(x as u32, y as u32) as f32;
-                let color = if raw_color < gray {
-                    (gray * (raw_color / gray).powf(exponent)) as u8
+                let mut color = if raw_color < gray {
+                    gray * (raw_color / gray).powf(exponent)
                 } else if raw_color > gray {
-                    (gray + rem_gray * ((raw_color - gray) / rem_gray).powf(inv_exponent)) as u8
+                    gray + rem_gray * ((raw_color - gray) / rem_gray).powf(inv_exponent)
                 } else {
-                    gray as u8
+                    gray
                 };
-                self.set_pixel(px as u32, py as u32, color);
+                if let Some(background) = background {
+                    color = if color >= BACKGROUND_THRESHOLD {
+                        background
+                    } else {
+                        color * background / BACKGROUND_THRESHOLD
+                    };
+                }
+                self.set_pixel(px as u32, py as u32, color as u8);
             }
         }
     }
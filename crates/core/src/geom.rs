@@ -94,6 +94,15 @@ pub enum CycleDir {
     Previous,
 }
 
+impl CycleDir {
+    pub fn opposite(self) -> CycleDir {
+        match self {
+            CycleDir::Next => CycleDir::Previous,
+            CycleDir::Previous => CycleDir::Next,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum LinearDir {
     Backward,
@@ -1250,7 +1259,7 @@ impl DivAssign<f32> for Boundary {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Region {
     Corner(DiagDir),
     Strip(Dir),
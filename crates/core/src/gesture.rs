@@ -15,10 +15,12 @@ pub const TAP_JITTER_MM: f32 = 6.0;
 pub const HOLD_JITTER_MM: f32 = 1.5;
 pub const HOLD_DELAY_SHORT: Duration = Duration::from_millis(666);
 pub const HOLD_DELAY_LONG: Duration = Duration::from_millis(1333);
+pub const DOUBLE_TAP_DELAY: Duration = Duration::from_millis(300);
 
 #[derive(Debug, Copy, Clone)]
 pub enum GestureEvent {
     Tap(Point),
+    DoubleTap(Point),
     MultiTap([Point; 2]),
     Swipe {
         dir: Dir,
@@ -82,6 +84,7 @@ impl fmt::Display for GestureEvent {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             GestureEvent::Tap(pt) => write!(f, "Tap {}", pt),
+            GestureEvent::DoubleTap(pt) => write!(f, "Double tap {}", pt),
             GestureEvent::MultiTap(pts) => write!(f, "Multitap {} {}", pts[0], pts[1]),
             GestureEvent::Swipe { dir, .. } => write!(f, "Swipe {}", dir),
             GestureEvent::SlantedSwipe { dir, .. } => write!(f, "SlantedSwipe {}", dir),
@@ -122,6 +125,8 @@ pub fn parse_gesture_events(rx: &Receiver<DeviceEvent>, ty: &Sender<Event>) {
     let segments: Arc<Mutex<Vec<Vec<Point>>>> = Arc::new(Mutex::new(Vec::new()));
     let tap_jitter = mm_to_px(TAP_JITTER_MM, CURRENT_DEVICE.dpi);
     let hold_jitter = mm_to_px(HOLD_JITTER_MM, CURRENT_DEVICE.dpi);
+    // Position and time of the last single tap.
+    let mut last_tap: Option<(Point, f64)> = None;
 
     while let Ok(evt) = rx.recv() {
         ty.send(Event::Device(evt)).ok();
@@ -180,7 +185,7 @@ pub fn parse_gesture_events(rx: &Receiver<DeviceEvent>, ty: &Sender<Event>) {
                     ts.positions.push(position);
                 }
             },
-            DeviceEvent::Finger { status: FingerStatus::Up, position, id, .. } => {
+            DeviceEvent::Finger { status: FingerStatus::Up, position, id, time } => {
                 let mut ct = contacts.lock().unwrap();
                 let mut sg = segments.lock().unwrap();
                 if let Some(mut ts) = ct.remove(&id) {
@@ -192,7 +197,20 @@ pub fn parse_gesture_events(rx: &Receiver<DeviceEvent>, ty: &Sender<Event>) {
                 if ct.is_empty() && !sg.is_empty() {
                     let len = sg.len();
                     if len == 1 {
-                        ty.send(Event::Gesture(interpret_segment(&sg.pop().unwrap(), tap_jitter))).ok();
+                        let ge = interpret_segment(&sg.pop().unwrap(), tap_jitter);
+                        if let GestureEvent::Tap(c) = ge {
+                            // The second tap of a double tap replaces the single tap.
+                            if let Some((pt, t)) = last_tap.take() {
+                                if time - t < DOUBLE_TAP_DELAY.as_secs_f64() && (c - pt).length() < tap_jitter {
+                                    ty.send(Event::Gesture(GestureEvent::DoubleTap(c))).ok();
+                                    continue;
+                                }
+                            }
+                            last_tap = Some((c, time));
+                        } else {
+                            last_tap = None;
+                        }
+                        ty.send(Event::Gesture(ge)).ok();
                     } else if len == 2 {
                         let ge1 = interpret_segment(&sg.pop().unwrap(), tap_jitter);
                         let ge2 = interpret_segment(&sg.pop().unwrap(), tap_jitter);
@@ -2,6 +2,8 @@ use std::char;
 use std::fmt;
 use std::str::FromStr;
 use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::iter::Peekable;
 use std::time::SystemTime;
 use std::num::ParseIntError;
 use std::fs::{self, File, Metadata};
@@ -104,6 +106,78 @@ pub fn trim_non_alphanumeric(text: &str) -> String {
     text.trim_matches(|c: char| !c.is_alphanumeric()).to_string()
 }
 
+/// Turns a word picked in the text into a dictionary query: soft hyphens are removed,
+/// and so are the surrounding punctuation and a trailing footnote marker.
+/// A footnote marker is a superscript digit, or a digit following a punctuation mark,
+/// so that the digits of words like *MP3* are kept.
+pub fn dictionary_query(text: &str) -> String {
+    let text = text.replace('\u{00AD}', "");
+    let mut query = trim_non_alphanumeric(&text);
+    let mut chars = query.chars().rev();
+    if let (Some(last), Some(prev)) = (chars.next(), chars.next()) {
+        let superscript = matches!(last, '¹' | '²' | '³' | '⁰' | '⁴'..='⁹');
+        if last.is_numeric() && ((superscript && !prev.is_numeric()) || !prev.is_alphanumeric()) &&
+           query.chars().any(char::is_alphabetic) {
+            query.pop();
+            query = trim_non_alphanumeric(&query);
+        }
+    }
+    query
+}
+
+/// Whether a word picked in the text ends a sentence. Single letters and a few common
+/// abbreviations followed by a period are assumed not to.
+pub fn ends_sentence(word: &str) -> bool {
+    let word = word.trim_end_matches(['"', '\'', ')', ']', '”', '’', '»']);
+    if word.ends_with(['!', '?', '…']) {
+        return true;
+    }
+    if let Some(stem) = word.strip_suffix('.') {
+        let stem = stem.trim_start_matches(|c: char| !c.is_alphanumeric());
+        return stem.chars().count() > 1 &&
+               !["Mr", "Mrs", "Ms", "Dr", "St", "Jr", "Sr", "vs", "etc", "cf"].contains(&stem);
+    }
+    false
+}
+
+/// Compares two strings, treating the runs of digits as numbers: *vol2* comes before *vol10*.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut chars_a = a.chars().peekable();
+    let mut chars_b = b.chars().peekable();
+    loop {
+        match (chars_a.peek().copied(), chars_b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let digits_a = take_digits(&mut chars_a);
+                let digits_b = take_digits(&mut chars_b);
+                let (n_a, n_b) = (digits_a.trim_start_matches('0'), digits_b.trim_start_matches('0'));
+                let ord = n_a.len().cmp(&n_b.len()).then_with(|| n_a.cmp(n_b));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            },
+            (Some(x), Some(y)) => {
+                let ord = x.to_lowercase().cmp(y.to_lowercase());
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                chars_a.next();
+                chars_b.next();
+            },
+        }
+    }
+}
+
+fn take_digits<I: Iterator<Item=char>>(chars: &mut Peekable<I>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+    digits
+}
+
 pub fn first_n_words(text: &str, n: usize) -> String {
     text.split_whitespace().take(n).collect::<Vec<&str>>().join(" ")
 }
@@ -285,4 +359,33 @@ mod tests {
         assert_eq!(decode_entities("a &#38; b"), "a & b");
         assert_eq!(decode_entities("a &lt; b &gt; c"), "a < b > c");
     }
+
+    #[test]
+    fn test_dictionary_query() {
+        assert_eq!(dictionary_query("“end.”"), "end");
+        assert_eq!(dictionary_query("word.3"), "word");
+        assert_eq!(dictionary_query("word²"), "word");
+        assert_eq!(dictionary_query("hy\u{00AD}phen"), "hyphen");
+        assert_eq!(dictionary_query("1984"), "1984");
+        assert_eq!(dictionary_query("MP3"), "MP3");
+        assert_eq!(dictionary_query("MP3."), "MP3");
+    }
+
+    #[test]
+    fn test_ends_sentence() {
+        assert!(ends_sentence("end."));
+        assert!(ends_sentence("really?”"));
+        assert!(ends_sentence("(stop!)"));
+        assert!(!ends_sentence("Mr."));
+        assert!(!ends_sentence("J."));
+        assert!(!ends_sentence("middle,"));
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp("vol2.epub", "vol10.epub"), Ordering::Less);
+        assert_eq!(natural_cmp("Vol 03.epub", "vol 3.epub"), Ordering::Equal);
+        assert_eq!(natural_cmp("book 12b", "book 12a"), Ordering::Greater);
+        assert_eq!(natural_cmp("book", "book 1"), Ordering::Less);
+    }
 }
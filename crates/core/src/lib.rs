@@ -17,6 +17,7 @@ pub mod library;
 pub mod view;
 pub mod metadata;
 mod symbolic_path;
+mod qrcode;
 pub mod rtc;
 pub mod settings;
 pub mod font;
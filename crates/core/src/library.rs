@@ -13,7 +13,7 @@ use crate::metadata::{Info, ReaderInfo, FileInfo, BookQuery, SimpleStatus, SortM
 use crate::metadata::{sort, sorter, extract_metadata_from_document};
 use crate::settings::{LibraryMode, ImportSettings};
 use crate::document::file_kind;
-use crate::helpers::{Fingerprint, Fp, save_json, load_json, IsHidden};
+use crate::helpers::{Fingerprint, Fp, save_json, load_json, natural_cmp, IsHidden};
 
 pub const METADATA_FILENAME: &str = ".metadata.json";
 pub const FAT32_EPOCH_FILENAME: &str = ".fat32-epoch";
@@ -216,6 +216,21 @@ impl Library {
         (files, dirs)
     }
 
+    // The next file of the directory of `path`, the file names being compared naturally.
+    pub fn next_sibling<P: AsRef<Path>>(&self, path: P) -> Option<Info> {
+        let path = path.as_ref();
+        let parent = path.parent()?;
+        let (mut files, _) = self.list(self.home.join(parent), None, false);
+        files.retain(|info| info.file.path.parent() == Some(parent));
+        files.sort_by(|a, b| {
+            let name_a = a.file.path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+            let name_b = b.file.path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+            natural_cmp(&name_a, &name_b)
+        });
+        let index = files.iter().position(|info| info.file.path == path)?;
+        files.into_iter().nth(index + 1)
+    }
+
     pub fn import(&mut self, settings: &ImportSettings) {
         if self.mode == LibraryMode::Filesystem {
             return;
@@ -6,8 +6,9 @@ use std::path::{Path, PathBuf};
 use std::cmp::Ordering;
 use regex::Regex;
 use chrono::{NaiveDateTime, Local};
-use fxhash::FxHashMap;
-use serde::{Serialize, Deserialize};
+use fxhash::{FxHashMap, FxHashSet};
+use serde::{Serialize, Deserialize, Deserializer};
+use serde::de::{Visitor, SeqAccess, MapAccess};
 use lazy_static::lazy_static;
 use titlecase::titlecase;
 use crate::geom::Point;
@@ -21,6 +22,10 @@ use crate::helpers::datetime_format;
 
 pub const DEFAULT_CONTRAST_EXPONENT: f32 = 1.0;
 pub const DEFAULT_CONTRAST_GRAY: f32 = 224.0;
+pub const HIGHLIGHT_DRIFT: u8 = 0x22;
+pub const ANNOTATION_DRIFT: u8 = 0x44;
+pub const HIGHLIGHT_COLORS: [(&str, u8); 4] = [("Light", 0x22), ("Medium", 0x44),
+                                               ("Dark", 0x66), ("Darker", 0x88)];
 
 pub type Metadata = Vec<Info>;
 
@@ -90,6 +95,8 @@ pub struct Annotation {
     pub selection: [TextLocation; 2],
     #[serde(with = "datetime_format")]
     pub modified: NaiveDateTime,
+    // The amount by which the highlighted region is darkened.
+    pub color: u8,
 }
 
 impl Default for Annotation {
@@ -99,6 +106,7 @@ impl Default for Annotation {
             text: String::new(),
             selection: [TextLocation::Dynamic(0), TextLocation::Dynamic(1)],
             modified: Local::now().naive_local(),
+            color: HIGHLIGHT_DRIFT,
         }
     }
 }
@@ -115,6 +123,18 @@ impl Margin {
     pub fn new(top: f32, right: f32, bottom: f32, left: f32) -> Margin {
         Margin { top, right, bottom, left }
     }
+
+    pub fn average(margins: &[Margin]) -> Option<Margin> {
+        if margins.is_empty() {
+            return None;
+        }
+        let n = margins.len() as f32;
+        let sum = margins.iter().fold(Margin::default(), |acc, m| {
+            Margin::new(acc.top + m.top, acc.right + m.right,
+                        acc.bottom + m.bottom, acc.left + m.left)
+        });
+        Some(Margin::new(sum.top / n, sum.right / n, sum.bottom / n, sum.left / n))
+    }
 }
 
 impl Default for Margin {
@@ -160,6 +180,15 @@ impl CroppingMargins {
         }
     }
 
+    // Builds even/odd margins from the content boxes detected on sample pages of each parity.
+    pub fn from_samples(even: &[Margin], odd: &[Margin]) -> Option<CroppingMargins> {
+        match (Margin::average(even), Margin::average(odd)) {
+            (Some(e), Some(o)) => Some(CroppingMargins::EvenOdd([e, o])),
+            (Some(m), None) | (None, Some(m)) => Some(CroppingMargins::Any(m)),
+            (None, None) => None,
+        }
+    }
+
     pub fn is_split(&self) -> bool {
         !matches!(*self, CroppingMargins::Any(..))
     }
@@ -174,6 +203,22 @@ pub enum TextAlign {
     Center,
 }
 
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReadingDirection {
+    Ltr,
+    Rtl,
+}
+
+// Horizontal placement of a page narrower than the screen, when fitting to page.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PageAlignment {
+    Left,
+    Center,
+    Right,
+}
+
 impl TextAlign {
     pub fn icon_name(&self) -> &str {
         match self {
@@ -207,6 +252,20 @@ pub struct ReaderInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub scroll_mode: Option<ScrollMode>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub spread: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_preserve: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reading_direction: Option<ReadingDirection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_alignment: Option<PageAlignment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publisher_fonts: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale_line_height: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hyphenate: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub page_offset: Option<Point>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rotation: Option<i8>,
@@ -223,6 +282,8 @@ pub struct ReaderInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub font_size: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_size_relative: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub text_align: Option<TextAlign>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub line_height: Option<f32>,
@@ -230,12 +291,26 @@ pub struct ReaderInfo {
     pub contrast_exponent: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub contrast_gray: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_gray: Option<f32>,
+    // The query of the search that was active when the book was closed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_query: Option<String>,
+    // Offsets of the recently left pages, restored when jumping back to them.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub page_offsets: BTreeMap<usize, Point>,
+    // Keys of the page offsets, oldest first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub offset_visits: Vec<usize>,
     #[serde(skip_serializing_if = "BTreeMap::is_empty")]
     pub page_names: BTreeMap<usize, String>,
-    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
-    pub bookmarks: BTreeSet<usize>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", deserialize_with = "deserialize_bookmarks")]
+    pub bookmarks: BTreeMap<usize, String>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub annotations: Vec<Annotation>,
+    // Start, end and number of page turns of each reading session.
+    #[serde(skip_serializing_if = "Vec::is_empty", with = "sessions_format")]
+    pub reading_sessions: Vec<(NaiveDateTime, NaiveDateTime, usize)>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Copy, Clone)]
@@ -264,10 +339,123 @@ impl PartialEq for ZoomMode {
 
 impl Eq for ZoomMode {}
 
+// Bookmarks used to be stored as a set of locations, without labels.
+fn deserialize_bookmarks<'de, D>(deserializer: D) -> Result<BTreeMap<usize, String>, D::Error> where D: Deserializer<'de> {
+    struct BookmarksVisitor;
+
+    impl<'de> Visitor<'de> for BookmarksVisitor {
+        type Value = BTreeMap<usize, String>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a sequence of locations or a map of labeled locations")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error> where A: SeqAccess<'de> {
+            let mut bookmarks = BTreeMap::new();
+            while let Some(location) = seq.next_element::<usize>()? {
+                bookmarks.insert(location, String::new());
+            }
+            Ok(bookmarks)
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error> where A: MapAccess<'de> {
+            let mut bookmarks = BTreeMap::new();
+            while let Some((location, label)) = map.next_entry::<usize, String>()? {
+                bookmarks.insert(location, label);
+            }
+            Ok(bookmarks)
+        }
+    }
+
+    deserializer.deserialize_any(BookmarksVisitor)
+}
+
+// Stores the dates of the reading sessions in the same format as the other dates.
+mod sessions_format {
+    use chrono::NaiveDateTime;
+    use serde::{Serialize, Deserialize, Serializer, Deserializer};
+    use crate::helpers::datetime_format;
+
+    #[derive(Serialize, Deserialize)]
+    struct Session(#[serde(with = "datetime_format")] NaiveDateTime,
+                   #[serde(with = "datetime_format")] NaiveDateTime,
+                   usize);
+
+    pub fn serialize<S>(sessions: &[(NaiveDateTime, NaiveDateTime, usize)], serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.collect_seq(sessions.iter().map(|&(start, end, page_turns)| Session(start, end, page_turns)))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<(NaiveDateTime, NaiveDateTime, usize)>, D::Error> where D: Deserializer<'de> {
+        let sessions = Vec::<Session>::deserialize(deserializer)?;
+        Ok(sessions.into_iter().map(|Session(start, end, page_turns)| (start, end, page_turns)).collect())
+    }
+}
+
+// Sessions separated by less than this number of seconds are merged.
+pub const SESSION_MERGE_DELAY: i64 = 30;
+
 impl ReaderInfo {
     pub fn progress(&self) -> f32 {
         (self.current_page / self.pages_count) as f32
     }
+
+    // Reflowable documents and fixed layouts have their own margins.
+    pub fn margin_width(&self, reflowable: bool) -> Option<i32> {
+        if reflowable {
+            self.margin_width
+        } else {
+            self.screen_margin_width
+        }
+    }
+
+    pub fn record_session(&mut self, start: NaiveDateTime, end: NaiveDateTime, page_turns: usize) {
+        if page_turns == 0 {
+            return;
+        }
+        if let Some(last) = self.reading_sessions.last_mut() {
+            if (start - last.1).num_seconds() < SESSION_MERGE_DELAY {
+                last.1 = end;
+                last.2 += page_turns;
+                return;
+            }
+        }
+        self.reading_sessions.push((start, end, page_turns));
+    }
+
+    pub fn set_margin_width(&mut self, reflowable: bool, width: i32) {
+        if reflowable {
+            self.margin_width = Some(width);
+        } else {
+            self.screen_margin_width = Some(width).filter(|&w| w != 0);
+        }
+    }
+
+    // A relative font size is an offset from the default font size.
+    pub fn font_size(&self, default: f32) -> Option<f32> {
+        self.font_size.map(|font_size| {
+            if self.font_size_relative == Some(true) {
+                default + font_size
+            } else {
+                font_size
+            }
+        })
+    }
+
+    pub fn set_font_size(&mut self, font_size: f32, default: f32) {
+        if self.font_size_relative == Some(true) {
+            self.font_size = Some(font_size - default);
+        } else {
+            self.font_size = Some(font_size);
+        }
+    }
+
+    pub fn set_font_size_relative(&mut self, relative: bool, default: f32) {
+        let font_size = self.font_size(default);
+        self.font_size_relative = Some(relative);
+        if let Some(font_size) = font_size {
+            self.set_font_size(font_size, default);
+        }
+    }
 }
 
 impl Default for ReaderInfo {
@@ -281,6 +469,13 @@ impl Default for ReaderInfo {
             extra_css: None,
             zoom_mode: None,
             scroll_mode: None,
+            spread: None,
+            image_preserve: None,
+            reading_direction: None,
+            page_alignment: None,
+            publisher_fonts: None,
+            scale_line_height: None,
+            hyphenate: None,
             page_offset: None,
             rotation: None,
             cropping_margins: None,
@@ -289,13 +484,19 @@ impl Default for ReaderInfo {
             show_progress_bar: None,
             font_family: None,
             font_size: None,
+            font_size_relative: None,
             text_align: None,
             line_height: None,
             contrast_exponent: None,
             contrast_gray: None,
+            background_gray: None,
+            search_query: None,
+            page_offsets: BTreeMap::new(),
+            offset_visits: Vec::new(),
             page_names: BTreeMap::new(),
-            bookmarks: BTreeSet::new(),
+            bookmarks: BTreeMap::new(),
             annotations: Vec::new(),
+            reading_sessions: Vec::new(),
         }
     }
 }
@@ -369,6 +570,13 @@ impl Info {
         }
     }
 
+    // Whether the framebuffer should be dithered when opening this document.
+    pub fn is_dithered(&self, dithered_kinds: &FxHashSet<String>) -> bool {
+        self.reader.as_ref()
+            .map(|r| r.dithered)
+            .unwrap_or_else(|| dithered_kinds.contains(&self.file.kind))
+    }
+
     pub fn file_stem(&self) -> String {
         self.file.path.file_stem().unwrap().to_string_lossy().into_owned()
     }
@@ -442,6 +650,10 @@ impl Info {
 }
 
 pub fn make_query(text: &str) -> Option<Regex> {
+    make_query_with(text, false, false)
+}
+
+pub fn make_query_with(text: &str, case_sensitive: bool, whole_word: bool) -> Option<Regex> {
     let any = Regex::new(r"^(\.*|\s)$").unwrap();
 
     if any.is_match(text) {
@@ -456,7 +668,12 @@ pub fn make_query(text: &str) -> Option<Regex> {
                    .replace('c', "[cç]")
                    .replace("ae", "(ae|æ)")
                    .replace("oe", "(oe|œ)");
-    Regex::new(&format!("(?i){}", text))
+    let flags = if case_sensitive { "" } else { "(?i)" };
+    if whole_word {
+        Regex::new(&format!(r"{}\b(?:{})\b", flags, text))
+    } else {
+        Regex::new(&format!("{}{}", flags, text))
+    }
           .map_err(|e| eprintln!("Can't create query: {:#}.", e))
           .ok()
 }
@@ -953,3 +1170,131 @@ pub fn file_name_from_info(info: &Info) -> String {
         .replace('!', "")
         .replace(':', "")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dithered_round_trip() {
+        let kinds: FxHashSet<String> = ["cbz".to_string()].into_iter().collect();
+        let mut info = Info {
+            file: FileInfo { kind: "pdf".to_string(), .. Default::default() },
+            .. Default::default()
+        };
+        assert!(!info.is_dithered(&kinds));
+        info.file.kind = "cbz".to_string();
+        assert!(info.is_dithered(&kinds));
+
+        info.file.kind = "pdf".to_string();
+        info.reader = Some(ReaderInfo { dithered: true, .. Default::default() });
+        let json = serde_json::to_string(&info).unwrap();
+        let reopened: Info = serde_json::from_str(&json).unwrap();
+        assert!(reopened.is_dithered(&kinds));
+    }
+
+    #[test]
+    fn margins_are_independent() {
+        let mut r = ReaderInfo::default();
+        r.set_margin_width(true, 8);
+        r.set_margin_width(false, 3);
+        assert_eq!(r.margin_width(true), Some(8));
+        assert_eq!(r.margin_width(false), Some(3));
+
+        r.set_margin_width(false, 0);
+        assert_eq!(r.margin_width(false), None);
+        assert_eq!(r.margin_width(true), Some(8));
+
+        r.set_margin_width(false, 5);
+        r.set_margin_width(true, 2);
+        let json = serde_json::to_string(&r).unwrap();
+        let reopened: ReaderInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(reopened.margin_width(true), Some(2));
+        assert_eq!(reopened.margin_width(false), Some(5));
+    }
+
+    #[test]
+    fn offset_visits_round_trip() {
+        let mut r = ReaderInfo::default();
+        for (location, y) in [(7, 120), (2, 40), (5, 80)] {
+            r.page_offsets.insert(location, pt!(0, y));
+            r.offset_visits.push(location);
+        }
+        let json = serde_json::to_string(&r).unwrap();
+        let reopened: ReaderInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(reopened.offset_visits, vec![7, 2, 5]);
+        assert_eq!(reopened.page_offsets.get(&2), Some(&pt!(0, 40)));
+    }
+
+    #[test]
+    fn reading_direction_round_trip() {
+        let r = ReaderInfo { reading_direction: Some(ReadingDirection::Rtl), .. Default::default() };
+        let json = serde_json::to_string(&r).unwrap();
+        assert!(json.contains(r#""readingDirection":"rtl""#));
+        let reopened: ReaderInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(reopened.reading_direction, Some(ReadingDirection::Rtl));
+        let plain: ReaderInfo = serde_json::from_str(&serde_json::to_string(&ReaderInfo::default()).unwrap()).unwrap();
+        assert_eq!(plain.reading_direction, None);
+    }
+
+    #[test]
+    fn reading_sessions() {
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+        let mut r = ReaderInfo::default();
+        r.record_session(at("2024-03-01 20:00:00"), at("2024-03-01 20:30:00"), 0);
+        assert!(r.reading_sessions.is_empty());
+
+        r.record_session(at("2024-03-01 21:00:00"), at("2024-03-01 21:20:00"), 12);
+        // Resumed right after a suspend.
+        r.record_session(at("2024-03-01 21:20:05"), at("2024-03-01 21:40:00"), 8);
+        assert_eq!(r.reading_sessions, vec![(at("2024-03-01 21:00:00"), at("2024-03-01 21:40:00"), 20)]);
+
+        r.record_session(at("2024-03-02 08:00:00"), at("2024-03-02 08:10:00"), 5);
+        assert_eq!(r.reading_sessions.len(), 2);
+    }
+
+    #[test]
+    fn bookmarks_migration() {
+        let old: ReaderInfo = serde_json::from_str(r#"{"bookmarks": [3, 17]}"#).unwrap();
+        assert_eq!(old.bookmarks.get(&3), Some(&String::new()));
+        assert_eq!(old.bookmarks.get(&17), Some(&String::new()));
+
+        let mut r = ReaderInfo::default();
+        r.bookmarks.insert(42, "The big reveal".to_string());
+        r.bookmarks.insert(7, String::new());
+        let json = serde_json::to_string(&r).unwrap();
+        let reopened: ReaderInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(reopened.bookmarks, r.bookmarks);
+    }
+
+    #[test]
+    fn query_options() {
+        let query = make_query_with("cat", false, true).unwrap();
+        assert!(query.is_match("The Cat sat."));
+        assert!(!query.is_match("Concatenate"));
+        let query = make_query_with("cat", true, false).unwrap();
+        assert!(!query.is_match("The Cat sat."));
+        assert!(query.is_match("concatenate"));
+    }
+
+    #[test]
+    fn annotation_color() {
+        let annot: Annotation = serde_json::from_str(r#"{"selection": [[0, 0], [0, 3]], "modified": "2020-01-01 00:00:00"}"#).unwrap();
+        assert_eq!(annot.color, HIGHLIGHT_DRIFT);
+        let annot = Annotation { color: 0x66, .. annot };
+        let json = serde_json::to_string(&annot).unwrap();
+        let reopened: Annotation = serde_json::from_str(&json).unwrap();
+        assert_eq!(reopened.color, 0x66);
+    }
+
+    #[test]
+    fn relative_font_size() {
+        let mut r = ReaderInfo { font_size: Some(12.0), .. Default::default() };
+        assert_eq!(r.font_size(10.0), Some(12.0));
+        r.set_font_size_relative(true, 10.0);
+        assert_eq!(r.font_size, Some(2.0));
+        assert_eq!(r.font_size(8.0), Some(10.0));
+        r.set_font_size(9.5, 8.0);
+        assert_eq!(r.font_size, Some(1.5));
+    }
+}
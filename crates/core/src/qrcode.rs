@@ -0,0 +1,482 @@
+// A minimal QR code encoder: byte mode, medium error correction level.
+
+use crate::framebuffer::Pixmap;
+use crate::color::BLACK;
+
+// Indexed by version, for the medium error correction level.
+const ECC_CODEWORDS_PER_BLOCK: [usize; 41] = [
+    0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26,
+    26, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+];
+const ERROR_CORRECTION_BLOCKS: [usize; 41] = [
+    0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16,
+    17, 17, 18, 20, 21, 23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49,
+];
+// The format bits of the medium error correction level.
+const ECC_FORMAT_BITS: u32 = 0;
+// Width, in modules, of the light margin around the symbol.
+pub const QUIET_ZONE: usize = 4;
+
+pub struct QrCode {
+    size: usize,
+    modules: Vec<bool>,
+}
+
+impl QrCode {
+    // Returns `None` when the data doesn't fit in the largest version.
+    pub fn encode(data: &[u8]) -> Option<QrCode> {
+        let version = (1..=40).find(|&v| {
+            4 + count_bits(v) + 8 * data.len() <= 8 * data_codewords(v)
+        })?;
+
+        let mut bits = Vec::new();
+        push_bits(&mut bits, 0b0100, 4);
+        push_bits(&mut bits, data.len() as u32, count_bits(version));
+        for &b in data {
+            push_bits(&mut bits, b as u32, 8);
+        }
+
+        let capacity = 8 * data_codewords(version);
+        let terminator = (capacity - bits.len()).min(4);
+        push_bits(&mut bits, 0, terminator);
+        let padding = (8 - bits.len() % 8) % 8;
+        push_bits(&mut bits, 0, padding);
+        let mut codewords = bits.chunks(8)
+                                .map(|c| c.iter().fold(0u8, |acc, &b| (acc << 1) | b as u8))
+                                .collect::<Vec<u8>>();
+        for pad in [0xEC, 0x11].iter().cycle() {
+            if codewords.len() >= data_codewords(version) {
+                break;
+            }
+            codewords.push(*pad);
+        }
+
+        let mut qr = QrCode::with_function_patterns(version);
+        let codewords = interleave_with_ecc(&codewords, version);
+        let is_function = qr.function_modules(version);
+        qr.draw_codewords(&codewords, &is_function);
+
+        let mask = (0..8).min_by_key(|&mask| {
+            let mut candidate = QrCode { size: qr.size, modules: qr.modules.clone() };
+            candidate.apply_mask(mask, &is_function);
+            candidate.draw_format_bits(mask);
+            candidate.penalty()
+        }).unwrap();
+        qr.apply_mask(mask, &is_function);
+        qr.draw_format_bits(mask);
+
+        Some(qr)
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    // Renders the symbol, surrounded by its quiet zone, with square modules of the given side.
+    pub fn to_pixmap(&self, module_size: u32) -> Pixmap {
+        let side = (self.size + 2 * QUIET_ZONE) as u32 * module_size;
+        let mut pixmap = Pixmap::new(side, side);
+        for y in 0..side {
+            for x in 0..side {
+                let mx = (x / module_size) as usize;
+                let my = (y / module_size) as usize;
+                if mx >= QUIET_ZONE && my >= QUIET_ZONE &&
+                   mx < QUIET_ZONE + self.size && my < QUIET_ZONE + self.size &&
+                   self.is_dark(mx - QUIET_ZONE, my - QUIET_ZONE) {
+                    pixmap.data[(y * side + x) as usize] = BLACK;
+                }
+            }
+        }
+        pixmap
+    }
+
+    fn set(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+    }
+
+    fn with_function_patterns(version: usize) -> QrCode {
+        let size = 4 * version + 17;
+        let mut qr = QrCode { size, modules: vec![false; size * size] };
+
+        for i in 0..size {
+            qr.set(6, i, i % 2 == 0);
+            qr.set(i, 6, i % 2 == 0);
+        }
+
+        for &(cx, cy) in &[(3, 3), (size - 4, 3), (3, size - 4)] {
+            for dy in -4i32..=4 {
+                for dx in -4i32..=4 {
+                    let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                    if x >= 0 && y >= 0 && (x as usize) < size && (y as usize) < size {
+                        let dist = dx.abs().max(dy.abs());
+                        qr.set(x as usize, y as usize, dist != 2 && dist != 4);
+                    }
+                }
+            }
+        }
+
+        for (cx, cy) in alignment_centers(version) {
+            for dy in -2i32..=2 {
+                for dx in -2i32..=2 {
+                    qr.set((cx as i32 + dx) as usize, (cy as i32 + dy) as usize,
+                           dx.abs().max(dy.abs()) != 1);
+                }
+            }
+        }
+
+        if version >= 7 {
+            let mut rem = version as u32;
+            for _ in 0..12 {
+                rem = (rem << 1) ^ ((rem >> 11) * 0x1F25);
+            }
+            let bits = (version as u32) << 12 | rem;
+            for i in 0..18 {
+                let dark = (bits >> i) & 1 == 1;
+                let a = size - 11 + i % 3;
+                let b = i / 3;
+                qr.set(a, b, dark);
+                qr.set(b, a, dark);
+            }
+        }
+
+        // The dark module.
+        qr.set(8, size - 8, true);
+
+        qr
+    }
+
+    fn function_modules(&self, version: usize) -> Vec<bool> {
+        let size = self.size;
+        let mut is_function = vec![false; size * size];
+        let mut mark = |x: usize, y: usize| is_function[y * size + x] = true;
+
+        for i in 0..size {
+            mark(6, i);
+            mark(i, 6);
+        }
+
+        // The finder patterns, their separators and the format information areas.
+        for y in 0..9 {
+            for x in 0..9 {
+                mark(x, y);
+            }
+        }
+        for y in 0..9 {
+            for x in size - 8..size {
+                mark(x, y);
+            }
+        }
+        for y in size - 8..size {
+            for x in 0..9 {
+                mark(x, y);
+            }
+        }
+
+        for (cx, cy) in alignment_centers(version) {
+            for y in cy - 2..=cy + 2 {
+                for x in cx - 2..=cx + 2 {
+                    mark(x, y);
+                }
+            }
+        }
+
+        if version >= 7 {
+            for i in 0..18 {
+                mark(size - 11 + i % 3, i / 3);
+                mark(i / 3, size - 11 + i % 3);
+            }
+        }
+
+        is_function
+    }
+
+    fn draw_codewords(&mut self, codewords: &[u8], is_function: &[bool]) {
+        let size = self.size;
+        let mut i = 0;
+        let mut right = size as i32 - 1;
+        while right >= 1 {
+            // Skip the vertical timing pattern.
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vert in 0..size {
+                for j in 0..2 {
+                    let x = (right - j) as usize;
+                    let y = if upward { size - 1 - vert } else { vert };
+                    if !is_function[y * size + x] && i < 8 * codewords.len() {
+                        self.set(x, y, (codewords[i / 8] >> (7 - i % 8)) & 1 == 1);
+                        i += 1;
+                    }
+                }
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u8, is_function: &[bool]) {
+        let size = self.size;
+        for y in 0..size {
+            for x in 0..size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if invert && !is_function[y * size + x] {
+                    self.modules[y * size + x] ^= true;
+                }
+            }
+        }
+    }
+
+    fn draw_format_bits(&mut self, mask: u8) {
+        let bits = format_bits(mask);
+        let bit = |i: usize| (bits >> i) & 1 == 1;
+        let size = self.size;
+
+        for i in 0..6 {
+            self.set(8, i, bit(i));
+        }
+        self.set(8, 7, bit(6));
+        self.set(8, 8, bit(7));
+        self.set(7, 8, bit(8));
+        for i in 9..15 {
+            self.set(14 - i, 8, bit(i));
+        }
+
+        for i in 0..8 {
+            self.set(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set(8, size - 15 + i, bit(i));
+        }
+    }
+
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let mut penalty = 0;
+
+        // Runs of modules of the same color, and finder like patterns.
+        let finder = [true, false, true, true, true, false, true, false, false, false, false];
+        for horizontal in [true, false] {
+            for a in 0..size {
+                let line = (0..size).map(|b| if horizontal { self.is_dark(b, a) } else { self.is_dark(a, b) })
+                                    .collect::<Vec<bool>>();
+                let mut run = 1;
+                for b in 1..=size {
+                    if b < size && line[b] == line[b - 1] {
+                        run += 1;
+                    } else {
+                        if run >= 5 {
+                            penalty += run - 2;
+                        }
+                        run = 1;
+                    }
+                }
+                for window in line.windows(finder.len()) {
+                    if window == finder || window.iter().rev().eq(finder.iter()) {
+                        penalty += 40;
+                    }
+                }
+            }
+        }
+
+        // Blocks of modules of the same color.
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let color = self.is_dark(x, y);
+                if color == self.is_dark(x + 1, y) && color == self.is_dark(x, y + 1) &&
+                   color == self.is_dark(x + 1, y + 1) {
+                    penalty += 3;
+                }
+            }
+        }
+
+        // Imbalance between dark and light modules.
+        let total = size * size;
+        let dark = self.modules.iter().filter(|&&m| m).count();
+        let percent = (100 * dark / total) as i32;
+        penalty += 10 * ((percent - 50).unsigned_abs() as usize / 5);
+
+        penalty
+    }
+}
+
+// Number of bits of the character count indicator in byte mode.
+fn count_bits(version: usize) -> usize {
+    if version <= 9 { 8 } else { 16 }
+}
+
+fn raw_data_modules(version: usize) -> usize {
+    let mut result = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let count = version / 7 + 2;
+        result -= (25 * count - 10) * count - 55;
+        if version >= 7 {
+            result -= 36;
+        }
+    }
+    result
+}
+
+fn data_codewords(version: usize) -> usize {
+    raw_data_modules(version) / 8 - ECC_CODEWORDS_PER_BLOCK[version] * ERROR_CORRECTION_BLOCKS[version]
+}
+
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let step = if version == 32 {
+        26
+    } else {
+        (4 * version + 2 * count + 1) / (2 * count - 2) * 2
+    };
+    let size = 4 * version + 17;
+    let mut positions = (0..count - 1).map(|i| size - 7 - i * step).collect::<Vec<usize>>();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+// The alignment patterns that don't overlap the finder patterns.
+fn alignment_centers(version: usize) -> Vec<(usize, usize)> {
+    let positions = alignment_positions(version);
+    let last = positions.len().saturating_sub(1);
+    let mut centers = Vec::new();
+    for (i, &y) in positions.iter().enumerate() {
+        for (j, &x) in positions.iter().enumerate() {
+            if (i == 0 && (j == 0 || j == last)) || (i == last && j == 0) {
+                continue;
+            }
+            centers.push((x, y));
+        }
+    }
+    centers
+}
+
+fn format_bits(mask: u8) -> u32 {
+    let data = ECC_FORMAT_BITS << 3 | mask as u32;
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    (data << 10 | rem) ^ 0x5412
+}
+
+fn push_bits(bits: &mut Vec<bool>, value: u32, count: usize) {
+    for i in (0..count).rev() {
+        bits.push((value >> i) & 1 == 1);
+    }
+}
+
+// Splits the data codewords into blocks, appends the error correction codewords
+// to each block and interleaves the blocks.
+fn interleave_with_ecc(data: &[u8], version: usize) -> Vec<u8> {
+    let blocks_count = ERROR_CORRECTION_BLOCKS[version];
+    let ecc_len = ECC_CODEWORDS_PER_BLOCK[version];
+    let raw_codewords = raw_data_modules(version) / 8;
+    let short_blocks_count = blocks_count - raw_codewords % blocks_count;
+    let short_block_len = raw_codewords / blocks_count;
+    let divisor = rs_divisor(ecc_len);
+
+    let mut blocks = Vec::with_capacity(blocks_count);
+    let mut start = 0;
+    for i in 0..blocks_count {
+        let len = short_block_len - ecc_len + if i < short_blocks_count { 0 } else { 1 };
+        let block = &data[start..start + len];
+        let ecc = rs_remainder(block, &divisor);
+        start += len;
+        blocks.push((block.to_vec(), ecc));
+    }
+
+    let mut result = Vec::with_capacity(raw_codewords);
+    for i in 0..=short_block_len - ecc_len {
+        for (block, _) in &blocks {
+            if let Some(&b) = block.get(i) {
+                result.push(b);
+            }
+        }
+    }
+    for i in 0..ecc_len {
+        for (_, ecc) in &blocks {
+            result.push(ecc[i]);
+        }
+    }
+    result
+}
+
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u8 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x1D);
+        z ^= ((y >> i) & 1) * x;
+    }
+    z
+}
+
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root: u8 = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    result
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &b in data {
+        let factor = b ^ result.remove(0);
+        result.push(0);
+        for (x, &y) in result.iter_mut().zip(divisor) {
+            *x ^= gf_multiply(y, factor);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_correction() {
+        let data = [32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17];
+        assert_eq!(rs_remainder(&data, &rs_divisor(10)),
+                   vec![196, 35, 39, 119, 235, 215, 231, 226, 93, 23]);
+    }
+
+    #[test]
+    fn test_format_bits() {
+        assert_eq!(format_bits(0), 0b101010000010010);
+        assert_eq!(format_bits(7), 0b100101010100000);
+    }
+
+    #[test]
+    fn test_capacity() {
+        assert_eq!(data_codewords(1), 16);
+        assert_eq!(data_codewords(10), 216);
+        assert_eq!(data_codewords(40), 2334);
+        assert_eq!(QrCode::encode(b"https://example.com").map(|qr| qr.size()), Some(25));
+        assert!(QrCode::encode(&[b'a'; 3000]).is_none());
+    }
+}
@@ -8,8 +8,9 @@ use std::collections::{BTreeMap, HashMap};
 use fxhash::FxHashSet;
 use serde::{Serialize, Deserialize};
 use sys_locale::get_locale;
-use crate::metadata::{SortMethod, TextAlign};
+use crate::metadata::{SortMethod, TextAlign, PageAlignment, DEFAULT_CONTRAST_EXPONENT, DEFAULT_CONTRAST_GRAY};
 use crate::frontlight::LightLevels;
+use crate::geom::LinearDir;
 use crate::color::BLACK;
 use crate::device::CURRENT_DEVICE;
 use crate::unit::mm_to_px;
@@ -116,6 +117,11 @@ pub struct Settings {
     pub date_format: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub external_urls_queue: Option<PathBuf>,
+    pub external_urls_qr_code: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clipboard_file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vocabulary_file: Option<PathBuf>,
     pub max_warmth: f32,
     pub google_translate_server: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -134,11 +140,15 @@ pub struct Settings {
     pub sketch: SketchSettings,
     pub calculator: CalculatorSettings,
     pub battery: BatterySettings,
+    pub dimming: DimmingSettings,
+    pub notification: NotificationSettings,
     pub frontlight_levels: LightLevels,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub themes: Vec<Theme>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub css_styles: Vec<CssStyle>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub contrast_presets: Vec<ContrastPreset>,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -232,6 +242,24 @@ pub struct CssStyle {
     pub css: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ContrastPreset {
+    pub name: String,
+    pub exponent: f32,
+    pub gray: f32,
+}
+
+impl Default for ContrastPreset {
+    fn default() -> Self {
+        ContrastPreset {
+            name: "Unnamed".to_string(),
+            exponent: DEFAULT_CONTRAST_EXPONENT,
+            gray: DEFAULT_CONTRAST_GRAY,
+        }
+    }
+}
+
 impl Default for CssStyle {
     fn default() -> Self {
         CssStyle {
@@ -391,6 +419,9 @@ pub struct RefreshRateSettings {
     #[serde(skip_serializing_if = "HashMap::is_empty")]
     pub by_kind: HashMap<String, RefreshRatePair>,
     pub chapter_start: bool,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub chapter_start_by_kind: HashMap<String, bool>,
+    pub debug_overlay: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -408,19 +439,50 @@ pub struct ProgressBarSettings {
     pub horz_margin: i32,
     pub vert_margin: i32,
     pub show_clock: bool,
+    pub show_time_left: bool,
+    pub show_word_position: bool,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub by_kind: HashMap<String, bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
 pub struct ReaderSettings {
     pub finished: FinishedAction,
+    pub finished_pages_left: usize,
+    pub finished_progress: f32,
     pub south_east_corner: SouthEastCornerAction,
+    pub south_west_corner: SouthWestCornerAction,
+    pub close_icon_corner: CloseIconCorner,
     pub bottom_right_gesture: BottomRightGestureAction,
     pub south_strip: SouthStripAction,
     pub west_strip: WestStripAction,
     pub east_strip: EastStripAction,
+    pub unresolved_link: UnresolvedLinkAction,
+    pub footnote_popups: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clock_format: Option<String>,
+    pub show_time_left: bool,
+    pub chapter_progress_tick: bool,
+    pub chapter_countdown: bool,
+    pub pinned_bars: bool,
+    pub default_search_direction: LinearDir,
+    pub repeat_last_search: bool,
+    pub search_case_sensitive: bool,
+    pub search_whole_word: bool,
+    pub max_search_results: usize,
+    pub incremental_search: bool,
+    pub read_ahead: usize,
+    pub cache_size: usize,
     pub strip_width: f32,
     pub corner_width: f32,
+    pub double_tap_highlight: bool,
+    pub min_scroll_swipe: f32,
+    pub min_page_swipe: f32,
+    pub history_size: usize,
+    pub auto_turn_interval: f32,
+    pub image_preserve_min_size: f32,
+    pub cropping_threshold: u8,
     pub font_path: String,
     pub font_family: String,
     pub font_size: f32,
@@ -432,12 +494,37 @@ pub struct ReaderSettings {
     pub max_margin_width: i32,
     pub line_height: f32,
     pub continuous_fit_to_width: bool,
+    pub page_alignment: PageAlignment,
+    pub zoom_presets: Vec<f32>,
     pub line_height_gradient: f32,
+    pub scale_line_height: bool,
+    pub line_height_scaling: f32,
     pub ignore_document_css: bool,
+    pub publisher_fonts: bool,
     pub dithered_kinds: FxHashSet<String>,
     pub paragraph_breaker: ParagraphBreakerSettings,
     pub refresh_rate: RefreshRateSettings,
     pub progress_bar: ProgressBarSettings,
+    pub selection_words: SelectionWordsSettings,
+}
+
+// Number of words of a selection sent to each lookup, zero meaning the whole selection.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SelectionWordsSettings {
+    pub define: usize,
+    pub wiki: usize,
+    pub search: usize,
+}
+
+impl Default for SelectionWordsSettings {
+    fn default() -> Self {
+        SelectionWordsSettings {
+            define: 5,
+            wiki: 8,
+            search: 5,
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
@@ -454,11 +541,28 @@ pub struct BatterySettings {
     pub power_off: f32,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DimmingSettings {
+    pub enabled: bool,
+    pub step: f32,
+    pub interval: f32,
+    pub floor: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NotificationSettings {
+    pub timeout: f32,
+    pub max_count: usize,
+}
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum FinishedAction {
     Notify,
     Close,
+    NextInSeries,
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
@@ -468,6 +572,21 @@ pub enum SouthEastCornerAction {
     GoToPage,
 }
 
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SouthWestCornerAction {
+    TableOfContents,
+    PreviousPage,
+    Bookmarks,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CloseIconCorner {
+    TopRight,
+    TopLeft,
+}
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum BottomRightGestureAction {
@@ -498,12 +617,22 @@ pub enum WestStripAction {
     None,
 }
 
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnresolvedLinkAction {
+    Notify,
+    Search,
+    None,
+}
+
 impl Default for RefreshRateSettings {
     fn default() -> Self {
         RefreshRateSettings {
             global: RefreshRatePair { regular: 8, inverted: 2 },
             by_kind: HashMap::new(),
             chapter_start: true,
+            chapter_start_by_kind: HashMap::new(),
+            debug_overlay: false,
         }
     }
 }
@@ -516,6 +645,9 @@ impl Default for ProgressBarSettings {
             horz_margin: 20,
             vert_margin: 12,
             show_clock: true,
+            show_time_left: false,
+            show_word_position: false,
+            by_kind: HashMap::new(),
         }
     }
 }
@@ -544,13 +676,39 @@ impl Default for ReaderSettings {
     fn default() -> Self {
         ReaderSettings {
             finished: FinishedAction::Close,
+            finished_pages_left: 0,
+            finished_progress: 1.0,
             south_east_corner: SouthEastCornerAction::GoToPage,
+            south_west_corner: SouthWestCornerAction::TableOfContents,
+            close_icon_corner: CloseIconCorner::TopRight,
             bottom_right_gesture: BottomRightGestureAction::ToggleDithered,
             south_strip: SouthStripAction::ToggleBars,
             west_strip: WestStripAction::PreviousPage,
             east_strip: EastStripAction::NextPage,
+            unresolved_link: UnresolvedLinkAction::Notify,
+            footnote_popups: true,
+            clock_format: None,
+            show_time_left: false,
+            chapter_progress_tick: false,
+            chapter_countdown: false,
+            pinned_bars: false,
+            default_search_direction: LinearDir::Forward,
+            repeat_last_search: true,
+            search_case_sensitive: false,
+            search_whole_word: false,
+            max_search_results: 200,
+            incremental_search: false,
+            read_ahead: 1,
+            cache_size: 24,
             strip_width: 0.6,
             corner_width: 0.4,
+            double_tap_highlight: true,
+            min_scroll_swipe: 24.0,
+            min_page_swipe: 0.0,
+            history_size: 32,
+            auto_turn_interval: 30.0,
+            image_preserve_min_size: 50.0,
+            cropping_threshold: 0xC0,
             font_path: DEFAULT_FONT_PATH.to_string(),
             font_family: DEFAULT_FONT_FAMILY.to_string(),
             font_size: DEFAULT_FONT_SIZE,
@@ -562,12 +720,18 @@ impl Default for ReaderSettings {
             max_margin_width: DEFAULT_MARGIN_WIDTH.saturating_add(2),
             line_height: DEFAULT_LINE_HEIGHT,
             continuous_fit_to_width: true,
+            page_alignment: PageAlignment::Center,
+            zoom_presets: vec![1.25, 1.5, 2.0],
             line_height_gradient: 0.1,
+            scale_line_height: false,
+            line_height_scaling: 0.025,
             ignore_document_css: false,
+            publisher_fonts: false,
             dithered_kinds: ["cbz", "png", "jpg", "jpeg"].iter().map(|k| k.to_string()).collect(),
             paragraph_breaker: ParagraphBreakerSettings::default(),
             refresh_rate: RefreshRateSettings::default(),
             progress_bar: ProgressBarSettings::default(),
+            selection_words: SelectionWordsSettings::default(),
         }
     }
 }
@@ -594,6 +758,26 @@ impl Default for BatterySettings {
     }
 }
 
+impl Default for DimmingSettings {
+    fn default() -> Self {
+        DimmingSettings {
+            enabled: false,
+            step: 5.0,
+            interval: 30.0,
+            floor: 10.0,
+        }
+    }
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings {
+            timeout: 4.0,
+            max_count: 6,
+        }
+    }
+}
+
 impl Default for Settings {
     fn default() -> Self {
         Settings {
@@ -630,6 +814,9 @@ impl Default for Settings {
                 },
             ],
             external_urls_queue: Some(PathBuf::from("bin/article_fetcher/urls.txt")),
+            external_urls_qr_code: false,
+            clipboard_file: Some(PathBuf::from("clipboard.txt")),
+            vocabulary_file: Some(PathBuf::from("vocabulary.jsonl")),
             keyboard_layout: "English".to_string(),
             frontlight: true,
             wifi: false,
@@ -655,6 +842,8 @@ impl Default for Settings {
             sketch: SketchSettings::default(),
             calculator: CalculatorSettings::default(),
             battery: BatterySettings::default(),
+            dimming: DimmingSettings::default(),
+            notification: NotificationSettings::default(),
             frontlight_levels: LightLevels::default(),
             frontlight_presets: Vec::new(),
             max_warmth: 100.0,
@@ -705,6 +894,39 @@ impl Default for Settings {
                     css: "font-family:serif; text-align:%textalign%; font-size:%fontsize%; line-height:%lineheight%;".to_string(),
                 },
             ],
+            contrast_presets: vec![
+                ContrastPreset {
+                    name: "Faint scan".to_string(),
+                    exponent: 2.5,
+                    gray: 224.0,
+                },
+                ContrastPreset {
+                    name: "Bold text".to_string(),
+                    exponent: 1.5,
+                    gray: 192.0,
+                },
+                ContrastPreset {
+                    name: "Photo".to_string(),
+                    exponent: DEFAULT_CONTRAST_EXPONENT,
+                    gray: DEFAULT_CONTRAST_GRAY,
+                },
+            ],
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chapter_start_by_kind_round_trip() {
+        let mut refresh_rate = RefreshRateSettings::default();
+        refresh_rate.chapter_start_by_kind.insert("epub".to_string(), false);
+        let text = toml::to_string(&refresh_rate).unwrap();
+        let refresh_rate: RefreshRateSettings = toml::from_str(&text).unwrap();
+        assert!(refresh_rate.chapter_start);
+        assert_eq!(refresh_rate.chapter_start_by_kind.get("epub"), Some(&false));
+        assert_eq!(refresh_rate.chapter_start_by_kind.get("pdf"), None);
+    }
+}
@@ -40,7 +40,7 @@ use self::navigation_bar::NavigationBar;
 use self::shelf::Shelf;
 use self::bottom_bar::BottomBar;
 use crate::gesture::GestureEvent;
-use crate::geom::{Rectangle, Dir, DiagDir, CycleDir, halves};
+use crate::geom::{Rectangle, Dir, DiagDir, CycleDir, LinearDir, halves};
 use crate::input::{DeviceEvent, ButtonCode, ButtonStatus};
 use crate::device::CURRENT_DEVICE;
 use crate::unit::scale_by_dpi;
@@ -660,6 +660,26 @@ impl Home {
         }
     }
 
+    // Shows the search bar filled with the given query and lists the matching books.
+    fn search_for(&mut self, text: &str, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) -> bool {
+        let query = BookQuery::new(text);
+        if query.is_none() {
+            return false;
+        }
+        self.query = query;
+        self.toggle_search_bar(Some(true), false, hub, rq, context);
+        self.toggle_keyboard(false, false, None, hub, rq, context);
+        if let Some(search_bar) = self.children[self.shelf_index+2].downcast_mut::<SearchBar>() {
+            search_bar.set_text(text, rq, context);
+        }
+        // Render the search bar and its separator.
+        for i in self.shelf_index + 1 ..= self.shelf_index + 2 {
+            rq.add(RenderData::new(self.child(i).id(), *self.child(i).rect(), UpdateMode::Gui));
+        }
+        self.refresh_visibles(true, true, hub, rq, context);
+        true
+    }
+
     fn toggle_search_bar(&mut self, enable: Option<bool>, update: bool, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
         let dpi = CURRENT_DEVICE.dpi;
         let (small_height, big_height) = (scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32,
@@ -1674,19 +1694,14 @@ impl View for Home {
             },
             Event::Select(EntryId::SearchAuthor(ref author)) => {
                 let text = format!("'a {}", author);
-                let query = BookQuery::new(&text);
-                if query.is_some() {
-                    self.query = query;
-                    self.toggle_search_bar(Some(true), false, hub, rq, context);
-                    self.toggle_keyboard(false, false, None, hub, rq, context);
-                    if let Some(search_bar) = self.children[self.shelf_index+2].downcast_mut::<SearchBar>() {
-                        search_bar.set_text(&text, rq, context);
-                    }
-                    // Render the search bar and its separator.
-                    for i in self.shelf_index + 1 ..= self.shelf_index + 2 {
-                        rq.add(RenderData::new(self.child(i).id(), *self.child(i).rect(), UpdateMode::Gui));
-                    }
-                    self.refresh_visibles(true, true, hub, rq, context);
+                self.search_for(&text, hub, rq, context);
+                true
+            },
+            // The search direction of the reader tells from which end to browse the results.
+            Event::SearchLibrary(ref text, dir) => {
+                if self.search_for(text, hub, rq, context) && dir == LinearDir::Backward {
+                    let pages_count = self.pages_count;
+                    self.go_to_page(pages_count.saturating_sub(1), hub, rq, context);
                 }
                 true
             },
@@ -230,6 +230,7 @@ impl View for InputField {
                 false
             },
             Event::Keyboard(kbd_evt) if self.focused => {
+                let text_len = self.text.len();
                 match kbd_evt {
                     KeyboardEvent::Append(c) => {
                         self.text.insert(self.cursor, c);
@@ -260,6 +261,9 @@ impl View for InputField {
                         context.record_input(&self.text, self.view_id);
                     },
                 };
+                if self.text.len() != text_len {
+                    bus.push_back(Event::TextChanged(self.view_id, self.text.clone()));
+                }
                 rq.add(RenderData::no_wait(self.id, self.rect, UpdateMode::Gui));
                 true
             },
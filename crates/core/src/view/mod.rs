@@ -30,6 +30,7 @@ pub mod frontlight;
 pub mod presets_list;
 pub mod preset;
 pub mod theme;
+pub mod typography;
 pub mod menu;
 pub mod menu_entry;
 pub mod clock;
@@ -58,8 +59,8 @@ use downcast_rs::{Downcast, impl_downcast};
 use crate::font::Fonts;
 use crate::document::{Location, TextLocation};
 use crate::settings::{ButtonScheme, FirstColumn, SecondColumn, RotationLock};
-use crate::metadata::{Info, ZoomMode, ScrollMode, SortMethod, TextAlign, SimpleStatus, PageScheme, Margin};
-use crate::geom::{LinearDir, CycleDir, Rectangle, Boundary};
+use crate::metadata::{Info, ZoomMode, ScrollMode, SortMethod, TextAlign, PageAlignment, SimpleStatus, PageScheme, Margin};
+use crate::geom::{Point, LinearDir, CycleDir, Rectangle, Boundary};
 use crate::framebuffer::{Framebuffer, UpdateMode};
 use crate::input::{DeviceEvent, FingerStatus};
 use crate::gesture::GestureEvent;
@@ -131,6 +132,11 @@ pub trait View: Downcast {
         false
     }
 
+    // Views that don't handle double taps receive them as two taps.
+    fn handles_double_tap(&self) -> bool {
+        false
+    }
+
     fn view_id(&self) -> Option<ViewId> {
         None
     }
@@ -152,6 +158,13 @@ impl Debug for Box<dyn View> {
 // A view that wants to render can write to the rendering queue.
 pub fn handle_event(view: &mut dyn View, evt: &Event, hub: &Hub, parent_bus: &mut Bus,
                     rq: &mut RenderQueue, context: &mut Context) -> bool {
+    // The first tap of a double tap was already sent on its own.
+    if let Event::Gesture(GestureEvent::DoubleTap(pt)) = *evt {
+        if !view.handles_double_tap() {
+            return handle_event(view, &Event::Gesture(GestureEvent::Tap(pt)), hub, parent_bus, rq, context);
+        }
+    }
+
     if view.len() > 0 {
         let mut captured = false;
 
@@ -319,6 +332,7 @@ pub enum Event {
     Keyboard(KeyboardEvent),
     Key(KeyKind),
     Open(Box<Info>),
+    OpenNextInSeries(PathBuf),
     OpenHtml(String, Option<String>),
     LoadPixmap(usize),
     Update(UpdateMode),
@@ -344,6 +358,7 @@ pub enum Event {
     Read,
     Download,
     Submit(ViewId, String),
+    TextChanged(ViewId, String),
     Slider(SliderId, f32, FingerStatus),
     ToggleNear(ViewId, Rectangle),
     ToggleInputHistoryMenu(ViewId, Rectangle),
@@ -371,6 +386,10 @@ pub enum Event {
     },
     CheckFetcher(u32),
     EndOfSearch,
+    SearchPreview,
+    SearchPreviewResult(String, Option<(usize, Vec<Boundary>)>),
+    ReleaseTap(Point),
+    SearchLibrary(String, LinearDir),
     Finished,
     ClockTick,
     BatteryTick,
@@ -385,6 +404,7 @@ pub enum Event {
     MightSuspend,
     PrepareSuspend,
     Suspend,
+    Resume,
     Share,
     PrepareShare,
     Validate,
@@ -458,6 +478,8 @@ pub enum ViewId {
     GoToResultsPageInput,
     NamePage,
     NamePageInput,
+    SearchAnnotations,
+    SearchAnnotationsInput,
     EditNote,
     EditNoteInput,
     EditLanguages,
@@ -474,16 +496,24 @@ pub enum ViewId {
     Keyboard,
     AboutDialog,
     ShareDialog,
+    SearchLibraryDialog,
     MarginCropper,
     TopBottomBars,
     TableOfContents,
+    Footnote,
     MessageNotif(Id),
     SubMenu(u8),
     Scrubber,
     ThemeMenu,
     EditThemeMenu,
     ThemeDialog,
+    TypographyDialog,
+    TouchZones,
+    UrlQrCode,
     CssSelectorMenu,
+    JumpHistoryMenu,
+    LabelBookmark,
+    LabelBookmarkInput,
     NameTheme,
     NameThemeInput,
     EditTheme(usize)
@@ -585,44 +615,85 @@ pub enum EntryId {
     SecondColumn(SecondColumn),
     ThumbnailPreviews,
     ApplyCroppings(usize, PageScheme),
+    DetectCroppings,
+    AutoCropMargins(PageScheme),
     RemoveCroppings,
+    RefreshPages,
+    ToggleImagePreserve,
+    ToggleReadingRuler,
+    ToggleReadingDirection,
     SetZoomMode(ZoomMode),
+    SetZoomPreset(usize),
+    CycleZoomPreset,
+    ToggleSpread,
+    SetPageAlignment(PageAlignment),
     SetScrollMode(ScrollMode),
     SetPageName,
     RemovePageName,
     HighlightSelection,
+    CopySelection,
     AnnotateSelection,
     DefineSelection,
     SearchForSelection,
     TranslateSelection,
     WikiSelection,
     AdjustSelection,
+    ExtendToSentence,
+    AddVocabulary,
     Annotations,
+    SearchAnnotations,
+    ExportAnnotations,
     Bookmarks,
+    ExportBookmarks,
+    ImportBookmarks,
+    ReadingStats,
+    RefreshMetadata,
+    WordPosition,
+    ToggleAutoTurn,
+    JumpHistory,
+    LabelBookmark,
+    JumpToHistory(usize),
     RemoveAnnotation([TextLocation; 2]),
     EditAnnotationNote([TextLocation; 2]),
+    ToggleTocEntry(usize),
     RemoveAnnotationNote([TextLocation; 2]),
+    SetAnnotationColor([TextLocation; 2], u8),
     GoTo(usize),
     GoToSelectedPageName,
     SearchDirection(LinearDir),
+    ToggleSearchCaseSensitive,
+    ToggleSearchWholeWord,
     SetButtonScheme(ButtonScheme),
     SetFontFamily(String),
+    SwapFontFamily,
     SetFontSize(i32),
+    ToggleRelativeFontSize,
     SetTextAlign(TextAlign),
     SetMarginWidth(i32),
     SetLineHeight(i32),
     SetContrastExponent(i32),
     SetContrastGray(i32),
+    SetBackgroundGray(Option<u8>),
+    ApplyContrastPreset(usize),
     ResetToDefaults,
     ApplyTheme(usize),
     RenameTheme(usize),
     DeleteTheme(usize),
     OverwriteTheme(usize),
     SaveTheme,
+    ShowTypography,
+    TogglePublisherFonts,
+    ToggleHyphenation,
+    ToggleScaledLineHeight,
+    NextVisibleAnnotation,
+    PreviousVisibleAnnotation,
+    EditTouchZones,
     SetCssTweak(usize),
-    SetCssTweakEx(String, usize),
+    SetCssTweakEx(String, usize, usize),
+    RepeatCssTweak,
     UndoLastCssTweak,
     UndoAllCssTweaks,
+    RestoreCssTweaks,
     ShowCssTweaks,
     SetRotationLock(Option<RotationLock>),
     SetSearchTarget(Option<String>),
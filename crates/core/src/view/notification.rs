@@ -12,8 +12,6 @@ use crate::input::DeviceEvent;
 use crate::unit::scale_by_dpi;
 use crate::context::Context;
 
-const NOTIFICATION_CLOSE_DELAY: Duration = Duration::from_secs(4);
-
 pub struct Notification {
     id: Id,
     rect: Rectangle,
@@ -30,9 +28,10 @@ impl Notification {
         let view_id = ViewId::MessageNotif(id);
         let hub2 = hub.clone();
         let index = context.notification_index;
+        let delay = Duration::from_secs_f32(context.settings.notification.timeout.max(0.0));
 
         thread::spawn(move || {
-            thread::sleep(NOTIFICATION_CLOSE_DELAY);
+            thread::sleep(delay);
             hub2.send(Event::Close(view_id)).ok();
         });
 
@@ -76,6 +75,23 @@ impl Notification {
     }
 }
 
+// Pushes a notification onto `children`, closing the oldest ones beyond the configured limit.
+pub fn push_notification(children: &mut Vec<Box<dyn View>>, notif: Notification, rq: &mut RenderQueue, context: &Context) {
+    let max_count = context.settings.notification.max_count.max(1);
+    let mut count = children.iter().filter(|child| child.is::<Notification>()).count();
+
+    while count >= max_count {
+        if let Some(index) = children.iter().position(|child| child.is::<Notification>()) {
+            let rect = *children[index].rect();
+            children.remove(index);
+            rq.add(RenderData::expose(rect, UpdateMode::Gui));
+        }
+        count -= 1;
+    }
+
+    children.push(Box::new(notif) as Box<dyn View>);
+}
+
 impl View for Notification {
     fn handle_event(&mut self, evt: &Event, _hub: &Hub, _bus: &mut Bus, _rq: &mut RenderQueue, _context: &mut Context) -> bool {
         match *evt {
@@ -15,6 +15,8 @@ pub struct PageLabel {
     current_page: usize,
     pages_count: usize,
     synthetic: bool,
+    label: Option<String>,
+    time_left: Option<String>,
 }
 
 impl PageLabel {
@@ -26,6 +28,8 @@ impl PageLabel {
             current_page,
             pages_count,
             synthetic,
+            label: None,
+            time_left: None,
         }
     }
 
@@ -44,6 +48,20 @@ impl PageLabel {
         }
     }
 
+    pub fn update_label(&mut self, label: Option<String>, rq: &mut RenderQueue) {
+        if self.label != label {
+            self.label = label;
+            rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+        }
+    }
+
+    pub fn update_time_left(&mut self, time_left: Option<String>, rq: &mut RenderQueue) {
+        if self.time_left != time_left {
+            self.time_left = time_left;
+            rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+        }
+    }
+
     pub fn text(&self, size: u8) -> String {
         if self.pages_count == 0 {
             return "No pages".to_string();
@@ -62,12 +80,19 @@ impl PageLabel {
         } else {
             format!("{:.0}", percent.floor())
         };
-        match size {
-            0 => format!("Page {1:.0$} of {2:.0} ({3}%)", precision, current_page, pages_count, percent),
-            1 => format!("p {1:.0$} / {2:.0} ({3}%)", precision, current_page, pages_count, percent),
-            2 => format!("{1:.0$}/{2:.0} {3}%", precision, current_page, pages_count, percent),
-            3 => format!("p{1:.0$} {2}%", precision, current_page, percent),
+        let current_page = self.label.clone()
+                               .unwrap_or_else(|| format!("{:.1$}", current_page, precision));
+        let text = match size {
+            0 => format!("Page {} of {:.0} ({}%)", current_page, pages_count, percent),
+            1 => format!("p {} / {:.0} ({}%)", current_page, pages_count, percent),
+            2 => format!("{}/{:.0} {}%", current_page, pages_count, percent),
+            3 => format!("p{} {}%", current_page, percent),
             _ => format!("{}%", percent),
+        };
+        match self.time_left {
+            Some(ref time_left) if size == 0 => format!("{} · {} left", text, time_left),
+            Some(ref time_left) if size <= 2 => format!("{} · {}", text, time_left),
+            _ => text,
         }
     }
 }
@@ -19,6 +19,26 @@ pub struct BottomBar {
     children: Vec<Box<dyn View>>,
     is_prev_disabled: bool,
     is_next_disabled: bool,
+    rtl: bool,
+}
+
+// The icon of the neighbor slot at `rect`, or a filler when there's no neighbor in that direction.
+fn neighbor_view(rect: Rectangle, dir: CycleDir, enabled: bool, rtl: bool) -> Box<dyn View> {
+    if !enabled {
+        return Box::new(Filler::new(rect, WHITE)) as Box<dyn View>;
+    }
+    let name = if (dir == CycleDir::Next) != rtl { "arrow-right" } else { "arrow-left" };
+    Box::new(Icon::new(name, rect, Event::Page(dir))) as Box<dyn View>
+}
+
+// The directions and states of the left and right neighbor slots.
+// Right-to-left documents have the next page on the left.
+fn neighbor_slots(rtl: bool, is_prev_disabled: bool, is_next_disabled: bool) -> (CycleDir, CycleDir, bool, bool) {
+    if rtl {
+        (CycleDir::Next, CycleDir::Previous, !is_next_disabled, !is_prev_disabled)
+    } else {
+        (CycleDir::Previous, CycleDir::Next, !is_prev_disabled, !is_next_disabled)
+    }
 }
 
 impl BottomBar {
@@ -28,18 +48,10 @@ impl BottomBar {
         let side = rect.height() as i32;
         let is_prev_disabled = neighbors.previous_page.is_none();
         let is_next_disabled = neighbors.next_page.is_none();
+        let (left_dir, right_dir, left_enabled, right_enabled) = neighbor_slots(false, is_prev_disabled, is_next_disabled);
 
-        let prev_rect = rect![rect.min, rect.min + side];
-
-        if is_prev_disabled {
-            let prev_filler = Filler::new(prev_rect, WHITE);
-            children.push(Box::new(prev_filler) as Box<dyn View>);
-        } else {
-            let prev_icon = Icon::new("arrow-left",
-                                      prev_rect,
-                                      Event::Page(CycleDir::Previous));
-            children.push(Box::new(prev_icon) as Box<dyn View>);
-        }
+        let left_rect = rect![rect.min, rect.min + side];
+        children.push(neighbor_view(left_rect, left_dir, left_enabled, false));
 
         let page_width = 2 * (rect.width() as i32 - 2 * side) / 5;
         let chapter_width = (rect.width() as i32 - 2 * side) - page_width;
@@ -61,17 +73,8 @@ impl BottomBar {
                                         synthetic);
         children.push(Box::new(page_label) as Box<dyn View>);
 
-        let next_rect = rect![rect.max - side, rect.max];
-
-        if is_next_disabled {
-            let next_filler = Filler::new(next_rect, WHITE);
-            children.push(Box::new(next_filler) as Box<dyn View>);
-        } else {
-            let next_icon = Icon::new("arrow-right",
-                                      rect![rect.max - side, rect.max],
-                                      Event::Page(CycleDir::Next));
-            children.push(Box::new(next_icon) as Box<dyn View>);
-        }
+        let right_rect = rect![rect.max - side, rect.max];
+        children.push(neighbor_view(right_rect, right_dir, right_enabled, false));
 
         BottomBar {
             id,
@@ -79,9 +82,24 @@ impl BottomBar {
             children,
             is_prev_disabled,
             is_next_disabled,
+            rtl: false,
         }
     }
 
+    pub fn set_rtl(&mut self, rtl: bool, rq: &mut RenderQueue) {
+        if self.rtl == rtl {
+            return;
+        }
+        self.rtl = rtl;
+        let (left_dir, right_dir, left_enabled, right_enabled) = neighbor_slots(rtl, self.is_prev_disabled, self.is_next_disabled);
+        let last = self.len() - 1;
+        let (left_rect, right_rect) = (*self.child(0).rect(), *self.child(last).rect());
+        self.children[0] = neighbor_view(left_rect, left_dir, left_enabled, rtl);
+        self.children[last] = neighbor_view(right_rect, right_dir, right_enabled, rtl);
+        rq.add(RenderData::new(self.id, left_rect, UpdateMode::Gui));
+        rq.add(RenderData::new(self.id, right_rect, UpdateMode::Gui));
+    }
+
     pub fn update_chapter_label(&mut self, title: String, progress: f32, rq: &mut RenderQueue) {
         let chapter_label = self.child_mut(1).downcast_mut::<ChapterLabel>().unwrap();
         chapter_label.update(title, progress, rq);
@@ -92,21 +110,23 @@ impl BottomBar {
         page_label.update(current_page, pages_count, rq);
     }
 
+    pub fn update_printed_label(&mut self, label: Option<String>, rq: &mut RenderQueue) {
+        let page_label = self.child_mut(2).downcast_mut::<PageLabel>().unwrap();
+        page_label.update_label(label, rq);
+    }
+
+    pub fn update_time_left(&mut self, time_left: Option<String>, rq: &mut RenderQueue) {
+        let page_label = self.child_mut(2).downcast_mut::<PageLabel>().unwrap();
+        page_label.update_time_left(time_left, rq);
+    }
+
     pub fn update_icons(&mut self, neighbors: &Neighbors, rq: &mut RenderQueue) {
         let is_prev_disabled = neighbors.previous_page.is_none();
 
         if self.is_prev_disabled != is_prev_disabled {
-            let index = 0;
+            let index = if self.rtl { self.len() - 1 } else { 0 };
             let prev_rect = *self.child(index).rect();
-            if is_prev_disabled {
-                let prev_filler = Filler::new(prev_rect, WHITE);
-                self.children[index] = Box::new(prev_filler) as Box<dyn View>;
-            } else {
-                let prev_icon = Icon::new("arrow-left",
-                                          prev_rect,
-                                          Event::Page(CycleDir::Previous));
-                self.children[index] = Box::new(prev_icon) as Box<dyn View>;
-            }
+            self.children[index] = neighbor_view(prev_rect, CycleDir::Previous, !is_prev_disabled, self.rtl);
             self.is_prev_disabled = is_prev_disabled;
             rq.add(RenderData::new(self.id, prev_rect, UpdateMode::Gui));
         }
@@ -114,17 +134,9 @@ impl BottomBar {
         let is_next_disabled = neighbors.next_page.is_none();
 
         if self.is_next_disabled != is_next_disabled {
-            let index = self.len() - 1;
+            let index = if self.rtl { 0 } else { self.len() - 1 };
             let next_rect = *self.child(index).rect();
-            if is_next_disabled {
-                let next_filler = Filler::new(next_rect, WHITE);
-                self.children[index] = Box::new(next_filler) as Box<dyn View>;
-            } else {
-                let next_icon = Icon::new("arrow-right",
-                                          next_rect,
-                                          Event::Page(CycleDir::Next));
-                self.children[index] = Box::new(next_icon) as Box<dyn View>;
-            }
+            self.children[index] = neighbor_view(next_rect, CycleDir::Next, !is_next_disabled, self.rtl);
             self.is_next_disabled = is_next_disabled;
             rq.add(RenderData::new(self.id, next_rect, UpdateMode::Gui));
         }
@@ -48,7 +48,8 @@ impl ChapterLabel {
 impl View for ChapterLabel {
     fn handle_event(&mut self, evt: &Event, _hub: &Hub, bus: &mut Bus, _rq: &mut RenderQueue, _context: &mut Context) -> bool {
         match *evt {
-            Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) => {
+            Event::Gesture(GestureEvent::Tap(center)) |
+            Event::Gesture(GestureEvent::HoldFingerShort(center, _)) if self.rect.includes(center) => {
                 bus.push_back(Event::Show(ViewId::TableOfContents));
                 true
             },
@@ -0,0 +1,127 @@
+use crate::device::CURRENT_DEVICE;
+use crate::framebuffer::{Framebuffer, UpdateMode};
+use crate::geom::{Rectangle, Point, CornerSpec, BorderSpec};
+use crate::font::{Fonts, RenderPlan, font_from_style, NORMAL_STYLE};
+use crate::color::{BLACK, WHITE, TEXT_NORMAL};
+use crate::view::{View, Event, Hub, Bus, Id, ID_FEEDER, RenderQueue, RenderData, ViewId};
+use crate::view::{THICKNESS_LARGE, BORDER_RADIUS_MEDIUM};
+use crate::gesture::GestureEvent;
+use crate::unit::scale_by_dpi;
+use crate::context::Context;
+
+pub struct Footnote {
+    id: Id,
+    rect: Rectangle,
+    children: Vec<Box<dyn View>>,
+    plans: Vec<RenderPlan>,
+    line_height: i32,
+    padding: i32,
+}
+
+impl Footnote {
+    // The popup is placed below `anchor` when it lies in the upper half of `rect`, above otherwise.
+    pub fn new(rect: Rectangle, anchor: Point, text: &str, rq: &mut RenderQueue, context: &mut Context) -> Footnote {
+        let id = ID_FEEDER.next();
+        let dpi = CURRENT_DEVICE.dpi;
+        let font = font_from_style(&mut context.fonts, &NORMAL_STYLE, dpi);
+        let padding = font.em() as i32;
+        let line_height = font.line_height();
+        let max_width = rect.width() as i32 - 4 * padding;
+        let max_lines = ((rect.height() as i32 / 2 - 2 * padding) / line_height).max(1) as usize;
+
+        let mut plans = Vec::new();
+        let mut plan = font.plan(text, None, None);
+
+        while plan.width > max_width && plans.len() + 1 < max_lines {
+            let (index, usable_width) = font.cut_point(&plan, max_width);
+            let mut next_plan = plan.split_off(index, usable_width);
+            font.trim_left(&mut next_plan);
+            plans.push(plan);
+            plan = next_plan;
+        }
+
+        font.crop_right(&mut plan, max_width);
+        plans.push(plan);
+
+        let width = plans.iter().map(|p| p.width).max().unwrap_or(0) + 2 * padding;
+        let height = plans.len() as i32 * line_height + 2 * padding;
+        let x_min = rect.min.x + (rect.width() as i32 - width) / 2;
+        let y_min = if anchor.y < rect.min.y + rect.height() as i32 / 2 {
+            (anchor.y + padding).min(rect.max.y - height - padding)
+        } else {
+            (anchor.y - padding - height).max(rect.min.y + padding)
+        };
+
+        let rect = rect![x_min, y_min, x_min + width, y_min + height];
+        rq.add(RenderData::new(id, rect, UpdateMode::Gui));
+
+        Footnote {
+            id,
+            rect,
+            children: Vec::new(),
+            plans,
+            line_height,
+            padding,
+        }
+    }
+}
+
+impl View for Footnote {
+    fn handle_event(&mut self, evt: &Event, _hub: &Hub, bus: &mut Bus, _rq: &mut RenderQueue, _context: &mut Context) -> bool {
+        match *evt {
+            Event::Gesture(GestureEvent::Tap(..)) => {
+                bus.push_back(Event::Close(ViewId::Footnote));
+                true
+            },
+            Event::Gesture(GestureEvent::Swipe { start, .. }) if self.rect.includes(start) => true,
+            _ => false,
+        }
+    }
+
+    fn render(&self, fb: &mut dyn Framebuffer, _rect: Rectangle, fonts: &mut Fonts) {
+        let dpi = CURRENT_DEVICE.dpi;
+
+        let border_radius = scale_by_dpi(BORDER_RADIUS_MEDIUM, dpi) as i32;
+        let border_thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as u16;
+
+        fb.draw_rounded_rectangle_with_border(&self.rect,
+                                              &CornerSpec::Uniform(border_radius),
+                                              &BorderSpec { thickness: border_thickness,
+                                                            color: BLACK },
+                                              &WHITE);
+
+        let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+        let x_height = font.x_heights.0 as i32;
+        let mut pt = pt!(self.rect.min.x + self.padding,
+                         self.rect.min.y + self.padding + (self.line_height + x_height) / 2);
+
+        for plan in &self.plans {
+            font.render(fb, TEXT_NORMAL[1], plan, pt);
+            pt.y += self.line_height;
+        }
+    }
+
+    fn rect(&self) -> &Rectangle {
+        &self.rect
+    }
+
+    fn rect_mut(&mut self) -> &mut Rectangle {
+        &mut self.rect
+    }
+
+    fn children(&self) -> &Vec<Box<dyn View>> {
+        &self.children
+    }
+
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+        &mut self.children
+    }
+
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    fn view_id(&self) -> Option<ViewId> {
+        Some(ViewId::Footnote)
+    }
+}
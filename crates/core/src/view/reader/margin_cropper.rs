@@ -12,6 +12,45 @@ use crate::device::CURRENT_DEVICE;
 use crate::context::Context;
 
 pub const BUTTON_DIAMETER: f32 = 30.0;
+// Rows and columns with fewer ink pixels than this fraction are treated as noise.
+const CONTENT_MIN_RATIO: f32 = 0.005;
+// Extra room kept around the detected content, relative to the page dimensions.
+const CONTENT_PADDING: f32 = 0.01;
+
+// Returns the margins around the content box of the given page rendering.
+// Pixels darker than `threshold` are considered ink.
+pub fn detect_margin(pixmap: &Pixmap, threshold: u8) -> Option<Margin> {
+    let (width, height) = (pixmap.width as usize, pixmap.height as usize);
+    if width == 0 || height == 0 || pixmap.data.len() < width * height {
+        return None;
+    }
+
+    let mut rows = vec![0usize; height];
+    let mut cols = vec![0usize; width];
+
+    for (y, row) in rows.iter_mut().enumerate() {
+        for (x, &c) in pixmap.data[y * width..(y + 1) * width].iter().enumerate() {
+            if c < threshold {
+                *row += 1;
+                cols[x] += 1;
+            }
+        }
+    }
+
+    let min_row = ((CONTENT_MIN_RATIO * width as f32) as usize).max(1);
+    let min_col = ((CONTENT_MIN_RATIO * height as f32) as usize).max(1);
+    let top = rows.iter().position(|&n| n >= min_row)?;
+    let bottom = rows.iter().rposition(|&n| n >= min_row)?;
+    let left = cols.iter().position(|&n| n >= min_col)?;
+    let right = cols.iter().rposition(|&n| n >= min_col)?;
+
+    let w = width as f32;
+    let h = height as f32;
+    Some(Margin::new((top as f32 / h - CONTENT_PADDING).max(0.0),
+                     ((w - 1.0 - right as f32) / w - CONTENT_PADDING).max(0.0),
+                     ((h - 1.0 - bottom as f32) / h - CONTENT_PADDING).max(0.0),
+                     (left as f32 / w - CONTENT_PADDING).max(0.0)))
+}
 
 pub struct MarginCropper {
     id: Id,
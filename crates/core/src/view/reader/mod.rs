@@ -5,23 +5,30 @@ mod results_bar;
 mod margin_cropper;
 mod chapter_label;
 mod results_label;
+mod footnote;
+mod touch_zones;
+mod url_qr_code;
 
 use std::thread;
 use std::sync::{Arc, Mutex};
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering as AtomicOrdering;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::io::{self, SeekFrom};
 use std::io::prelude::*;
-use std::fs::OpenOptions;
-use std::collections::{VecDeque, BTreeMap};
+use std::fs::{self, OpenOptions};
+use std::collections::{VecDeque, BTreeMap, BTreeSet};
 use std::cell::{RefCell, Ref};
 use std::mem::drop;
+use std::time::{Duration, Instant};
 use fxhash::{FxHashMap, FxHashSet};
-use chrono::Local;
+use chrono::{Local, NaiveDateTime};
+use serde_json::json;
 use regex::Regex;
 use septem::prelude::*;
 use septem::{Roman, Digit};
 use rand_core::RngCore;
+use paragraph_breaker::INFINITE_PENALTY;
 use crate::input::{DeviceEvent, FingerStatus, ButtonCode, ButtonStatus};
 use crate::framebuffer::{Framebuffer, UpdateMode, Pixmap};
 use crate::view::{View, Event, AppCmd, Hub, Bus, RenderQueue, RenderData};
@@ -29,15 +36,18 @@ use crate::view::{ViewId, Id, ID_FEEDER, EntryKind, EntryId, SliderId};
 use crate::view::{SMALL_BAR_HEIGHT, BIG_BAR_HEIGHT, THICKNESS_MEDIUM};
 use crate::unit::{scale_by_dpi, mm_to_px};
 use crate::device::CURRENT_DEVICE;
-use crate::helpers::{AsciiExtension, first_n_words, trim_non_alphanumeric, encode_entities, safe_slice};
-use crate::font::{Fonts, font_from_style, SMALL_STYLE};
+use crate::helpers::{AsciiExtension, load_json, save_json, ends_sentence, first_n_words, trim_non_alphanumeric, dictionary_query, encode_entities, safe_slice};
+use crate::font::{Fonts, font_from_style, NORMAL_STYLE, SMALL_STYLE};
 use crate::font::family_names;
-use self::margin_cropper::{MarginCropper, BUTTON_DIAMETER};
+use self::margin_cropper::{MarginCropper, BUTTON_DIAMETER, detect_margin};
 use super::top_bar::TopBar;
 use self::tool_bar::ToolBar;
 use self::scrubber::Scrubber;
 use self::bottom_bar::BottomBar;
 use self::results_bar::ResultsBar;
+use self::footnote::Footnote;
+use self::touch_zones::TouchZones;
+use self::url_qr_code::UrlQrCode;
 use crate::view::common::{locate, rlocate, locate_by_id, get_save_path};
 use crate::view::common::{toggle_main_menu, toggle_battery_menu, toggle_clock_menu};
 use crate::view::icon::ICONS_PIXMAPS;
@@ -47,33 +57,63 @@ use crate::view::search_bar::SearchBar;
 use crate::view::keyboard::Keyboard;
 use crate::view::menu::{Menu, MenuKind};
 use crate::view::menu_entry::MenuEntry;
-use crate::view::notification::Notification;
+use crate::view::notification::{Notification, push_notification};
 use crate::view::theme::{ThemeDialog, ThemeProp};
-use crate::settings::{guess_frontlight, FinishedAction, SouthEastCornerAction, BottomRightGestureAction, SouthStripAction, WestStripAction, EastStripAction, ProgressBarSettings};
+use crate::view::typography::TypographyDialog;
+use crate::view::dialog::Dialog;
+use crate::settings::{guess_frontlight, FinishedAction, SouthEastCornerAction, SouthWestCornerAction, CloseIconCorner, BottomRightGestureAction, SouthStripAction, WestStripAction, EastStripAction, UnresolvedLinkAction, ProgressBarSettings};
 use crate::settings::{DEFAULT_FONT_FAMILY, DEFAULT_TEXT_ALIGN, DEFAULT_LINE_HEIGHT, DEFAULT_MARGIN_WIDTH, MIN_LINE_HEIGHT_GRADIENT, MAX_LINE_HEIGHT_GRADIENT};
-use crate::settings::{HYPHEN_PENALTY, STRETCH_TOLERANCE};
+use crate::settings::{HYPHEN_PENALTY, STRETCH_TOLERANCE, ReaderSettings};
 use crate::settings::Theme;
 use crate::frontlight::LightLevels;
-use crate::gesture::GestureEvent;
+use crate::gesture::{GestureEvent, DOUBLE_TAP_DELAY};
 use crate::document::{Document, open, Location, TextLocation, BoundedText, Neighbors, BYTES_PER_PAGE};
-use crate::document::{TocEntry, SimpleTocEntry, TocLocation, toc_as_html, annotations_as_html, bookmarks_as_html};
+use crate::document::{TocEntry, SimpleTocEntry, TocLocation, toc_as_html, toc_collapsed_entries, toc_chapter_path, TOGGLE_TOC_ENTRY_URI, annotations_as_html, selection_from_uri, EDIT_NOTE_URI, REMOVE_ANNOTATION_URI, annotations_as_markdown, bookmarks_as_html, reading_stats_as_html};
 use crate::document::html::HtmlDocument;
-use crate::metadata::{Info, FileInfo, ReaderInfo, Annotation, TextAlign, ZoomMode, ScrollMode, PageScheme};
-use crate::metadata::{Margin, CroppingMargins, make_query};
-use crate::metadata::{DEFAULT_CONTRAST_EXPONENT, DEFAULT_CONTRAST_GRAY};
+use crate::metadata::{Info, FileInfo, ReaderInfo, Annotation, TextAlign, ReadingDirection, ZoomMode, ScrollMode, PageScheme};
+use crate::metadata::{Margin, CroppingMargins, PageAlignment, make_query, make_query_with, extract_metadata_from_document};
+use crate::metadata::{DEFAULT_CONTRAST_EXPONENT, DEFAULT_CONTRAST_GRAY, HIGHLIGHT_DRIFT, ANNOTATION_DRIFT, HIGHLIGHT_COLORS};
 use crate::geom::{Point, Vec2, Rectangle, Boundary, CornerSpec, BorderSpec};
 use crate::geom::{Dir, DiagDir, CycleDir, LinearDir, Axis, Region, halves};
-use crate::color::{BLACK, WHITE, GRAY03, GRAY10};
+use crate::color::{BLACK, WHITE, GRAY03, GRAY08, GRAY10, GRAY12, GRAY13, GRAY14};
 use crate::context::Context;
 
-const HISTORY_SIZE: usize = 32;
 const RECT_DIST_JITTER: f32 = 24.0;
-const ANNOTATION_DRIFT: u8 =  0x44;
-const HIGHLIGHT_DRIFT: u8 =  0x22;
+// Radius, in pixels at 300 DPI, of the handles drawn at the ends of an adjusted selection.
+const SELECTION_HANDLE_RADIUS: f32 = 12.0;
 const MEM_SCHEME: &str = "mem:";
 const ON_INVERTED: &str = "__inverted";
 const ON_UNINVERTED: &str = "__uninverted";
-const MAX_SEARCH_RESULTS: usize = 200;
+// Besides on new result pages, the results bar is refreshed every that many results.
+const RESULTS_BAR_STEP: usize = 10;
+// Number of even/odd page pairs sampled when detecting the cropping margins.
+const CROPPING_SAMPLES: usize = 3;
+// Space between the two pages of a spread.
+const SPREAD_GUTTER_WIDTH: f32 = 16.0;
+// Number of pages probed on each side of a page whose dimensions are unknown.
+const NEIGHBOR_DIMS_PROBES: usize = 4;
+// Number of locations loaded ahead of the visible ones when scrolling continuously.
+const PREFETCH_AHEAD: usize = 2;
+// Maximum number of page offsets remembered per book.
+const PAGE_OFFSETS_SIZE: usize = 16;
+// Selector style of the first ancestor-qualified selector of a CSS tweak.
+const CSS_ANCESTOR_STYLE: usize = 5;
+// Maximum number of characters of the label of a link treated as a note reference.
+const NOTE_LABEL_MAX_LEN: usize = 3;
+// Change of the contrast exponent per step of a two-finger vertical drag.
+const CONTRAST_EXPONENT_STEP: f32 = 0.1;
+// Extra drift applied to the focused annotation.
+const FOCUSED_ANNOTATION_BOOST: u8 = 0x33;
+// Number of drag steps spanning the height of the page.
+const CONTRAST_DRAG_STEPS: i32 = 40;
+// How long a dismissed selection can be brought back.
+const SELECTION_STASH_DELAY: Duration = Duration::from_secs(5);
+// Extension of the file next to a book where its bookmarks are exported.
+const BOOKMARKS_SIDECAR_EXTENSION: &str = "bookmarks.json";
+// Typing pause after which the first match of the search bar's text is shown.
+const SEARCH_PREVIEW_DELAY: Duration = Duration::from_millis(600);
+// Number of pages scanned for the first match of the search bar's text.
+const SEARCH_PREVIEW_PAGES: usize = 8;
 
 enum ThemeStash {
     New(Theme),
@@ -85,6 +125,12 @@ struct Chapter {
     pub page: usize,
     pub progress: f32,
     pub remain: f32,
+    // Extent, in bytes, of the text of the pages loaded so far, for the current layout.
+    pub page_extents: FxHashMap<usize, usize>,
+    // Number of words of the document, for the current layout.
+    pub word_count: Option<usize>,
+    // Position of the current page, in words, shown by the progress bar.
+    pub word_position: Option<String>,
 }
 
 impl Default for Chapter {
@@ -94,10 +140,307 @@ impl Default for Chapter {
             page: usize::MAX,
             progress: 0.0,
             remain: 0.0,
+            page_extents: FxHashMap::default(),
+            word_count: None,
+            word_position: None,
         }
     }
 }
 
+// Bounds on the time spent on a page for it to count towards the reading speed.
+const PACE_MIN_DURATION: Duration = Duration::from_secs(3);
+const PACE_MAX_DURATION: Duration = Duration::from_secs(600);
+// Weight of the latest sample in the moving averages.
+const PACE_SMOOTHING: f32 = 0.25;
+
+// Frontlight intensity at the start of the dimming schedule, and the last one applied.
+#[derive(Debug, Copy, Clone)]
+struct Dimming {
+    started: Instant,
+    intensity: f32,
+    applied: f32,
+}
+
+struct ReadingPace {
+    page: usize,
+    started: Instant,
+    words_per_minute: Option<f32>,
+    // Number of words per location unit.
+    density: Option<f32>,
+}
+
+impl ReadingPace {
+    fn new(page: usize) -> ReadingPace {
+        ReadingPace {
+            page,
+            started: Instant::now(),
+            words_per_minute: None,
+            density: None,
+        }
+    }
+
+    fn record(&mut self, page: usize, words_count: usize) {
+        let elapsed = self.started.elapsed();
+        if page > self.page && words_count > 0 &&
+           elapsed >= PACE_MIN_DURATION && elapsed <= PACE_MAX_DURATION {
+            let wpm = 60.0 * words_count as f32 / elapsed.as_secs_f32();
+            let density = words_count as f32 / (page - self.page) as f32;
+            self.words_per_minute = Some(smooth(self.words_per_minute, wpm));
+            self.density = Some(smooth(self.density, density));
+        }
+        self.page = page;
+        self.started = Instant::now();
+    }
+
+    // Estimated minutes needed to read the given number of location units.
+    fn minutes_left(&self, units: usize) -> Option<f32> {
+        self.words_per_minute.zip(self.density)
+            .map(|(wpm, density)| units as f32 * density / wpm)
+    }
+}
+
+struct ReadingSession {
+    start: NaiveDateTime,
+    page: usize,
+    page_turns: usize,
+}
+
+impl ReadingSession {
+    fn new(page: usize) -> ReadingSession {
+        ReadingSession {
+            start: Local::now().naive_local(),
+            page,
+            page_turns: 0,
+        }
+    }
+}
+
+// Heights of the top and bottom bars, the bottom ones being shorter in search mode.
+fn bars_heights(searching: bool) -> (i32, i32) {
+    let dpi = CURRENT_DEVICE.dpi;
+    let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+    let (small_thickness, big_thickness) = halves(thickness);
+    let (small_height, big_height) = (scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32,
+                                      scale_by_dpi(BIG_BAR_HEIGHT, dpi) as i32);
+    let bottom = if searching {
+        3 * small_height + small_thickness
+    } else {
+        let med_height = (small_height + big_height) / 2;
+        small_height + 3 * med_height + 2 * big_thickness + small_thickness
+    };
+    (small_height + big_thickness, bottom)
+}
+
+// Reflowable documents are laid out between the pinned bars, the tallest ones.
+fn layout_height(height: u32, pinned_bars: bool) -> u32 {
+    if !pinned_bars {
+        return height;
+    }
+    let (top, bottom) = bars_heights(false);
+    (height as i32 - top - bottom).max(1) as u32
+}
+
+fn smooth(average: Option<f32>, sample: f32) -> f32 {
+    average.map_or(sample, |a| a + PACE_SMOOTHING * (sample - a))
+}
+
+// Groups the digits by thousands, e.g. *45,000*.
+fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut text = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            text.push(',');
+        }
+        text.push(c);
+    }
+    text
+}
+
+// Rounds large counts to thousands, to fit the progress bar's label.
+fn format_short_count(count: usize) -> String {
+    if count >= 10_000 {
+        format!("{}k", count / 1000)
+    } else {
+        count.to_string()
+    }
+}
+
+// The line height growing with the font size, from the default line height at the default font size.
+fn scaled_line_height(font_size: f32, settings: &ReaderSettings) -> f32 {
+    let scaling = settings.line_height_scaling.clamp(MIN_LINE_HEIGHT_GRADIENT, MAX_LINE_HEIGHT_GRADIENT);
+    (settings.line_height + (font_size - settings.font_size) * scaling).max(1.0)
+}
+
+// The first words of a selection, or all of them when the count is zero.
+fn selection_words(text: &str, count: usize) -> String {
+    let count = if count == 0 { usize::MAX } else { count };
+    trim_non_alphanumeric(&first_n_words(text, count))
+}
+
+// A book is hyphenated with the global penalty, unless that penalty forbids hyphenation.
+fn book_hyphen_penalty(hyphenate: Option<bool>, settings: &ReaderSettings) -> i32 {
+    let hyphen_penalty = settings.paragraph_breaker.hyphen_penalty;
+    match hyphenate {
+        Some(true) if hyphen_penalty >= INFINITE_PENALTY => HYPHEN_PENALTY,
+        Some(false) => INFINITE_PENALTY,
+        _ => hyphen_penalty,
+    }
+}
+
+// The dimensions of the nearest page whose dimensions are known.
+fn neighbor_dims(doc: &dyn Document, location: usize) -> Option<(f32, f32)> {
+    let pages_count = doc.pages_count();
+    for d in 1..=NEIGHBOR_DIMS_PROBES {
+        if let Some(dims) = location.checked_sub(d).and_then(|index| doc.dims(index)) {
+            return Some(dims);
+        }
+        if location + d < pages_count {
+            if let Some(dims) = doc.dims(location + d) {
+                return Some(dims);
+            }
+        }
+    }
+    None
+}
+
+fn toc_locations(toc: &[TocEntry], locations: &mut Vec<Location>) {
+    for entry in toc {
+        locations.push(entry.location.clone());
+        toc_locations(&entry.children, locations);
+    }
+}
+
+// Stops a thread through its running flag and waits until it has dropped its clones
+// of the shared data, the document for the search thread.
+fn stop_thread(running: &AtomicBool, handle: Option<thread::JoinHandle<()>>) {
+    running.store(false, AtomicOrdering::Relaxed);
+    if let Some(handle) = handle {
+        handle.join().ok();
+    }
+}
+
+// Joins the words of a page into a searchable text, keyed by the offset of each word in that text.
+fn words_as_text(words: &[BoundedText]) -> (String, BTreeMap<usize, Boundary>) {
+    let mut text = String::new();
+    let mut rects = BTreeMap::new();
+    let mut end_offset = 0;
+    for word in words {
+        let (is_dyn, offset) =
+            if let TextLocation::Dynamic(offset) = word.location {
+                (true, offset)
+            } else {
+                (false, 1)
+            };
+        if text.ends_with('\u{00AD}') {
+            text.pop();
+        } else if !text.ends_with('-') && !text.is_empty() && offset > end_offset {
+            text.push(' ');
+        }
+        rects.insert(text.len(), word.rect);
+        text += &word.text;
+        if is_dyn {
+            end_offset = offset + word.text.len();
+        }
+    }
+    (text, rects)
+}
+
+fn match_rects(rects: &BTreeMap<usize, Boundary>, m: &regex::Match) -> Option<Vec<Boundary>> {
+    let (first, _) = rects.range(..= m.start()).next_back()?;
+    Some(rects.range(*first .. m.end()).map(|(_, rect)| *rect).collect())
+}
+
+// Searches the whole document in a background thread, which sends the matches to the hub.
+fn spawn_search(doc: Arc<Mutex<Box<dyn Document>>>, query: Regex, running: Arc<AtomicBool>, search_direction: LinearDir,
+                pages_count: usize, max_results: usize, hub: Hub) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut results_count = 0;
+        let mut loc = match search_direction {
+            LinearDir::Forward => Location::Exact(0),
+            LinearDir::Backward => Location::Exact(pages_count-1),
+        };
+
+        loop {
+            if !running.load(AtomicOrdering::Relaxed) {
+                break;
+            }
+
+            let mut doc = doc.lock().unwrap();
+
+            if let Some(location) = doc.resolve_location(loc) {
+                if let Some((ref words, _)) = doc.words(Location::Exact(location)) {
+                    let (text, rects) = words_as_text(words);
+                    for m in query.find_iter(&text) {
+                        if !running.load(AtomicOrdering::Relaxed) {
+                            break;
+                        }
+                        if let Some(match_rects) = match_rects(&rects, &m) {
+                            results_count += 1;
+                            hub.send(Event::SearchResult(location, match_rects)).ok();
+                            if max_results > 0 && results_count >= max_results && running.load(AtomicOrdering::Relaxed) {
+                                hub.send(Event::Notify(format!("Maximum {max_results} results reached. Search stopped."))).ok();
+                                running.store(false, AtomicOrdering::Relaxed);
+                                break;
+                            }
+                        }
+                    }
+                }
+                loc = match search_direction {
+                    LinearDir::Forward => Location::Next(location),
+                    LinearDir::Backward => Location::Previous(location),
+                };
+            } else {
+                break;
+            }
+        }
+
+        running.store(false, AtomicOrdering::Relaxed);
+        drop(doc);
+        hub.send(Event::EndOfSearch).ok();
+    })
+}
+
+// Appends a line to the given file, ending the file's last line first if needed.
+fn append_line(path: &Path, line: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true)
+                                     .read(true)
+                                     .append(true)
+                                     .open(path)?;
+    let mut last = [0u8];
+    if file.seek(SeekFrom::End(-1)).is_ok() && file.read_exact(&mut last).is_ok() && last[0] != b'\n' {
+        writeln!(file)?;
+    }
+    writeln!(file, "{}", line)
+}
+
+// Releases the held tap at `pt` once a second tap can't come anymore.
+fn arm_held_tap(pt: Point, hub: &Hub) {
+    let hub2 = hub.clone();
+    thread::spawn(move || {
+        thread::sleep(DOUBLE_TAP_DELAY);
+        hub2.send(Event::ReleaseTap(pt)).ok();
+    });
+}
+
+// Delays the next incremental search step.
+fn arm_search_preview(delay: Duration, hub: &Hub) {
+    let hub2 = hub.clone();
+    thread::spawn(move || {
+        thread::sleep(delay);
+        hub2.send(Event::SearchPreview).ok();
+    });
+}
+
+fn format_duration(minutes: f32) -> String {
+    let minutes = minutes.round() as u32;
+    if minutes < 60 {
+        format!("≈{}m", minutes)
+    } else {
+        format!("≈{}h {}m", minutes / 60, minutes % 60)
+    }
+}
+
 pub struct Reader {
     id: Id,
     rect: Rectangle,
@@ -108,11 +451,21 @@ pub struct Reader {
     text: FxHashMap<usize, Vec<BoundedText>>,        // Text of the current chunks.
     annotations: FxHashMap<usize, Vec<Annotation>>,  // Annotations for the current chunks.
     noninverted_regions: FxHashMap<usize, Vec<Boundary>>,
+    image_preserve: bool,                            // Invert the text while leaving the images untouched.
+    ruler: Option<i32>,                              // Ordinate of the line highlighted by the reading ruler.
     focus: Option<ViewId>,
     search: Option<Search>,
+    search_preview: Option<SearchPreview>,
+    search_thread: Option<thread::JoinHandle<()>>,
+    word_count_thread: Option<thread::JoinHandle<()>>,
     search_direction: LinearDir,
+    search_case_sensitive: bool,
+    search_whole_word: bool,
+    last_query: Option<String>,
     held_buttons: FxHashSet<ButtonCode>,
+    pressed_buttons: FxHashSet<ButtonCode>,
     selection: Option<Selection>,
+    last_selection: Option<(Selection, Instant)>,
     target_annotation: Option<[TextLocation; 2]>,
     history: VecDeque<usize>,
     state: State,
@@ -122,16 +475,38 @@ pub struct Reader {
     view_port: ViewPort,
     contrast: Contrast,
     synthetic: bool,
+    reading_direction: ReadingDirection,
     page_turns: usize,
+    last_turn: CycleDir,   // direction of the last page turn, in reading order
+    pace: ReadingPace,
+    session: ReadingSession,
+    dimming: Option<Dimming>,
+    words_counted: bool,   // the words of the document have been counted
+    focused_annotation: Option<[TextLocation; 2]>,
+    held_tap: Option<Point>, // single tap waiting for a possible second one
+    show_time_left: bool,
+    chapter_progress_tick: bool,
+    pinned_bars: bool,     // the bars stay visible and the pages are displayed between them
+    pinned_heights: (i32, i32), // the heights of the pinned bars when the pages were last rendered
+    toc_collapsed: Option<BTreeSet<usize>>,
     reflowable: bool,
     ephemeral: bool,
     finished: bool,
+    auto_turn: Option<Arc<AtomicBool>>,
     progress_bar: ProgressBarSettings,
     theme: Option<ThemeStash>, // temporarily store selection in theme dialog
     chapter: RefCell<Chapter>, // cache chapter info
+    page_labels: RefCell<Option<FxHashMap<String, usize>>>, // cache the pages of the printed page numbers
+    chapter_ticks: RefCell<Option<Vec<usize>>>, // cache the locations of the entries of the table of contents
     time_format: String,
+    close_icon_corner: CloseIconCorner,
     dirty_clock: RefCell<bool>,
-
+    css_stash: Option<String>, // CSS removed by the last "Undo all", restorable once
+    last_css_tweak: Option<(usize, usize)>, // (selector style, tweak index) of the last applied tweak
+    previous_font_family: Option<String>,
+    layout_anchor: Option<usize>, // offset of the word kept visible across relayouts
+    cropping_scheme: Option<PageScheme>, // scheme applied once the detected margins are validated
+    refresh_debug: Option<(UpdateMode, u8)>, // last update mode and effective refresh rate
 }
 
 #[derive(Debug)]
@@ -140,6 +515,8 @@ struct ViewPort {
     scroll_mode: ScrollMode,
     page_offset: Point,   // Offset relative to the top left corner of a resource's frame.
     margin_width: i32,
+    spread: bool,         // Show two pages side by side in landscape, when fitting to page.
+    page_alignment: PageAlignment,
 }
 
 impl Default for ViewPort {
@@ -149,6 +526,8 @@ impl Default for ViewPort {
             scroll_mode: ScrollMode::Screen,
             page_offset: pt!(0, 0),
             margin_width: 0,
+            spread: false,
+            page_alignment: PageAlignment::Center,
         }
     }
 }
@@ -158,9 +537,13 @@ enum State {
     Idle,
     Selection(i32),
     AdjustSelection,
+    // A selection handle is dragged by the given finger, which was held if the flag is set.
+    DragHandle(i32, bool),
+    // The gesture that ends the drag of a handle is still to come.
+    HandleReleased,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Selection {
     start: TextLocation,
     end: TextLocation,
@@ -172,6 +555,7 @@ struct Resource {
     pixmap: Pixmap,
     frame: Rectangle,  // The pixmap's rectangle minus the cropping margins.
     scale: f32,
+    unavailable: bool, // The page couldn't be rendered.
 }
 
 #[derive(Debug, Clone)]
@@ -182,12 +566,26 @@ struct RenderChunk {
     scale: f32,
 }
 
+// Incremental search of the text typed in the search bar.
+#[derive(Debug)]
+struct SearchPreview {
+    text: String,
+    changed: Instant,
+    // Page displayed when the typing started, from which the first match is looked for.
+    origin: usize,
+    armed: bool,
+    running: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
 #[derive(Debug)]
 struct Search {
     query: String,
     highlights: BTreeMap<usize, Vec<Vec<Boundary>>>,
     running: Arc<AtomicBool>,
     current_page: usize,
+    // Page key and group index of the match we last stepped to.
+    current_match: Option<(usize, usize)>,
     results_count: usize,
 }
 
@@ -198,6 +596,7 @@ impl Default for Search {
             highlights: BTreeMap::new(),
             running: Arc::new(AtomicBool::new(true)),
             current_page: 0,
+            current_match: None,
             results_count: 0,
         }
     }
@@ -207,6 +606,7 @@ impl Default for Search {
 struct Contrast {
     exponent: f32,
     gray: f32,
+    background: Option<f32>,
 }
 
 impl Default for Contrast {
@@ -214,6 +614,7 @@ impl Default for Contrast {
         Contrast {
             exponent: DEFAULT_CONTRAST_EXPONENT,
             gray: DEFAULT_CONTRAST_GRAY,
+            background: None,
         }
     }
 }
@@ -277,18 +678,109 @@ fn find_cut(frame: &Rectangle, y_pos: i32, scale: f32, dir: LinearDir, lines: &[
     })
 }
 
+// Offset of the first word starting on the page at `start`.
+fn page_anchor(words: &[BoundedText], start: usize) -> Option<usize> {
+    words.iter()
+         .filter_map(|word| match word.location {
+             TextLocation::Dynamic(offset) if offset >= start => Some(offset),
+             _ => None,
+         })
+         .min()
+}
+
+// Offset of the first word of the page at `location`, or the location itself if the page has no text.
+fn first_word_offset(doc: &mut dyn Document, location: usize) -> usize {
+    let next_page = doc.resolve_location(Location::Next(location));
+    doc.words(Location::Exact(location))
+       .and_then(|(words, _)| page_anchor(&words, location))
+       .filter(|&offset| next_page.map_or(true, |next| offset < next))
+       .unwrap_or(location)
+}
+
+// Page of the current layout that holds the given offset.
+fn page_of_offset(doc: &mut dyn Document, offset: usize) -> Option<usize> {
+    let offset = offset.min(doc.pages_count().saturating_sub(1));
+    doc.resolve_location(Location::Exact(offset))
+}
+
+// The rule applying the tweak `css` to `selector`, preceded by a newline.
+// At-rules and complete rules are kept as is, a leading pseudo-class or
+// pseudo-element is attached to each selector, and bare declarations are
+// wrapped in a block. The rule fits on one line, so that the last tweak
+// can be peeled off at the last newline.
+fn css_rule(selector: &str, css: &str) -> String {
+    let css = css.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let brace = css.find('{');
+    if css.starts_with('@') || brace.map_or(false, |i| i > 0 && !css.starts_with(':')) {
+        format!("\n{}", css)
+    } else if let Some(i) = brace.filter(|_| css.starts_with(':')) {
+        let (pseudo, block) = css.split_at(i);
+        let selectors = selector.split(',')
+                                .map(|sel| format!("{}{}", sel.trim(), pseudo.trim()))
+                                .collect::<Vec<String>>()
+                                .join(", ");
+        format!("\n{} {}", selectors, block)
+    } else {
+        format!("\n{} {}{}{}",
+                selector,
+                if css.starts_with('{') {""} else {"{"},
+                css,
+                if css.ends_with('}') {""} else {"}"})
+    }
+}
+
+// The tweaks of `css` without the last one.
+fn peel_last_rule(css: &str) -> String {
+    let css = css.trim();
+    // locate the next to last } (the last } isn't followed by \n thanks to trim() )
+    css.rfind("}\n").map(|i| css[..=i].to_string()).unwrap_or_default()
+}
+
+// The selectors offered for a CSS tweak, with their selector style: the combinations of the
+// block and wrapper selectors, then the target within each level of its ancestor chain.
+fn css_selectors(div_sel: &str, span_sel: &str, ancestors: &[String]) -> Vec<(usize, String)> {
+    let mut selectors = if span_sel.is_empty() {
+        vec![(0, div_sel.to_owned())]
+    } else {
+        vec![(0, div_sel.to_owned()),
+             (1, span_sel.to_owned()),
+             (2, format!("{} {}", div_sel, span_sel)),
+             (3, format!("{}, {}", div_sel, span_sel)),
+             (4, format!("{0}, {0} {1}", div_sel, span_sel))]
+    };
+    let mut target = if span_sel.is_empty() {
+        div_sel.to_owned()
+    } else {
+        format!("{} {}", div_sel, span_sel)
+    };
+    for (depth, ancestor) in ancestors.iter().enumerate() {
+        target = format!("{} {}", ancestor, target);
+        selectors.push((CSS_ANCESTOR_STYLE + depth, target.clone()));
+    }
+    selectors
+}
+
 impl Reader {
-    pub fn new(rect: Rectangle, mut info: Info, hub: &Hub, context: &mut Context) -> Option<Reader> {
+    pub fn new(rect: Rectangle, info: Info, hub: &Hub, context: &mut Context) -> Option<Reader> {
+        Reader::with_target(rect, info, None, hub, context)
+    }
+
+    // Opens the document at the given location instead of the last read page.
+    pub fn new_at(rect: Rectangle, info: Info, target: Location, hub: &Hub, context: &mut Context) -> Option<Reader> {
+        Reader::with_target(rect, info, Some(target), hub, context)
+    }
+
+    fn with_target(rect: Rectangle, mut info: Info, target: Option<Location>, hub: &Hub, context: &mut Context) -> Option<Reader> {
         let id = ID_FEEDER.next();
         let settings = &context.settings;
         let path = context.library.home.join(&info.file.path);
 
         open(&path).and_then(|mut doc| {
             let (width, height) = context.display.dims;
-            let font_size = info.reader.as_ref().and_then(|r| r.font_size)
+            let font_size = info.reader.as_ref().and_then(|r| r.font_size(settings.reader.font_size))
                                 .unwrap_or(settings.reader.font_size);
 
-            doc.layout(width, height, font_size, CURRENT_DEVICE.dpi);
+            doc.layout(width, layout_height(height, settings.reader.pinned_bars), font_size, CURRENT_DEVICE.dpi);
 
             let margin_width = info.reader.as_ref().and_then(|r| r.margin_width)
                                    .unwrap_or(settings.reader.margin_width);
@@ -299,6 +791,7 @@ impl Reader {
 
             let mut progress_bar = settings.reader.progress_bar.clone();
             progress_bar.enabled = info.reader.as_ref().and_then(|r| r.show_progress_bar)
+                                       .or_else(|| progress_bar.by_kind.get(&info.file.kind).cloned())
                                        .unwrap_or(progress_bar.enabled);
 
             let font_family = info.reader.as_ref().and_then(|r| r.font_family.as_ref())
@@ -308,6 +801,18 @@ impl Reader {
                 doc.set_font_family(font_family, &settings.reader.font_path);
             }
 
+            // The family set above still applies to the text the document's fonts don't cover.
+            if info.reader.as_ref().and_then(|r| r.publisher_fonts)
+                   .unwrap_or(settings.reader.publisher_fonts) {
+                doc.use_document_fonts(true);
+            }
+
+            if let Some(ref mut r) = info.reader {
+                if r.line_height.is_none() && r.scale_line_height.unwrap_or(settings.reader.scale_line_height) {
+                    r.line_height = Some(scaled_line_height(font_size, &settings.reader));
+                }
+            }
+
             let line_height = info.reader.as_ref().and_then(|r| r.line_height)
                                   .unwrap_or(settings.reader.line_height);
 
@@ -322,7 +827,8 @@ impl Reader {
                 doc.set_text_align(text_align);
             }
 
-            let hyphen_penalty = settings.reader.paragraph_breaker.hyphen_penalty;
+            let hyphen_penalty = book_hyphen_penalty(info.reader.as_ref().and_then(|r| r.hyphenate),
+                                                     &settings.reader);
 
             if hyphen_penalty != HYPHEN_PENALTY {
                 doc.set_hyphen_penalty(hyphen_penalty);
@@ -338,10 +844,13 @@ impl Reader {
                 doc.set_ignore_document_css(true);
             }
 
-            let mut view_port = ViewPort::default();
+            let mut view_port = ViewPort {
+                page_alignment: settings.reader.page_alignment,
+                .. Default::default()
+            };
             let mut contrast = Contrast::default();
             let pages_count = doc.pages_count();
-            let current_page;
+            let mut current_page;
 
             // TODO: use get_or_insert_with?
             if let Some(ref mut r) = info.reader {
@@ -365,6 +874,12 @@ impl Reader {
                     view_port.zoom_mode = zoom_mode;
                 }
 
+                view_port.spread = r.spread.unwrap_or(false);
+
+                if let Some(page_alignment) = r.page_alignment {
+                    view_port.page_alignment = page_alignment;
+                }
+
                 if let Some(scroll_mode) = r.scroll_mode {
                     view_port.scroll_mode = scroll_mode;
                 } else {
@@ -379,8 +894,14 @@ impl Reader {
                     view_port.page_offset = page_offset;
                 }
 
+                // Offsets saved without their visit order are deemed visited by location.
+                if r.offset_visits.len() != r.page_offsets.len() ||
+                   r.offset_visits.iter().any(|l| !r.page_offsets.contains_key(l)) {
+                    r.offset_visits = r.page_offsets.keys().cloned().collect();
+                }
+
                 if !doc.is_reflowable() {
-                    view_port.margin_width = mm_to_px(r.screen_margin_width.unwrap_or(0) as f32,
+                    view_port.margin_width = mm_to_px(r.margin_width(false).unwrap_or(0) as f32,
                                                       CURRENT_DEVICE.dpi) as i32;
                 }
 
@@ -392,18 +913,31 @@ impl Reader {
                     contrast.gray = gray;
                 }
 
+                contrast.background = r.background_gray;
+
             } else {
                 current_page = doc.resolve_location(Location::Exact(0))?;
 
                 info.reader = Some(ReaderInfo {
                     current_page,
                     pages_count,
+                    dithered: settings.reader.dithered_kinds.contains(&info.file.kind),
                     .. Default::default()
                 });
             }
 
+            // The target is resolved once the layout settings are applied.
+            if let Some(location) = target.and_then(|loc| doc.resolve_location(loc)) {
+                current_page = location;
+                view_port.page_offset = pt!(0, 0);
+            }
+
+            context.fb.set_dithered(info.is_dithered(&settings.reader.dithered_kinds));
+
             let synthetic = doc.has_synthetic_page_numbers();
             let reflowable = doc.is_reflowable();
+            let reading_direction = info.reader.as_ref().and_then(|r| r.reading_direction)
+                                        .unwrap_or_else(|| doc.reading_direction());
 
             println!("{}", info.file.path.display());
 
@@ -419,11 +953,19 @@ impl Reader {
                 text: FxHashMap::default(),
                 annotations: FxHashMap::default(),
                 noninverted_regions: FxHashMap::default(),
+                image_preserve: info.reader.as_ref().and_then(|r| r.image_preserve).unwrap_or(false),
+                ruler: None,
                 focus: None,
                 search: None,
-                search_direction: LinearDir::Forward,
+                search_preview: None,
+                search_direction: settings.reader.default_search_direction,
+                search_case_sensitive: settings.reader.search_case_sensitive,
+                search_whole_word: settings.reader.search_whole_word,
+                last_query: info.reader.as_ref().and_then(|r| r.search_query.clone()),
                 held_buttons: FxHashSet::default(),
+                pressed_buttons: FxHashSet::default(),
                 selection: None,
+                last_selection: None,
                 target_annotation: None,
                 history: VecDeque::new(),
                 state: State::Idle,
@@ -432,17 +974,50 @@ impl Reader {
                 pages_count,
                 view_port,
                 synthetic,
+                reading_direction,
                 page_turns: 0,
+                pace: ReadingPace::new(current_page),
+                session: ReadingSession::new(current_page),
+                dimming: None,
+                words_counted: false,
+                last_turn: CycleDir::Next,
+                search_thread: None,
+                word_count_thread: None,
+                focused_annotation: None,
+                held_tap: None,
+                show_time_left: settings.reader.show_time_left,
+                chapter_progress_tick: settings.reader.chapter_progress_tick,
+                pinned_bars: settings.reader.pinned_bars,
+                pinned_heights: (0, 0),
+                toc_collapsed: None,
                 contrast,
                 ephemeral: false,
                 reflowable,
                 finished: false,
+                auto_turn: None,
                 progress_bar,
                 theme: None,
                 chapter: RefCell::new(Chapter::default()),
-                time_format: context.settings.time_format.clone(),
+                page_labels: RefCell::new(None),
+                chapter_ticks: RefCell::new(None),
+                time_format: settings.reader.clock_format.clone()
+                                     .unwrap_or_else(|| settings.time_format.clone()),
+                close_icon_corner: settings.reader.close_icon_corner,
                 dirty_clock: RefCell::new(false),
+                css_stash: None,
+                last_css_tweak: None,
+                previous_font_family: None,
+                layout_anchor: None,
+                cropping_scheme: None,
+                refresh_debug: settings.reader.refresh_rate.debug_overlay
+                                       .then_some((UpdateMode::Full, 0)),
             })
+        }).map(|mut reader| {
+            if reader.pinned_bars {
+                let mut rq = RenderQueue::new();
+                reader.toggle_bars(Some(true), hub, &mut rq, context);
+            }
+            reader
         })
     }
 
@@ -493,11 +1068,19 @@ impl Reader {
             text: FxHashMap::default(),
             annotations: FxHashMap::default(),
             noninverted_regions: FxHashMap::default(),
+            image_preserve: false,
+            ruler: None,
             focus: None,
             search: None,
-            search_direction: LinearDir::Forward,
+            search_preview: None,
+            search_direction: context.settings.reader.default_search_direction,
+            search_case_sensitive: context.settings.reader.search_case_sensitive,
+            search_whole_word: context.settings.reader.search_whole_word,
+            last_query: None,
             held_buttons: FxHashSet::default(),
+            pressed_buttons: FxHashSet::default(),
             selection: None,
+            last_selection: None,
             target_annotation: None,
             history: VecDeque::new(),
             state: State::Idle,
@@ -506,16 +1089,43 @@ impl Reader {
             pages_count,
             view_port: ViewPort::default(),
             synthetic: true,
+            reading_direction: ReadingDirection::Ltr,
             page_turns: 0,
+            pace: ReadingPace::new(current_page),
+            session: ReadingSession::new(current_page),
+            dimming: None,
+            words_counted: false,
+            last_turn: CycleDir::Next,
+            search_thread: None,
+            word_count_thread: None,
+            focused_annotation: None,
+            held_tap: None,
+            show_time_left: context.settings.reader.show_time_left,
+            chapter_progress_tick: false,
+            pinned_bars: false,
+            pinned_heights: (0, 0),
+            toc_collapsed: None,
             contrast: Contrast::default(),
             ephemeral: true,
             reflowable: true,
             finished: false,
+            auto_turn: None,
             progress_bar,
             theme: None,
             chapter: RefCell::new(Chapter::default()),
-            time_format: context.settings.time_format.clone(),
+            page_labels: RefCell::new(None),
+            chapter_ticks: RefCell::new(None),
+            time_format: context.settings.reader.clock_format.clone()
+                                .unwrap_or_else(|| context.settings.time_format.clone()),
+            close_icon_corner: context.settings.reader.close_icon_corner,
             dirty_clock: RefCell::new(false),
+            css_stash: None,
+            last_css_tweak: None,
+            previous_font_family: None,
+            layout_anchor: None,
+            cropping_scheme: None,
+            refresh_debug: context.settings.reader.refresh_rate.debug_overlay
+                                  .then_some((UpdateMode::Full, 0)),
         }
     }
 
@@ -529,24 +1139,65 @@ impl Reader {
                                   .and_then(|r| r.cropping_margins.as_ref()
                                                  .map(|c| c.margin(location)))
                                   .cloned().unwrap_or_default();
-        let dims = doc.dims(location).unwrap_or((3.0, 4.0));
+        let dims = doc.dims(location)
+                      .or_else(|| neighbor_dims(&**doc, location))
+                      .unwrap_or((3.0, 4.0));
         let screen_margin_width = self.view_port.margin_width;
-        let scale = scaling_factor(&self.rect, &cropping_margin, screen_margin_width, dims, self.view_port.zoom_mode);
+        let (top, bottom) = self.pinned_bars_heights();
+        let page_rect = rect![self.rect.min.x, self.rect.min.y + top,
+                              self.rect.max.x, self.rect.max.y - bottom];
+        let rect = if self.is_spread() {
+            let width = (self.rect.width() as i32 - 2 * screen_margin_width - self.spread_gutter()) / 2;
+            rect![page_rect.min, pt!(page_rect.min.x + width + 2 * screen_margin_width, page_rect.max.y)]
+        } else {
+            page_rect
+        };
+        let scale = scaling_factor(&rect, &cropping_margin, screen_margin_width, dims, self.view_port.zoom_mode);
         if let Some((pixmap, _)) = doc.pixmap(Location::Exact(location), scale) {
             let frame = rect![(cropping_margin.left * pixmap.width as f32).ceil() as i32,
                               (cropping_margin.top * pixmap.height as f32).ceil() as i32,
                               ((1.0 - cropping_margin.right) * pixmap.width as f32).floor() as i32,
                               ((1.0 - cropping_margin.bottom) * pixmap.height as f32).floor() as i32];
-            self.cache.insert(location, Resource { pixmap, frame, scale });
+            self.cache.insert(location, Resource { pixmap, frame, scale, unavailable: false });
         } else {
             let width = (dims.0 as f32 * scale).max(1.0) as u32;
             let height = (dims.1 as f32 * scale).max(1.0) as u32;
-            let pixmap = Pixmap::empty(width, height);
+            // Pages that can't be rendered are framed to tell them apart from blank pages.
+            let pixmap = Pixmap::try_new(width, height).map(|mut pixmap| {
+                let rect = pixmap.rect();
+                let thickness = scale_by_dpi(THICKNESS_MEDIUM, CURRENT_DEVICE.dpi) as u16;
+                pixmap.draw_rectangle_outline(&rect, &BorderSpec { thickness, color: GRAY08 });
+                pixmap
+            }).unwrap_or_else(|| Pixmap::empty(width, height));
             let frame = pixmap.rect();
-            self.cache.insert(location, Resource { pixmap, frame, scale });
+            self.cache.insert(location, Resource { pixmap, frame, scale, unavailable: true });
         }
     }
 
+    // Heights of the top and bottom bars, when they're pinned.
+    fn pinned_bars_heights(&self) -> (i32, i32) {
+        if !self.pinned_bars {
+            return (0, 0);
+        }
+        bars_heights(self.search.is_some() || locate::<SearchBar>(self).is_some())
+    }
+
+    // Height of the surface where the pages are displayed.
+    fn page_height(&self) -> u32 {
+        let (top, bottom) = self.pinned_bars_heights();
+        (self.rect.height() as i32 - top - bottom).max(1) as u32
+    }
+
+    fn is_spread(&self) -> bool {
+        self.view_port.spread && !self.reflowable &&
+        self.view_port.zoom_mode == ZoomMode::FitToPage &&
+        self.rect.width() > self.rect.height()
+    }
+
+    fn spread_gutter(&self) -> i32 {
+        scale_by_dpi(SPREAD_GUTTER_WIDTH, CURRENT_DEVICE.dpi) as i32
+    }
+
     fn load_text(&mut self, location: usize) {
         if self.text.contains_key(&location) {
             return;
@@ -566,16 +1217,58 @@ impl Reader {
             if self.current_page != ch.page {
                 ch.page = self.current_page;
                 let mut doc = self.doc.lock().unwrap();
-                let rtoc = self.toc().or_else(|| doc.toc());
+                let rtoc = self.toc(doc.as_ref()).or_else(|| doc.toc());
                 let chapter = rtoc.as_ref().and_then(|toc| doc.chapter(self.current_page, toc));
                 ch.title = chapter.map(|(c, _, _)| c.title.clone()).unwrap_or_default();
                 ch.progress = chapter.map(|(_, p, _)| p).unwrap_or_default();
                 ch.remain = chapter.map(|(_, _, r)| r).unwrap_or_default();
+                if self.synthetic {
+                    self.sample_page_extents(&mut ch.page_extents);
+                    // The document counts the remaining bytes in nominal pages.
+                    if !ch.page_extents.is_empty() {
+                        let bpp = ch.page_extents.values().sum::<usize>() as f32 / ch.page_extents.len() as f32;
+                        ch.remain *= BYTES_PER_PAGE as f32 / bpp;
+                    }
+                }
+                // The words of fixed-layout documents are only counted on demand.
+                if self.progress_bar.show_word_position && (self.reflowable || self.words_counted) {
+                    if ch.word_count.is_none() {
+                        ch.word_count = doc.word_count();
+                    }
+                    ch.word_position = ch.word_count.and_then(|total| {
+                        doc.word_offset(self.current_page)
+                           .map(|offset| format!("{}/{}", format_short_count(offset), format_short_count(total)))
+                    });
+                }
             }
         }
         self.chapter.borrow()
     }
 
+    // Adds the extents, in bytes, of the text of the loaded pages to the samples,
+    // so that short pages, like the ends of chapters, weigh less as more pages are read.
+    fn sample_page_extents(&self, extents: &mut FxHashMap<usize, usize>) {
+        let samples = self.text.iter().filter_map(|(&location, words)| {
+            let first = words.iter().find_map(|w| match w.location {
+                TextLocation::Dynamic(offset) => Some(offset),
+                _ => None,
+            })?;
+            let last = words.iter().rev().find_map(|w| match w.location {
+                TextLocation::Dynamic(offset) => Some(offset + w.text.len()),
+                _ => None,
+            })?;
+            Some((location, last.saturating_sub(first)))
+        }).filter(|&(_, extent)| extent > 0);
+        extents.extend(samples);
+    }
+
+    // The text of the pages, the chapter info and the chapter ticks depend on the layout.
+    fn clear_text(&mut self) {
+        self.text.clear();
+        self.chapter.replace(Chapter::default());
+        self.chapter_ticks.replace(None);
+    }
+
     fn go_to_page(&mut self, location: usize, record: bool, hub: &Hub, rq: &mut RenderQueue, context: &Context) {
         let loc = {
             let mut doc = self.doc.lock().unwrap();
@@ -583,9 +1276,9 @@ impl Reader {
         };
 
         if let Some(location) = loc {
-            if record {
+            if record && self.history.back() != Some(&self.current_page) {
                 self.history.push_back(self.current_page);
-                if self.history.len() > HISTORY_SIZE {
+                if self.history.len() > context.settings.reader.history_size {
                     self.history.pop_front();
                 }
             }
@@ -594,9 +1287,15 @@ impl Reader {
                 s.current_page = s.highlights.range(..=location).count().saturating_sub(1);
             }
 
+            if !self.reflowable {
+                self.record_page_offset();
+            }
+
             self.current_page = location;
-            self.view_port.page_offset = pt!(0);
-            self.current_page = location;
+            self.view_port.page_offset = self.info.reader.as_ref()
+                                             .filter(|_| self.view_port.zoom_mode != ZoomMode::FitToPage)
+                                             .and_then(|r| r.page_offsets.get(&location).cloned())
+                                             .unwrap_or_else(|| pt!(0));
             let mode = self.get_update_mode(true, context);
             self.update(Some(mode), hub, rq, context);
             self.update_bottom_bar(rq);
@@ -607,11 +1306,58 @@ impl Reader {
         }
     }
 
+    fn record_page_offset(&mut self) {
+        let location = self.current_page;
+        let offset = self.view_port.page_offset;
+        if let Some(ref mut r) = self.info.reader {
+            r.offset_visits.retain(|&l| l != location);
+            if offset == pt!(0) {
+                r.page_offsets.remove(&location);
+                return;
+            }
+            r.page_offsets.insert(location, offset);
+            r.offset_visits.push(location);
+            if r.offset_visits.len() > PAGE_OFFSETS_SIZE {
+                let excess = r.offset_visits.len() - PAGE_OFFSETS_SIZE;
+                for oldest in r.offset_visits.drain(..excess) {
+                    r.page_offsets.remove(&oldest);
+                }
+            }
+        }
+    }
+
+    // Tells how many pages follow the current one in its chapter, after a page turn
+    // that stayed within the chapter titled `title`, from `location`.
+    fn notify_chapter_countdown(&mut self, title: &str, location: usize, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        if !context.settings.reader.chapter_countdown || self.current_page == location {
+            return;
+        }
+
+        let left = {
+            let chapter = self.chapter();
+            if chapter.title != title {
+                return;
+            }
+            // The remaining pages include the current one.
+            (chapter.remain.ceil() as usize).saturating_sub(1)
+        };
+
+        if left > 0 {
+            let msg = if left == 1 {
+                "1 page left in chapter".to_string()
+            } else {
+                format!("{} pages left in chapter", left)
+            };
+            let notif = Notification::new(msg, hub, rq, context);
+            push_notification(&mut self.children, notif, rq, context);
+        }
+    }
+
     fn go_to_chapter(&mut self, dir: CycleDir, hub: &Hub, rq: &mut RenderQueue, context: &Context) {
         let current_page = self.current_page;
         let loc = {
             let mut doc = self.doc.lock().unwrap();
-            if let Some(toc) = self.toc()
+            if let Some(toc) = self.toc(doc.as_ref())
                                    .or_else(|| doc.toc()) {
                 let chap_offset = if dir == CycleDir::Previous {
                    doc.chapter(current_page, &toc)
@@ -632,7 +1378,34 @@ impl Reader {
         }
     }
 
-    fn text_location_range(&self) -> Option<[TextLocation; 2]> {
+    /// The location of the first displayed page: a page index for fixed layouts,
+    /// a byte offset for synthetic (EPUB and HTML) documents.
+    pub fn current_page(&self) -> usize {
+        self.current_page
+    }
+
+    /// The number of pages, expressed in the same unit as `current_page`.
+    pub fn pages_count(&self) -> usize {
+        self.pages_count
+    }
+
+    /// The reading progress, between 0.0 and 1.0.
+    pub fn progress(&self) -> f32 {
+        if self.pages_count == 0 {
+            return 0.0;
+        }
+        self.current_page as f32 / self.pages_count as f32
+    }
+
+    /// The title of the chapter containing the current page, if the document has a table of contents.
+    pub fn chapter_title(&self) -> Option<String> {
+        let chapter = self.chapter();
+        Some(chapter.title.clone()).filter(|title| !title.is_empty())
+    }
+
+    /// The locations of the first and last words visible on screen.
+    /// Returns `None` when no text is displayed.
+    pub fn text_location_range(&self) -> Option<[TextLocation; 2]> {
         let mut min_loc = None;
         let mut max_loc = None;
         for chunk in &self.chunks {
@@ -664,9 +1437,9 @@ impl Reader {
         let loc_bkm = self.info.reader.as_ref().and_then(|r| {
             match dir {
                 CycleDir::Next => r.bookmarks.range(self.current_page+1 ..)
-                                   .next().cloned(),
+                                   .next().map(|(location, _)| *location),
                 CycleDir::Previous => r.bookmarks.range(.. self.current_page)
-                                       .next_back().cloned(),
+                                       .next_back().map(|(location, _)| *location),
             }
         });
 
@@ -675,6 +1448,43 @@ impl Reader {
         }
     }
 
+    // Steps through the annotations visible on the screen, in reading order.
+    fn focus_visible_annotation(&mut self, dir: CycleDir, rq: &mut RenderQueue) {
+        let mut selections = self.annotations.values().flatten()
+                                 .map(|annot| annot.selection)
+                                 .collect::<Vec<[TextLocation; 2]>>();
+        selections.sort();
+        selections.dedup();
+
+        if selections.is_empty() {
+            return;
+        }
+
+        let len = selections.len();
+        let index = self.focused_annotation.and_then(|sel| selections.iter().position(|s| *s == sel));
+        let next_index = match (dir, index) {
+            (CycleDir::Next, Some(i)) => (i + 1) % len,
+            (CycleDir::Next, None) => 0,
+            (CycleDir::Previous, Some(i)) => (i + len - 1) % len,
+            (CycleDir::Previous, None) => len - 1,
+        };
+
+        let previous = self.focused_annotation.replace(selections[next_index]);
+
+        for sel in previous.into_iter().chain(self.focused_annotation) {
+            if let Some(rect) = self.text_rect(sel) {
+                rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+            }
+        }
+    }
+
+    fn focused_annotation_at(&self, pt: Point) -> Option<Annotation> {
+        let sel = self.focused_annotation?;
+        self.text_rect(sel).filter(|rect| rect.includes(pt))?;
+        self.annotations.values().flatten()
+            .find(|annot| annot.selection == sel).cloned()
+    }
+
     fn go_to_annotation(&mut self, dir: CycleDir, hub: &Hub, rq: &mut RenderQueue, context: &Context) {
         let loc_annot = self.info.reader.as_ref().and_then(|r| {
             match dir {
@@ -704,6 +1514,54 @@ impl Reader {
         }
     }
 
+    fn toggle_jump_history_menu(&mut self, enable: Option<bool>, rq: &mut RenderQueue, context: &mut Context) {
+        if let Some(index) = locate_by_id(self, ViewId::JumpHistoryMenu) {
+            if let Some(true) = enable {
+                return;
+            }
+
+            rq.add(RenderData::expose(*self.child(index).rect(), UpdateMode::Gui));
+            self.children.remove(index);
+        } else {
+            if let Some(false) = enable {
+                return;
+            }
+
+            let entries = {
+                let mut doc = self.doc.lock().unwrap();
+                let rtoc = self.toc(doc.as_ref()).or_else(|| doc.toc());
+                self.history.iter().enumerate().rev().map(|(index, &location)| {
+                    let page = if self.synthetic {
+                        format!("{:.1}", location as f64 / BYTES_PER_PAGE)
+                    } else {
+                        format!("{}", location + 1)
+                    };
+                    let label = rtoc.as_ref()
+                                    .and_then(|toc| doc.chapter(location, toc))
+                                    .map(|(chap, _, _)| format!("{} — {}", page, chap.title))
+                                    .unwrap_or(page);
+                    EntryKind::Command(label, EntryId::JumpToHistory(index))
+                }).collect::<Vec<EntryKind>>()
+            };
+
+            if entries.is_empty() {
+                return;
+            }
+
+            let pt = pt!(self.rect.width() as i32 / 2, self.rect.height() as i32 / 3);
+            let menu = Menu::new(rect![pt, pt], ViewId::JumpHistoryMenu, MenuKind::Contextual, entries, context);
+            rq.add(RenderData::new(menu.id(), *menu.rect(), UpdateMode::Gui));
+            self.children.push(Box::new(menu) as Box<dyn View>);
+        }
+    }
+
+    fn jump_to_history(&mut self, index: usize, hub: &Hub, rq: &mut RenderQueue, context: &Context) {
+        if let Some(&location) = self.history.get(index) {
+            self.history.truncate(index);
+            self.go_to_page(location, false, hub, rq, context);
+        }
+    }
+
     fn vertical_scroll(&mut self, delta_y: i32, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
         if delta_y == 0 || self.view_port.zoom_mode == ZoomMode::FitToPage || self.cache.is_empty() {
             return;
@@ -757,7 +1615,7 @@ impl Reader {
             },
             ScrollMode::Page => {
                 let frame_height = self.cache[&location].frame.height() as i32;
-                let available_height = self.rect.height() as i32 - 2 * self.view_port.margin_width;
+                let available_height = self.page_height() as i32 - 2 * self.view_port.margin_width;
                 if frame_height > available_height {
                     next_top_offset = next_top_offset.max(0).min(frame_height - available_height);
                 } else {
@@ -794,7 +1652,7 @@ impl Reader {
         let Resource { frame, .. } = self.cache[&self.current_page];
         let next_page_offset = self.view_port.page_offset + delta;
         let vpw = self.rect.width() as i32 - 2 * self.view_port.margin_width;
-        let vph = self.rect.height() as i32 - 2 * self.view_port.margin_width;
+        let vph = self.page_height() as i32 - 2 * self.view_port.margin_width;
         let vprect = rect![pt!(0), pt!(vpw, vph)] + next_page_offset + frame.min;
 
         if vprect.overlaps(&frame) {
@@ -803,11 +1661,20 @@ impl Reader {
         }
     }
 
+    // Maps a left-to-right page direction to the reading direction of the document.
+    fn page_dir(&self, dir: CycleDir) -> CycleDir {
+        match self.reading_direction {
+            ReadingDirection::Ltr => dir,
+            ReadingDirection::Rtl => dir.opposite(),
+        }
+    }
+
     fn go_to_neighbor(&mut self, dir: CycleDir, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
         if self.chunks.is_empty() {
             return;
         }
 
+        self.last_turn = dir;
         let current_page = self.current_page;
         let page_offset = self.view_port.page_offset;
 
@@ -815,12 +1682,20 @@ impl Reader {
             let neighloc = match dir { 
                 CycleDir::Previous => {
                     match self.view_port.zoom_mode {
+                        ZoomMode::FitToPage if self.is_spread() => {
+                            let mut doc = self.doc.lock().unwrap();
+                            match doc.resolve_location(Location::Previous(current_page)) {
+                                Some(location) => Location::Exact(doc.resolve_location(Location::Previous(location))
+                                                                     .unwrap_or(location)),
+                                None => Location::Previous(current_page),
+                            }
+                        },
                         ZoomMode::FitToPage => Location::Previous(current_page),
                         ZoomMode::FitToWidth => match self.view_port.scroll_mode {
                             ScrollMode::Screen => {
                                 let first_chunk = self.chunks.first().cloned().unwrap();
                                 let mut location = first_chunk.location;
-                                let available_height = self.rect.height() as i32 - 2 * self.view_port.margin_width;
+                                let available_height = self.page_height() as i32 - 2 * self.view_port.margin_width;
                                 let mut height = 0;
 
                                 loop {
@@ -859,7 +1734,7 @@ impl Reader {
                                 Location::Exact(location)
                             },
                             ScrollMode::Page => {
-                                let available_height = self.rect.height() as i32 - 2 * self.view_port.margin_width;
+                                let available_height = self.page_height() as i32 - 2 * self.view_port.margin_width;
                                 if self.view_port.page_offset.y > 0 {
                                     self.view_port.page_offset.y = (self.view_port.page_offset.y - available_height).max(0);
                                     Location::Exact(current_page)
@@ -883,6 +1758,13 @@ impl Reader {
                 },
                 CycleDir::Next => {
                     match self.view_port.zoom_mode {
+                        ZoomMode::FitToPage if self.is_spread() => {
+                            let mut doc = self.doc.lock().unwrap();
+                            match doc.resolve_location(Location::Next(current_page)) {
+                                Some(location) => Location::Next(location),
+                                None => Location::Next(current_page),
+                            }
+                        },
                         ZoomMode::FitToPage => Location::Next(current_page),
                         ZoomMode::FitToWidth => match self.view_port.scroll_mode {
                             ScrollMode::Screen => {
@@ -900,7 +1782,7 @@ impl Reader {
                                 }
                             },
                             ScrollMode::Page => {
-                                let available_height = self.rect.height() as i32 - 2 * self.view_port.margin_width;
+                                let available_height = self.page_height() as i32 - 2 * self.view_port.margin_width;
                                 let frame_height = self.cache[&current_page].frame.height() as i32;
                                 let next_top_offset = self.view_port.page_offset.y + available_height;
                                 if frame_height < available_height || next_top_offset == frame_height {
@@ -936,27 +1818,17 @@ impl Reader {
                 if self.search.is_some() {
                     self.update_results_bar(rq);
                 }
+
+                if dir == CycleDir::Next && !self.finished && self.reached_finished_threshold(context) {
+                    self.finished = true;
+                    self.run_finished_action("Book finished.", hub, rq, context);
+                }
             },
             _ => {
                 match dir {
                     CycleDir::Next => {
                         self.finished = true;
-                        let action = if self.ephemeral {
-                            FinishedAction::Close
-                        } else {
-                            context.settings.reader.finished
-                        };
-                        match action {
-                            FinishedAction::Notify => {
-                                let notif = Notification::new("No next page.".to_string(),
-                                                              hub, rq, context);
-                                self.children.push(Box::new(notif) as Box<dyn View>);
-                            },
-                            FinishedAction::Close => {
-                                self.quit(context);
-                                hub.send(Event::Back).ok();
-                            },
-                        }
+                        self.run_finished_action("No next page.", hub, rq, context);
                     },
                     CycleDir::Previous => {
                         if self.ephemeral {
@@ -965,7 +1837,7 @@ impl Reader {
                         } else {
                             let notif = Notification::new("No previous page.".to_string(),
                                                           hub, rq, context);
-                            self.children.push(Box::new(notif) as Box<dyn View>);
+                            push_notification(&mut self.children, notif, rq, context);
                         }
                     },
                 }
@@ -973,17 +1845,108 @@ impl Reader {
         }
     }
 
-    fn go_to_results_page(&mut self, index: usize, hub: &Hub, rq: &mut RenderQueue, context: &Context) {
-        let mut loc = None;
-        if let Some(ref mut s) = self.search {
-            if index < s.highlights.len() {
-                s.current_page = index;
-                loc = Some(*s.highlights.keys().nth(index).unwrap());
-            }
-        }
-        if let Some(location) = loc {
-            self.current_page = location;
-            self.view_port.page_offset = pt!(0, 0);
+    fn run_finished_action(&mut self, message: &str, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        self.stop_auto_turn();
+        let action = if self.ephemeral {
+            FinishedAction::Close
+        } else {
+            context.settings.reader.finished
+        };
+        match action {
+            FinishedAction::Notify => {
+                let notif = Notification::new(message.to_string(), hub, rq, context);
+                push_notification(&mut self.children, notif, rq, context);
+            },
+            FinishedAction::Close => {
+                self.quit(context);
+                hub.send(Event::Back).ok();
+            },
+            FinishedAction::NextInSeries => {
+                self.quit(context);
+                hub.send(Event::Back).ok();
+                hub.send(Event::OpenNextInSeries(self.info.file.path.clone())).ok();
+            },
+        }
+    }
+
+    // Whether the current page lies within the trailing pages deemed skippable
+    // by the finished thresholds.
+    fn reached_finished_threshold(&self, context: &Context) -> bool {
+        if self.ephemeral || self.pages_count == 0 {
+            return false;
+        }
+        let settings = &context.settings.reader;
+        let (pages_left, progress) = if self.synthetic {
+            let bytes_left = self.pages_count.saturating_sub(self.current_page) as f64;
+            ((bytes_left / BYTES_PER_PAGE).ceil() as usize,
+             self.current_page as f32 / self.pages_count as f32)
+        } else {
+            (self.pages_count - self.current_page,
+             (self.current_page + 1) as f32 / self.pages_count as f32)
+        };
+        // The pages left include the current one.
+        pages_left.saturating_sub(1) < settings.finished_pages_left ||
+        progress > settings.finished_progress
+    }
+
+    fn toggle_auto_turn(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        if self.auto_turn.is_some() {
+            self.stop_auto_turn();
+            let notif = Notification::new("Auto turn stopped.".to_string(), hub, rq, context);
+            push_notification(&mut self.children, notif, rq, context);
+            return;
+        }
+
+        let interval = context.settings.reader.auto_turn_interval.max(1.0);
+        let running = Arc::new(AtomicBool::new(true));
+        let running2 = Arc::clone(&running);
+        let hub2 = hub.clone();
+
+        thread::spawn(move || {
+            loop {
+                thread::sleep(Duration::from_secs_f32(interval));
+                if !running2.load(AtomicOrdering::Relaxed) {
+                    break;
+                }
+                hub2.send(Event::Page(CycleDir::Next)).ok();
+            }
+        });
+
+        self.auto_turn = Some(running);
+        let notif = Notification::new(format!("Turning pages every {}s.", interval), hub, rq, context);
+        push_notification(&mut self.children, notif, rq, context);
+    }
+
+    // Whether other threads hold the document, after waiting for a stopped search thread to release it.
+    fn doc_is_shared(&mut self) -> bool {
+        if self.search.as_ref().map_or(true, |s| !s.running.load(AtomicOrdering::Relaxed)) {
+            if let Some(handle) = self.search_thread.take() {
+                handle.join().ok();
+            }
+        }
+        if let Some(handle) = self.word_count_thread.take_if(|handle| handle.is_finished()) {
+            handle.join().ok();
+        }
+        Arc::strong_count(&self.doc) > 1
+    }
+
+    fn stop_auto_turn(&mut self) {
+        if let Some(running) = self.auto_turn.take() {
+            running.store(false, AtomicOrdering::Relaxed);
+        }
+    }
+
+    fn go_to_results_page(&mut self, index: usize, hub: &Hub, rq: &mut RenderQueue, context: &Context) {
+        let mut loc = None;
+        if let Some(ref mut s) = self.search {
+            if index < s.highlights.len() {
+                s.current_page = index;
+                loc = Some(*s.highlights.keys().nth(index).unwrap());
+            }
+        }
+        if let Some(location) = loc {
+            self.current_page = location;
+            self.view_port.page_offset = pt!(0, 0);
             self.selection = None;
             self.state = State::Idle;
             self.update_results_bar(rq);
@@ -1007,6 +1970,12 @@ impl Reader {
             }
             self.view_port.page_offset = pt!(0, 0);
             self.current_page = location;
+            if self.view_port.zoom_mode != ZoomMode::FitToPage {
+                let index = self.search.as_ref()
+                                .and_then(|s| s.highlights.get(&location))
+                                .map_or(0, |groups| if dir == CycleDir::Next { 0 } else { groups.len().saturating_sub(1) });
+                self.center_on_match(location, index);
+            }
             self.update_results_bar(rq);
             self.update_bottom_bar(rq);
             self.update(None, hub, rq, context);
@@ -1020,6 +1989,80 @@ impl Reader {
         }
     }
 
+    // Steps through the matches of the current page before moving to the neighboring results page.
+    // When the whole page is visible, there's nothing to scroll to and we only move page-wise.
+    fn go_to_results_match(&mut self, dir: CycleDir, hub: &Hub, rq: &mut RenderQueue, context: &Context) {
+        if self.view_port.zoom_mode == ZoomMode::FitToPage {
+            self.go_to_results_neighbor(dir, hub, rq, context);
+            return;
+        }
+
+        let current_page = self.current_page;
+        let target = self.search.as_ref().and_then(|s| {
+            let count = s.highlights.get(&current_page)?.len();
+            let index = s.current_match.filter(|m| m.0 == current_page).map(|m| m.1);
+            match (dir, index) {
+                (CycleDir::Next, None) if count > 0 => Some(0),
+                (CycleDir::Next, Some(i)) if i + 1 < count => Some(i + 1),
+                (CycleDir::Previous, None) if count > 0 => Some(count - 1),
+                (CycleDir::Previous, Some(i)) if i > 0 => Some(i - 1),
+                _ => None,
+            }
+        });
+
+        if let Some(index) = target {
+            self.center_on_match(current_page, index);
+            self.update(None, hub, rq, context);
+            return;
+        }
+
+        self.go_to_results_neighbor(dir, hub, rq, context);
+    }
+
+    // Sets the page offset so that the given match is centered within the viewport.
+    fn center_on_match(&mut self, location: usize, index: usize) {
+        let rects = match self.search.as_mut() {
+            Some(s) => {
+                s.current_match = Some((location, index));
+                s.highlights.get(&location).and_then(|groups| groups.get(index)).cloned()
+            },
+            None => None,
+        };
+
+        let rects = match rects {
+            Some(rects) if !rects.is_empty() => rects,
+            _ => return,
+        };
+
+        self.load_pixmap(location);
+        let Resource { frame, scale, .. } = self.cache[&location];
+        let rect = rects.iter()
+                        .map(|b| (*b * scale).to_rect())
+                        .reduce(|mut a, b| { a.absorb(&b); a })
+                        .unwrap() - frame.min;
+        let center = pt!((rect.min.x + rect.max.x) / 2, (rect.min.y + rect.max.y) / 2);
+        let smw = self.view_port.margin_width;
+        let vpw = self.rect.width() as i32 - 2 * smw;
+        let vph = self.page_height() as i32 - 2 * smw;
+        let frame_width = frame.width() as i32;
+        let frame_height = frame.height() as i32;
+
+        match self.view_port.zoom_mode {
+            ZoomMode::FitToWidth => {
+                let max_offset = match self.view_port.scroll_mode {
+                    ScrollMode::Page => (frame_height - vph).max(0),
+                    ScrollMode::Screen => frame_height - 1,
+                };
+                self.view_port.page_offset.y = (center.y - vph / 2).clamp(0, max_offset);
+            },
+            ZoomMode::Custom(_) => {
+                self.view_port.page_offset = pt!((center.x - vpw / 2).clamp(0, (frame_width - vpw).max(0)),
+                                                 (center.y - vph / 2).clamp(0, (frame_height - vph).max(0)));
+            },
+            ZoomMode::FitToPage => (),
+        }
+    }
+
     fn update_bottom_bar(&mut self, rq: &mut RenderQueue) {
         let current_page = self.current_page;
         if let Some(index) = locate::<BottomBar>(self) {
@@ -1027,6 +2070,8 @@ impl Reader {
                 let chapter = self.chapter();
                 (chapter.title.clone(), chapter.remain)
             };
+            let time_left = self.time_left();
+            let label = self.page_label();
             let mut doc = self.doc.lock().unwrap();
             let bottom_bar = self.children[index].as_mut().downcast_mut::<BottomBar>().unwrap();
             let neighbors = Neighbors {
@@ -1035,12 +2080,53 @@ impl Reader {
             };
             bottom_bar.update_chapter_label(title, progress, rq);
             bottom_bar.update_page_label(current_page, self.pages_count, rq);
+            bottom_bar.update_printed_label(label, rq);
+            bottom_bar.update_time_left(time_left, rq);
             bottom_bar.update_icons(&neighbors, rq);
 
         }
         self.set_scrubber(current_page, rq);
     }
 
+    fn time_left(&self) -> Option<String> {
+        if !self.show_time_left {
+            return None;
+        }
+        self.book_time_left()
+    }
+
+    // Counting the words of a fixed-layout document requires extracting the text of every page,
+    // hence the counting happens in the background.
+    // The words are counted one part at a time, so that the pages can still be loaded meanwhile.
+    fn show_word_position(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        if self.word_count_thread.as_ref().is_some_and(|handle| !handle.is_finished()) {
+            return;
+        }
+        if !self.words_counted {
+            self.words_counted = true;
+            let notif = Notification::new("Counting words…".to_string(), hub, rq, context);
+            push_notification(&mut self.children, notif, rq, context);
+        }
+        let doc2 = self.doc.clone();
+        let hub2 = hub.clone();
+        let location = self.current_page;
+        self.word_count_thread = Some(thread::spawn(move || {
+            while doc2.lock().unwrap().count_words_step() {}
+            let mut doc = doc2.lock().unwrap();
+            let message = match (doc.word_offset(location), doc.word_count()) {
+                (Some(offset), Some(total)) => format!("Word {} of {}.", format_count(offset.min(total)), format_count(total)),
+                _ => "Can't count the words of this document.".to_string(),
+            };
+            hub2.send(Event::Notify(message)).ok();
+        }));
+    }
+
+    // Estimated time needed to finish the book, once the reading pace is known.
+    fn book_time_left(&self) -> Option<String> {
+        self.pace.minutes_left(self.pages_count.saturating_sub(self.current_page))
+            .map(format_duration)
+    }
+
     fn set_scrubber(&mut self, loc: usize, rq: &mut RenderQueue) {
         if let Some(index) = locate::<Scrubber>(self) {
             let scrubber = self.children[index].as_mut().downcast_mut::<Scrubber>().unwrap();
@@ -1066,7 +2152,7 @@ impl Reader {
                                       .unwrap_or_else(|| settings.reader.font_family.clone());
                 tool_bar.update_font_family(font_family, rq);
                 let font_size = self.info.reader.as_ref()
-                                    .and_then(|r| r.font_size)
+                                    .and_then(|r| r.font_size(settings.reader.font_size))
                                     .unwrap_or(settings.reader.font_size);
                 tool_bar.update_font_size_slider(font_size, rq);
                 let text_align = self.info.reader.as_ref()
@@ -1083,8 +2169,8 @@ impl Reader {
             }
             let reflowable = self.reflowable;
             let margin_width = self.info.reader.as_ref()
-                                   .and_then(|r| if reflowable { r.margin_width } else { r.screen_margin_width })
-                                   .unwrap_or_else(|| if reflowable { settings.reader.margin_width } else { 0 });
+                                   .and_then(|r| r.margin_width(reflowable))
+                                   .unwrap_or(if reflowable { settings.reader.margin_width } else { 0 });
             tool_bar.update_margin_width(margin_width, rq);
         }
     }
@@ -1105,18 +2191,80 @@ impl Reader {
         }
     }
 
+    // Collect the images that shouldn't be inverted, either because the whole screen is,
+    // or because only the text is. The minimum size only applies to the latter.
     #[inline]
-    fn update_noninverted_regions(&mut self, inverted: bool) {
+    fn update_noninverted_regions(&mut self, inverted: bool, context: &Context) {
         self.noninverted_regions.clear();
-        if inverted {
+        if inverted || self.image_preserve {
+            let min_size = if self.image_preserve {
+                scale_by_dpi(context.settings.reader.image_preserve_min_size, CURRENT_DEVICE.dpi)
+            } else {
+                0.0
+            };
             for chunk in &self.chunks {
-                if let Some((images, _)) = self.doc.lock().unwrap().images(Location::Exact(chunk.location)) {
-                    self.noninverted_regions.insert(chunk.location, images);
+                if let Some((mut images, _)) = self.doc.lock().unwrap().images(Location::Exact(chunk.location)) {
+                    images.retain(|img| img.width() * chunk.scale >= min_size &&
+                                        img.height() * chunk.scale >= min_size);
+                    if !images.is_empty() {
+                        self.noninverted_regions.insert(chunk.location, images);
+                    }
                 }
             }
         }
     }
 
+    // Returns the word nearest to the given point, and its rectangle on screen.
+    fn nearest_word(&self, center: Point) -> Option<(BoundedText, Rectangle)> {
+        let mut found = None;
+        let mut dmin = u32::MAX;
+        let dmax = (scale_by_dpi(RECT_DIST_JITTER, CURRENT_DEVICE.dpi) as i32).pow(2) as u32;
+
+        for chunk in &self.chunks {
+            for word in &self.text[&chunk.location] {
+                let rect = (word.rect * chunk.scale).to_rect() - chunk.frame.min + chunk.position;
+                let d = center.rdist2(&rect);
+                if d < dmax && d < dmin {
+                    dmin = d;
+                    found = Some((word.clone(), rect));
+                }
+            }
+        }
+
+        found
+    }
+
+    // Returns the text range of the search match drawn under the given point,
+    // unless it already belongs to an annotation.
+    fn search_match_at(&self, pt: Point) -> Option<[TextLocation; 2]> {
+        let s = self.search.as_ref()?;
+        for chunk in &self.chunks {
+            let groups = match s.highlights.get(&chunk.location) {
+                Some(groups) => groups,
+                None => continue,
+            };
+            for rects in groups {
+                let rects = rects.iter()
+                                 .map(|r| (*r * chunk.scale).to_rect() - chunk.frame.min + chunk.position)
+                                 .collect::<Vec<Rectangle>>();
+                if !rects.iter().any(|r| r.includes(pt)) {
+                    continue;
+                }
+                // The match rectangles are the rectangles of the matched words.
+                let mut locations = self.text[&chunk.location].iter().filter(|word| {
+                    let center = ((word.rect * chunk.scale).to_rect() - chunk.frame.min + chunk.position).center();
+                    rects.iter().any(|r| r.includes(center))
+                }).map(|word| word.location);
+                let start = locations.next()?;
+                let end = locations.next_back().unwrap_or(start);
+                let annotated = self.annotations.values().flatten()
+                                    .any(|annot| start <= annot.selection[1] && end >= annot.selection[0]);
+                return if annotated { None } else { Some([start, end]) };
+            }
+        }
+        None
+    }
+
     #[inline]
     fn update_annotations(&mut self) {
         self.annotations.clear();
@@ -1139,16 +2287,27 @@ impl Reader {
         }
     }
 
-    fn get_update_mode(&self, check_chapter_start: bool, context: &Context) -> UpdateMode {
+    fn refresh_rate(&self, context: &Context) -> u8 {
         let pair = context.settings.reader.refresh_rate.by_kind
                                    .get(&self.info.file.kind)
                                    .unwrap_or_else(|| &context.settings.reader.refresh_rate.global);
-        let refresh_rate = if context.fb.inverted() { pair.inverted } else { pair.regular };
+        if context.fb.inverted() { pair.inverted } else { pair.regular }
+    }
+
+    fn refresh_at_chapter_start(&self, context: &Context) -> bool {
+        let refresh_rate = &context.settings.reader.refresh_rate;
+        refresh_rate.chapter_start_by_kind.get(&self.info.file.kind)
+                    .cloned()
+                    .unwrap_or(refresh_rate.chapter_start)
+    }
+
+    fn get_update_mode(&self, check_chapter_start: bool, context: &Context) -> UpdateMode {
+        let refresh_rate = self.refresh_rate(context);
         // if due for full refresh
         if refresh_rate > 0 && self.page_turns + 1 >= refresh_rate as usize
            ||
            // or start of chapter
-           check_chapter_start && context.settings.reader.refresh_rate.chapter_start
+           check_chapter_start && self.refresh_at_chapter_start(context)
            && self.page_turns > 1 // ignore recent refresh and very short chapters
            && self.chapter().progress == 0.0 {
             UpdateMode::Full
@@ -1165,22 +2324,99 @@ impl Reader {
             self.page_turns += 1;
         }
 
+        if self.refresh_debug.is_some() {
+            self.refresh_debug = Some((update_mode, self.refresh_rate(context)));
+        }
+
+        // The pages are fitted again between the bars of the current mode.
+        let pinned_heights = self.pinned_bars_heights();
+        if pinned_heights != self.pinned_heights {
+            self.pinned_heights = pinned_heights;
+            self.cache.clear();
+        }
+
+        if self.current_page != self.session.page {
+            // The ruler starts over from the first line of a new page.
+            if let Some(y) = self.ruler.as_mut() {
+                *y = self.rect.min.y;
+            }
+            self.focused_annotation = None;
+            self.session.page = self.current_page;
+            self.session.page_turns += 1;
+        }
+
+        if (self.show_time_left || self.progress_bar.show_time_left) && self.current_page != self.pace.page {
+            // Only regular page turns tell us something about the reading speed.
+            let next_page = self.chunks.last().and_then(|c| {
+                self.doc.lock().ok()
+                    .and_then(|mut doc| doc.resolve_location(Location::Next(c.location)))
+            });
+            let words_count = if next_page == Some(self.current_page) {
+                self.chunks.iter()
+                    .filter_map(|c| self.text.get(&c.location))
+                    .map(|words| words.len())
+                    .sum()
+            } else {
+                0
+            };
+            self.pace.record(self.current_page, words_count);
+        }
+
+        // The stashed selection might not be visible anymore.
+        self.last_selection = None;
         self.chunks.clear();
         let mut location = self.current_page;
         let smw = self.view_port.margin_width;
+        let top = self.pinned_bars_heights().0;
 
         match self.view_port.zoom_mode {
+            ZoomMode::FitToPage if self.is_spread() => {
+                let next_location = self.doc.lock().ok()
+                                        .and_then(|mut doc| doc.resolve_location(Location::Next(location)));
+                let locations = [Some(location), next_location];
+                let gutter = self.spread_gutter();
+                let mut width = -gutter;
+                for loc in locations.iter().flatten() {
+                    self.load_pixmap(*loc);
+                    self.load_text(*loc);
+                    width += self.cache[loc].frame.width() as i32 + gutter;
+                }
+                let rtl = self.reading_direction == ReadingDirection::Rtl;
+                let mut dx = smw + (self.rect.width() as i32 - width - 2 * smw) / 2;
+                // Right-to-left spreads show their first page on the right.
+                if rtl {
+                    dx += width;
+                }
+                for loc in locations.iter().flatten() {
+                    let Resource { frame, scale, .. } = self.cache[loc];
+                    let dy = top + smw + ((self.page_height() - frame.height()) as i32 - 2 * smw) / 2;
+                    if rtl {
+                        dx -= frame.width() as i32;
+                    }
+                    self.chunks.push(RenderChunk { frame, location: *loc, position: pt!(dx, dy), scale });
+                    if rtl {
+                        dx -= gutter;
+                    } else {
+                        dx += frame.width() as i32 + gutter;
+                    }
+                }
+            },
             ZoomMode::FitToPage => {
                 self.load_pixmap(location);
                 self.load_text(location);
                 let Resource { frame, scale, .. } = self.cache[&location];
-                let dx = smw + ((self.rect.width() - frame.width()) as i32 - 2 * smw) / 2;
-                let dy = smw + ((self.rect.height() - frame.height()) as i32 - 2 * smw) / 2;
+                let slack = (self.rect.width() - frame.width()) as i32 - 2 * smw;
+                let dx = smw + match self.view_port.page_alignment {
+                    PageAlignment::Left => 0,
+                    PageAlignment::Center => slack / 2,
+                    PageAlignment::Right => slack,
+                };
+                let dy = top + smw + ((self.page_height() - frame.height()) as i32 - 2 * smw) / 2;
                 self.chunks.push(RenderChunk { frame, location, position: pt!(dx, dy), scale });
             },
             ZoomMode::FitToWidth => match self.view_port.scroll_mode {
                 ScrollMode::Screen => {
-                    let available_height = self.rect.height() as i32 - 2 * smw;
+                    let available_height = self.page_height() as i32 - 2 * smw;
                     let mut height = 0;
                     while height < available_height {
                         self.load_pixmap(location);
@@ -1189,7 +2425,7 @@ impl Reader {
                         if location == self.current_page {
                             frame.min.y += self.view_port.page_offset.y;
                         }
-                        let position = pt!(smw, smw + height);
+                        let position = pt!(smw, top + smw + height);
                         self.chunks.push(RenderChunk { frame, location, position, scale });
                         height += frame.height() as i32;
                         if let Ok(mut doc) = self.doc.lock() {
@@ -1222,11 +2458,11 @@ impl Reader {
                 ScrollMode::Page => {
                     self.load_pixmap(location);
                     self.load_text(location);
-                    let available_height = self.rect.height() as i32 - 2 * smw;
+                    let available_height = self.page_height() as i32 - 2 * smw;
                     let Resource { mut frame, scale, .. } = self.cache[&location];
                     frame.min.y += self.view_port.page_offset.y;
                     frame.max.y = (frame.min.y + available_height).min(frame.max.y);
-                    let position = pt!(smw, smw + (available_height - frame.height() as i32) / 2);
+                    let position = pt!(smw, top + smw + (available_height - frame.height() as i32) / 2);
                     self.chunks.push(RenderChunk { frame, location, position, scale });
                 },
             },
@@ -1235,10 +2471,10 @@ impl Reader {
                 self.load_text(location);
                 let Resource { frame, scale, .. } = self.cache[&location];
                 let vpw = self.rect.width() as i32 - 2 * smw;
-                let vph = self.rect.height() as i32 - 2 * smw;
+                let vph = self.page_height() as i32 - 2 * smw;
                 let vpr = rect![pt!(0), pt!(vpw, vph)] + self.view_port.page_offset + frame.min;
                 if let Some(rect) = frame.intersection(&vpr) {
-                    let position = pt!(smw) + rect.min - vpr.min;
+                    let position = pt!(smw, top + smw) + rect.min - vpr.min;
                     self.chunks.push(RenderChunk { frame: rect, location, position, scale });
                 }
             },
@@ -1248,19 +2484,37 @@ impl Reader {
         let first_location = self.chunks.first().map(|c| c.location).unwrap();
         let last_location = self.chunks.last().map(|c| c.location).unwrap();
 
-        while self.cache.len() > 3 {
+        // Always keep the visible locations and the prefetch windows on both sides,
+        // and more pages as long as they fit the cache size. The pages lying in the
+        // direction of the last turn are evicted last.
+        let ahead = self.prefetch_ahead(context);
+        let behind = (ahead / 2).max(1);
+        let (left_keep, right_keep) = match self.last_turn {
+            CycleDir::Next => (behind, ahead),
+            CycleDir::Previous => (ahead, behind),
+        };
+        let visible_count = self.cache.range(first_location..=last_location).count();
+        let max_size = context.settings.reader.cache_size * 1024 * 1024;
+        let mut size: usize = self.cache.values().map(|r| r.pixmap.data.len()).sum();
+        while self.cache.len() > visible_count + left_keep + right_keep && size > max_size {
             let left_count = self.cache.range(..first_location).count();
             let right_count = self.cache.range(last_location+1..).count();
-            let extremum = if left_count >= right_count {
+            let evict_left = match self.last_turn {
+                CycleDir::Next => left_count > left_keep || right_count <= right_keep,
+                CycleDir::Previous => right_count <= right_keep,
+            };
+            let extremum = if evict_left {
                 self.cache.keys().next().cloned().unwrap()
             } else {
                 self.cache.keys().next_back().cloned().unwrap()
             };
-            self.cache.remove(&extremum);
+            if let Some(resource) = self.cache.remove(&extremum) {
+                size -= resource.pixmap.data.len();
+            }
         }
 
         self.update_annotations();
-        self.update_noninverted_regions(context.fb.inverted());
+        self.update_noninverted_regions(context.fb.inverted(), context);
 
         if self.view_port.zoom_mode == ZoomMode::FitToPage ||
            self.view_port.zoom_mode == ZoomMode::FitToWidth {
@@ -1268,22 +2522,57 @@ impl Reader {
             let hub2 = hub.clone();
             thread::spawn(move || {
                 let mut doc = doc2.lock().unwrap();
-                if let Some(next_location) = doc.resolve_location(Location::Next(last_location)) {
-                    hub2.send(Event::LoadPixmap(next_location)).ok();
+                let mut location = last_location;
+                for _ in 0..ahead {
+                    if let Some(next_location) = doc.resolve_location(Location::Next(location)) {
+                        hub2.send(Event::LoadPixmap(next_location)).ok();
+                        location = next_location;
+                    } else {
+                        break;
+                    }
                 }
             });
             let doc3 = self.doc.clone();
             let hub3 = hub.clone();
             thread::spawn(move || {
                 let mut doc = doc3.lock().unwrap();
-                if let Some(previous_location) = doc.resolve_location(Location::Previous(first_location)) {
-                    hub3.send(Event::LoadPixmap(previous_location)).ok();
+                let mut location = first_location;
+                for _ in 0..behind {
+                    if let Some(previous_location) = doc.resolve_location(Location::Previous(location)) {
+                        hub3.send(Event::LoadPixmap(previous_location)).ok();
+                        location = previous_location;
+                    } else {
+                        break;
+                    }
                 }
             });
         }
     }
 
-    fn search(&mut self, text: &str, query: Regex, hub: &Hub, rq: &mut RenderQueue) {
+    // Scrolling continuously reaches the next locations sooner, and resolving them
+    // might require laying out the next chapter of a reflowable document.
+    fn prefetch_ahead(&self, context: &Context) -> usize {
+        let read_ahead = context.settings.reader.read_ahead.max(1);
+        if self.view_port.zoom_mode == ZoomMode::FitToWidth &&
+           self.view_port.scroll_mode == ScrollMode::Screen {
+            read_ahead.max(PREFETCH_AHEAD)
+        } else {
+            read_ahead
+        }
+    }
+
+    fn search_query(&self, text: &str) -> Option<Regex> {
+        make_query_with(text, self.search_case_sensitive, self.search_whole_word)
+    }
+
+    fn search(&mut self, text: &str, query: Regex, max_results: usize, hub: &Hub, rq: &mut RenderQueue) {
+        self.last_query = Some(text.to_string());
+        self.hide_ruler(rq);
+
+        if let Some(ref s) = self.search {
+            stop_thread(&s.running, self.search_thread.take());
+        }
+
         let s = Search {
             query: text.to_string(),
             .. Default::default()
@@ -1292,102 +2581,142 @@ impl Reader {
         // trigger draw stop button
         hub.send(Event::Update(UpdateMode::Gui)).ok();
 
+        self.search_thread = Some(spawn_search(Arc::clone(&self.doc), query, Arc::clone(&s.running),
+                                               self.search_direction, self.pages_count, max_results, hub.clone()));
+
+        if self.search.is_some() {
+            self.render_results(rq);
+        }
+
+        self.search = Some(s);
+    }
+
+    // Looks for the first match, in the next few pages, of the text typed so far.
+    fn preview_search(&mut self, text: &str, origin: usize, hub: &Hub) {
+        let query = match self.search_query(text).filter(|_| !text.trim().is_empty()) {
+            Some(query) => query,
+            None => return,
+        };
+
+        if let Some(sp) = self.search_preview.as_mut() {
+            stop_thread(&sp.running, sp.thread.take());
+        }
+
+        if self.doc_is_shared() {
+            return;
+        }
+
         let hub2 = hub.clone();
         let doc2 = Arc::clone(&self.doc);
-        let running = Arc::clone(&s.running);
-        let search_direction = self.search_direction;
-        let pages_count = self.pages_count;
+        let running = Arc::new(AtomicBool::new(true));
+        let running2 = Arc::clone(&running);
+        let text = text.to_string();
 
-        thread::spawn(move || {
-            let mut results_count = 0;
-            let mut loc = match search_direction {
-                LinearDir::Forward => Location::Exact(0),
-                LinearDir::Backward => Location::Exact(pages_count-1),
-            };
+        let handle = thread::spawn(move || {
+            let mut result = None;
+            let mut loc = Location::Exact(origin);
 
-            loop {
-                if !running.load(AtomicOrdering::Relaxed) {
-                    break;
+            for _ in 0..SEARCH_PREVIEW_PAGES {
+                if !running2.load(AtomicOrdering::Relaxed) {
+                    return;
                 }
-
                 let mut doc = doc2.lock().unwrap();
-                let mut text = String::new();
-                let mut rects = BTreeMap::new();
-
-                if let Some(location) = doc.resolve_location(loc) {
-                    if let Some((ref words, _)) = doc.words(Location::Exact(location)) {
-                        if !words.is_empty() {
-                            let mut end_offset = 0;
-                            for word in words {
-                                if !running.load(AtomicOrdering::Relaxed) {
-                                    break;
-                                }
-                                let (is_dyn, offset) =
-                                    if let TextLocation::Dynamic(offset) = word.location {
-                                        (true, offset)
-                                    } else {
-                                        (false, 1)
-                                    };
-                                if text.ends_with('\u{00AD}') {
-                                    text.pop();
-                                } else if !text.ends_with('-') && !text.is_empty() && offset > end_offset {
-                                    text.push(' ');
-                                }
-                                rects.insert(text.len(), word.rect);
-                                text += &word.text;
-                                if is_dyn {
-                                    end_offset = offset + word.text.len();
-                                }
-                            }
-                        }
-                        for m in query.find_iter(&text) {
-                            if let Some((first, _)) = rects.range(..= m.start()).next_back() {
-                                let mut match_rects = Vec::new();
-                                for (_, rect) in rects.range(*first .. m.end()) {
-                                    if !running.load(AtomicOrdering::Relaxed) {
-                                        break;
-                                    }
-                                    match_rects.push(*rect);
-                                }
-                                results_count += 1;
-                                hub2.send(Event::SearchResult(location, match_rects)).ok();
-                                if results_count >= MAX_SEARCH_RESULTS && running.load(AtomicOrdering::Relaxed) {
-                                    hub2.send(Event::Notify(format!("Maximum {MAX_SEARCH_RESULTS} results reached. Search stopped."))).ok();
-                                    running.store(false, AtomicOrdering::Relaxed);
-                                    break;
-                                }
-                            }
-                        }
+                let location = match doc.resolve_location(loc) {
+                    Some(location) => location,
+                    None => break,
+                };
+                if let Some((ref words, _)) = doc.words(Location::Exact(location)) {
+                    let (text, rects) = words_as_text(words);
+                    result = query.find(&text)
+                                  .and_then(|m| match_rects(&rects, &m))
+                                  .map(|rects| (location, rects));
+                    if result.is_some() {
+                        break;
                     }
-                    loc = match search_direction {
-                        LinearDir::Forward => Location::Next(location),
-                        LinearDir::Backward => Location::Previous(location),
-                    };
-                } else {
-                    break;
                 }
+                loc = Location::Next(location);
             }
 
-            running.store(false, AtomicOrdering::Relaxed);
-            hub2.send(Event::EndOfSearch).ok();
+            drop(doc2);
+            if running2.load(AtomicOrdering::Relaxed) {
+                hub2.send(Event::SearchPreviewResult(text, result)).ok();
+            }
         });
 
+        if let Some(sp) = self.search_preview.as_mut() {
+            sp.running = running;
+            sp.thread = Some(handle);
+        }
+    }
+
+    fn stop_search_preview(&mut self) {
+        if let Some(mut sp) = self.search_preview.take() {
+            stop_thread(&sp.running, sp.thread.take());
+        }
+    }
+
+    // Displays the first match of the text typed so far as a search with a single result.
+    fn show_search_preview(&mut self, text: &str, origin: usize, result: Option<(usize, Vec<Boundary>)>, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
         if self.search.is_some() {
             self.render_results(rq);
         }
 
-        self.search = Some(s);
+        if let Some((location, rects)) = result {
+            let mut highlights = BTreeMap::new();
+            highlights.insert(location, vec![rects]);
+            self.search = Some(Search {
+                query: text.to_string(),
+                highlights,
+                running: Arc::new(AtomicBool::new(false)),
+                results_count: 1,
+                .. Default::default()
+            });
+            if location == self.current_page {
+                self.render_results(rq);
+            } else {
+                self.go_to_page(location, true, hub, rq, context);
+            }
+        } else {
+            self.search = None;
+            if origin != self.current_page {
+                self.go_to_page(origin, true, hub, rq, context);
+            }
+        }
     }
 
     /// stop search or exit search mode if search already stopped or only 1 page of results
     fn stop_search(&mut self, rq: &mut RenderQueue) {
         if let Some(ref mut s) = self.search {
-            let was_running = s.running.swap(false, AtomicOrdering::Relaxed);
+            let was_running = s.running.load(AtomicOrdering::Relaxed);
+            stop_thread(&s.running, self.search_thread.take());
             let pages_count = s.highlights.len();
             self.render_results(rq);
             if !was_running || pages_count <= 1 {
                 self.search = None;
+                self.forget_search_query();
+            }
+        }
+    }
+
+    fn forget_search_query(&mut self) {
+        self.last_query = None;
+        if let Some(ref mut r) = self.info.reader {
+            r.search_query = None;
+        }
+    }
+
+    // Leaves search mode, stopping the search thread, or enters it by opening the search bar.
+    fn toggle_search_mode(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        if self.search.is_some() || locate::<SearchBar>(self).is_some() {
+            if let Some(s) = self.search.take() {
+                stop_thread(&s.running, self.search_thread.take());
             }
+            self.forget_search_query();
+            self.toggle_results_bar(false, rq, context);
+            self.toggle_search_bar(false, hub, rq, context);
+            self.update(Some(UpdateMode::Partial), hub, rq, context);
+        } else {
+            self.toggle_search_bar(true, hub, rq, context);
         }
     }
 
@@ -1552,6 +2881,7 @@ impl Reader {
                 return;
             }
 
+            self.hide_ruler(rq);
             self.remove_tool_bar(rq);
             self.remove_scrubber(rq);
 
@@ -1573,7 +2903,10 @@ impl Reader {
             let y_min = sp_rect.max.y;
             let rect = rect![self.rect.min.x, y_min,
                              self.rect.max.x, y_min + small_height - thickness];
-            let search_bar = SearchBar::new(rect, ViewId::ReaderSearchInput, "", "", true, context);
+            let query = self.info.reader.as_ref()
+                            .and_then(|r| r.search_query.clone())
+                            .unwrap_or_default();
+            let search_bar = SearchBar::new(rect, ViewId::ReaderSearchInput, "", &query, true, context);
             self.children.insert(index, Box::new(search_bar) as Box<dyn View>);
 
             let separator = Filler::new(sp_rect, BLACK);
@@ -1591,22 +2924,35 @@ impl Reader {
         }
     }
 
+    fn remove_bars(&mut self, hub: &Hub, rq: &mut RenderQueue) {
+        if let (Some(top_index), Some(bottom_index)) = (locate::<TopBar>(self), locate::<BottomBar>(self)) {
+            let mut top_rect = *self.child(top_index).rect();
+            for i in top_index+1 ..= bottom_index {
+                top_rect.absorb(self.child(i).rect());
+            }
+
+            self.children.drain(top_index..=bottom_index);
+
+            rq.add(RenderData::expose(top_rect, UpdateMode::Gui));
+            hub.send(Event::Focus(None)).ok();
+        }
+    }
+
     fn toggle_bars(&mut self, enable: Option<bool>, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
-        if let Some(top_index) = locate::<TopBar>(self) {
+        if enable.is_none() && self.pinned_bars && locate::<TopBar>(self).is_some() {
+            return;
+        }
+
+        if locate::<TopBar>(self).is_some() {
             if let Some(true) = enable {
                 return;
             }
 
-            if let Some(bottom_index) = locate::<BottomBar>(self) {
-                let mut top_rect = *self.child(top_index).rect();
-                for i in top_index+1 ..= bottom_index {
-                    top_rect.absorb(self.child(i).rect());
-                }
-
-                self.children.drain(top_index..=bottom_index);
+            self.remove_bars(hub, rq);
 
-                rq.add(RenderData::expose(top_rect, UpdateMode::Gui));
-                hub.send(Event::Focus(None)).ok();
+            // Pinned bars are rebuilt, to match the current mode, instead of being hidden.
+            if self.pinned_bars {
+                self.toggle_bars(Some(true), hub, rq, context);
             }
         } else {
             if let Some(false) = enable {
@@ -1686,7 +3032,9 @@ impl Reader {
                                                   y_top,
                                                   self.rect.max.x,
                                                   y_top + med_height as i32],
-                                             self.current_page, self.pages_count, self.synthetic);
+                                             self.current_page, self.pages_count, self.synthetic,
+                                             self.reading_direction == ReadingDirection::Rtl,
+                                             &self.chapter_locations(&mut **doc));
                 self.children.insert(index, Box::new(scrubber) as Box<dyn View>);
                 index += 1;
 
@@ -1728,7 +3076,7 @@ impl Reader {
 
             drop(doc);
 
-            let bottom_bar = {
+            let mut bottom_bar = {
                 let chapter = self.chapter();
                 BottomBar::new(rect![self.rect.min.x,
                                      self.rect.max.y - small_height + big_thickness,
@@ -1741,6 +3089,9 @@ impl Reader {
                                &neighbors,
                                self.synthetic)
             };
+            bottom_bar.set_rtl(self.reading_direction == ReadingDirection::Rtl, rq);
+            bottom_bar.update_printed_label(self.page_label(), rq);
+            bottom_bar.update_time_left(self.time_left(), rq);
             self.children.insert(index, Box::new(bottom_bar) as Box<dyn View>);
 
             for i in 0..=index {
@@ -1757,30 +3108,36 @@ impl Reader {
 
             rq.add(RenderData::expose(*self.child(index).rect(), UpdateMode::Gui));
             self.children.remove(index);
+            self.cropping_scheme = None;
         } else {
             if !enable {
                 return;
             }
 
-            self.toggle_bars(Some(false), hub, rq, context);
-
-            let dpi = CURRENT_DEVICE.dpi;
-            let padding = scale_by_dpi(BUTTON_DIAMETER / 2.0, dpi) as i32;
-            let pixmap_rect = rect![self.rect.min + pt!(padding),
-                                    self.rect.max - pt!(padding)];
-
             let margin = self.info.reader.as_ref()
                              .and_then(|r| r.cropping_margins.as_ref()
                                             .map(|c| c.margin(self.current_page)))
                              .cloned().unwrap_or_default();
+            let pixmap = self.margin_cropper_pixmap();
+            self.push_margin_cropper(pixmap, &margin, hub, rq, context);
+        }
+    }
 
-            let mut doc = self.doc.lock().unwrap();
-            let (pixmap, _) = build_pixmap(&pixmap_rect, doc.as_mut(), self.current_page);
+    // Renders the uncropped current page at the size shown by the margin cropper.
+    fn margin_cropper_pixmap(&self) -> Pixmap {
+        let dpi = CURRENT_DEVICE.dpi;
+        let padding = scale_by_dpi(BUTTON_DIAMETER / 2.0, dpi) as i32;
+        let pixmap_rect = rect![self.rect.min + pt!(padding),
+                                self.rect.max - pt!(padding)];
+        let mut doc = self.doc.lock().unwrap();
+        build_pixmap(&pixmap_rect, doc.as_mut(), self.current_page).0
+    }
 
-            let margin_cropper = MarginCropper::new(self.rect, pixmap, &margin, context);
-            rq.add(RenderData::new(margin_cropper.id(), *margin_cropper.rect(), UpdateMode::Gui));
-            self.children.push(Box::new(margin_cropper) as Box<dyn View>);
-        }
+    fn push_margin_cropper(&mut self, pixmap: Pixmap, margin: &Margin, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        self.toggle_bars(Some(false), hub, rq, context);
+        let margin_cropper = MarginCropper::new(self.rect, pixmap, margin, context);
+        rq.add(RenderData::new(margin_cropper.id(), *margin_cropper.rect(), UpdateMode::Gui));
+        self.children.push(Box::new(margin_cropper) as Box<dyn View>);
     }
 
     fn toggle_edit_note(&mut self, text: Option<String>, enable: Option<bool>, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
@@ -1833,16 +3190,171 @@ impl Reader {
         }
     }
 
-    fn remove_scrubber(&mut self, rq: &mut RenderQueue) {
-        if let Some(index) = locate::<Scrubber>(self) {
-            let rect = *self.child(index).rect();
-            self.children.drain(index..=index);
-            rq.add(RenderData::expose(rect, UpdateMode::Gui));
-        }
-    }
-
-    fn toggle_go_to_page(&mut self, enable: Option<bool>, id: ViewId, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
-        let (text, input_id) = if id == ViewId::GoToPage {
+    fn toggle_label_bookmark(&mut self, enable: Option<bool>, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        if let Some(index) = locate_by_id(self, ViewId::LabelBookmark) {
+            if let Some(true) = enable {
+                return;
+            }
+
+            rq.add(RenderData::expose(*self.child(index).rect(), UpdateMode::Gui));
+            self.children.remove(index);
+
+            self.toggle_keyboard(false, None, hub, rq, context);
+        } else {
+            if let Some(false) = enable {
+                return;
+            }
+
+            let mut label_bookmark = NamedInput::new("Bookmark label".to_string(), ViewId::LabelBookmark, ViewId::LabelBookmarkInput, 16, context);
+            if let Some(label) = self.info.reader.as_ref().and_then(|r| r.bookmarks.get(&self.current_page)) {
+                label_bookmark.set_text(label, rq, context);
+            }
+            rq.add(RenderData::new(label_bookmark.id(), *label_bookmark.rect(), UpdateMode::Gui));
+            hub.send(Event::Focus(Some(ViewId::LabelBookmarkInput))).ok();
+
+            self.children.push(Box::new(label_bookmark) as Box<dyn View>);
+        }
+    }
+
+    fn toggle_search_annotations(&mut self, enable: Option<bool>, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        if let Some(index) = locate_by_id(self, ViewId::SearchAnnotations) {
+            if let Some(true) = enable {
+                return;
+            }
+
+            rq.add(RenderData::expose(*self.child(index).rect(), UpdateMode::Gui));
+            self.children.remove(index);
+
+            self.toggle_keyboard(false, None, hub, rq, context);
+        } else {
+            if let Some(false) = enable {
+                return;
+            }
+
+            let search_annotations = NamedInput::new("Search annotations".to_string(), ViewId::SearchAnnotations,
+                                                     ViewId::SearchAnnotationsInput, 16, context);
+            rq.add(RenderData::new(search_annotations.id(), *search_annotations.rect(), UpdateMode::Gui));
+            hub.send(Event::Focus(Some(ViewId::SearchAnnotationsInput))).ok();
+
+            self.children.push(Box::new(search_annotations) as Box<dyn View>);
+        }
+    }
+
+    fn search_annotations(&mut self, text: &str, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        let query = match make_query(text) {
+            Some(query) => query,
+            None => {
+                let notif = Notification::new("Invalid search query.".to_string(), hub, rq, context);
+                push_notification(&mut self.children, notif, rq, context);
+                return;
+            },
+        };
+
+        let mut annotations = self.info.reader.as_ref()
+                                  .map(|r| r.annotations.iter()
+                                            .filter(|annot| query.is_match(&annot.text) || query.is_match(&annot.note))
+                                            .cloned().collect::<Vec<Annotation>>())
+                                  .unwrap_or_default();
+
+        if annotations.is_empty() {
+            let notif = Notification::new("No matching annotations.".to_string(), hub, rq, context);
+            push_notification(&mut self.children, notif, rq, context);
+            return;
+        }
+
+        annotations.sort_by(|a, b| a.selection[0].cmp(&b.selection[0]));
+        self.toggle_bars(Some(false), hub, rq, context);
+        hub.send(Event::OpenHtml(annotations_as_html(&annotations, None), None)).ok();
+    }
+
+    fn export_annotations(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        let mut annotations = self.info.reader.as_ref()
+                                  .map(|r| r.annotations.clone())
+                                  .unwrap_or_default();
+        annotations.sort_by(|a, b| a.selection[0].cmp(&b.selection[0]));
+
+        let annotations = {
+            let mut doc = self.doc.lock().unwrap();
+            let rtoc = self.toc(doc.as_ref()).or_else(|| doc.toc());
+            annotations.into_iter().map(|annot| {
+                let chapter = rtoc.as_ref()
+                                  .and_then(|toc| doc.chapter(annot.selection[0].location(), toc))
+                                  .map(|(c, _, _)| c.title.clone())
+                                  .unwrap_or_default();
+                (chapter, annot)
+            }).collect::<Vec<(String, Annotation)>>()
+        };
+
+        let (path, _) = get_save_path(&self.info.title, "md", context);
+        let msg = match fs::write(&path, annotations_as_markdown(&self.info.title, &annotations)) {
+            Err(e) => format!("{}", e),
+            Ok(()) => format!("Exported annotations to {}.", path),
+        };
+        let notif = Notification::new(msg, hub, rq, context);
+        push_notification(&mut self.children, notif, rq, context);
+    }
+
+    // The bookmarks sidecar of `book.epub` is `book.bookmarks.json`.
+    fn bookmarks_sidecar_path(&self, context: &Context) -> Option<PathBuf> {
+        if self.ephemeral {
+            return None;
+        }
+        Some(context.library.home.join(&self.info.file.path).with_extension(BOOKMARKS_SIDECAR_EXTENSION))
+    }
+
+    fn export_bookmarks(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        let bookmarks = match self.info.reader.as_ref().map(|r| &r.bookmarks) {
+            Some(bookmarks) => bookmarks,
+            None => return,
+        };
+        let path = match self.bookmarks_sidecar_path(context) {
+            Some(path) => path,
+            None => return,
+        };
+        let msg = match save_json(bookmarks, &path) {
+            Err(e) => format!("{}", e),
+            Ok(()) => format!("Exported bookmarks to {}.", path.display()),
+        };
+        let notif = Notification::new(msg, hub, rq, context);
+        push_notification(&mut self.children, notif, rq, context);
+    }
+
+    // The imported bookmarks are added to the existing ones, whose labels are kept
+    // unless they're empty.
+    fn import_bookmarks(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        let path = match self.bookmarks_sidecar_path(context) {
+            Some(path) => path,
+            None => return,
+        };
+        let msg = match load_json::<BTreeMap<usize, String>, _>(&path) {
+            Err(e) => format!("{}", e),
+            Ok(imported) => {
+                let r = self.info.reader.get_or_insert_with(ReaderInfo::default);
+                let count = r.bookmarks.len();
+                for (location, label) in imported.into_iter().filter(|(location, _)| *location < self.pages_count) {
+                    let current = r.bookmarks.entry(location).or_default();
+                    if current.is_empty() {
+                        *current = label;
+                    }
+                }
+                format!("Imported {} new bookmark(s).", r.bookmarks.len() - count)
+            },
+        };
+        rq.add(RenderData::new(self.id, self.bookmark_rect(), UpdateMode::Gui));
+        let notif = Notification::new(msg, hub, rq, context);
+        push_notification(&mut self.children, notif, rq, context);
+    }
+
+    fn remove_scrubber(&mut self, rq: &mut RenderQueue) {
+        if let Some(index) = locate::<Scrubber>(self) {
+            let rect = *self.child(index).rect();
+            self.children.drain(index..=index);
+            rq.add(RenderData::expose(rect, UpdateMode::Gui));
+        }
+    }
+
+    fn toggle_go_to_page(&mut self, enable: Option<bool>, id: ViewId, prefill: Option<&str>, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        let (text, input_id) = if id == ViewId::GoToPage {
             ("Go to page", ViewId::GoToPageInput)
         } else {
             ("Go to results page", ViewId::GoToResultsPageInput)
@@ -1865,7 +3377,11 @@ impl Reader {
 
             self.remove_tool_bar(rq);
             self.remove_scrubber(rq);
-            let go_to_page = NamedInput::new(text.to_string(), id, input_id, 4, context);
+            let input_size = prefill.map_or(4, |text| text.len().max(4));
+            let mut go_to_page = NamedInput::new(text.to_string(), id, input_id, input_size, context);
+            if let Some(text) = prefill {
+                go_to_page.set_text(text, rq, context);
+            }
             rq.add(RenderData::new(go_to_page.id(), *go_to_page.rect(), UpdateMode::Gui));
             hub.send(Event::Focus(Some(input_id))).ok();
 
@@ -1900,6 +3416,12 @@ impl Reader {
                 entries.push(EntryKind::Command("Remove Note".to_string(), EntryId::RemoveAnnotationNote(sel)));
             }
 
+            entries.push(EntryKind::Separator);
+            entries.push(EntryKind::SubMenu("Color".to_string(),
+                HIGHLIGHT_COLORS.iter().map(|&(name, color)| {
+                    EntryKind::RadioButton(name.to_string(), EntryId::SetAnnotationColor(sel, color), annot.color == color)
+                }).collect()));
+
             let selection_menu = Menu::new(rect, ViewId::AnnotationMenu, MenuKind::Contextual, entries, context);
             rq.add(RenderData::new(selection_menu.id(), *selection_menu.rect(), UpdateMode::Gui));
             self.children.push(Box::new(selection_menu) as Box<dyn View>);
@@ -1922,7 +3444,14 @@ impl Reader {
                 EntryKind::Command("Highlight".to_string(), EntryId::HighlightSelection),
                 EntryKind::Command("Add Note".to_string(), EntryId::AnnotateSelection),
                 EntryKind::Command("Adjust Selection".to_string(), EntryId::AdjustSelection),
+                EntryKind::Command("Extend to Sentence".to_string(), EntryId::ExtendToSentence),
             ];
+            if context.settings.clipboard_file.is_some() {
+                entries.insert(2, EntryKind::Command("Copy".to_string(), EntryId::CopySelection));
+            }
+            if context.settings.vocabulary_file.is_some() && !self.info.language.is_empty() {
+                entries.push(EntryKind::Command("Add to Vocabulary".to_string(), EntryId::AddVocabulary));
+            }
 
             if self.info.file.kind == "epub" {
                 let has_extra_css = self.info.reader.as_ref().map_or(false, |r| r.extra_css.is_some());
@@ -1938,11 +3467,18 @@ impl Reader {
                             tweaks.push(EntryKind::Separator);
                         }
                         tweaks.push(EntryKind::Command("Undo last".to_string(), EntryId::UndoLastCssTweak));
-                        tweaks.push(EntryKind::Command("Undo all".to_string(), EntryId::UndoAllCssTweaks));
+                        tweaks.push(EntryKind::SubMenu("Undo all".to_string(),
+                                                       vec![EntryKind::Command("Confirm".to_string(), EntryId::UndoAllCssTweaks)]));
+                    }
+                    if self.css_stash.is_some() {
+                        tweaks.push(EntryKind::Command("Restore all".to_string(), EntryId::RestoreCssTweaks));
                     }
                     if !tweaks.is_empty() {
                         entries.push(EntryKind::Separator);
                         entries.push(EntryKind::Command("Inspect".to_string(), EntryId::ShowCssTweaks));
+                        if let Some(style) = self.last_css_tweak.and_then(|(_, index)| context.settings.css_styles.get(index)) {
+                            entries.push(EntryKind::Command(format!("Repeat {}", style.name), EntryId::RepeatCssTweak));
+                        }
                         entries.push(EntryKind::SubMenu("CSS tweaks".to_string(), tweaks));
                     }
                 }
@@ -1980,27 +3516,50 @@ impl Reader {
             let zoom_mode = self.view_port.zoom_mode;
             let scroll_mode = self.view_port.scroll_mode;
             let sf = if let ZoomMode::Custom(sf) = zoom_mode { sf } else { 1.0 };
-
-            let mut entries = if self.reflowable {
-                vec![EntryKind::SubMenu("Zoom Mode".to_string(), vec![
-                     EntryKind::RadioButton("Fit to Page".to_string(),
-                                            EntryId::SetZoomMode(ZoomMode::FitToPage),
-                                            zoom_mode == ZoomMode::FitToPage),
-                     EntryKind::RadioButton(format!("Custom ({:.1}%)", 100.0 * sf),
-                                            EntryId::SetZoomMode(ZoomMode::Custom(sf)),
-                                            zoom_mode == ZoomMode::Custom(sf))])]
-            } else {
-                vec![EntryKind::SubMenu("Zoom Mode".to_string(), vec![
-                     EntryKind::RadioButton("Fit to Page".to_string(),
-                                            EntryId::SetZoomMode(ZoomMode::FitToPage),
-                                            zoom_mode == ZoomMode::FitToPage),
-                     EntryKind::RadioButton("Fit to Width".to_string(),
-                                            EntryId::SetZoomMode(ZoomMode::FitToWidth),
-                                            zoom_mode == ZoomMode::FitToWidth),
-                     EntryKind::RadioButton(format!("Custom ({:.1}%)", 100.0 * sf),
-                                            EntryId::SetZoomMode(ZoomMode::Custom(sf)),
-                                            zoom_mode == ZoomMode::Custom(sf))])]
-            };
+            let presets = &context.settings.reader.zoom_presets;
+            let is_preset = presets.iter().any(|p| zoom_mode == ZoomMode::Custom(*p));
+
+            let mut zoom_entries = vec![EntryKind::RadioButton("Fit to Page".to_string(),
+                                                               EntryId::SetZoomMode(ZoomMode::FitToPage),
+                                                               zoom_mode == ZoomMode::FitToPage)];
+            if !self.reflowable {
+                zoom_entries.push(EntryKind::RadioButton("Fit to Width".to_string(),
+                                                         EntryId::SetZoomMode(ZoomMode::FitToWidth),
+                                                         zoom_mode == ZoomMode::FitToWidth));
+            }
+            zoom_entries.push(EntryKind::RadioButton(format!("Custom ({:.1}%)", 100.0 * sf),
+                                                     EntryId::SetZoomMode(ZoomMode::Custom(sf)),
+                                                     zoom_mode == ZoomMode::Custom(sf) && !is_preset));
+
+            if !presets.is_empty() {
+                zoom_entries.push(EntryKind::Separator);
+                for (index, p) in presets.iter().enumerate() {
+                    zoom_entries.push(EntryKind::RadioButton(format!("{:.0}%", 100.0 * p),
+                                                             EntryId::SetZoomPreset(index),
+                                                             zoom_mode == ZoomMode::Custom(*p)));
+                }
+                zoom_entries.push(EntryKind::Command("Next Zoom Preset".to_string(), EntryId::CycleZoomPreset));
+            }
+
+            if !self.reflowable {
+                zoom_entries.extend_from_slice(&[
+                     EntryKind::Separator,
+                     EntryKind::CheckBox("Two-Page Spread".to_string(),
+                                         EntryId::ToggleSpread,
+                                         self.view_port.spread),
+                     EntryKind::SubMenu("Page Alignment".to_string(), vec![
+                         EntryKind::RadioButton("Left".to_string(),
+                                                EntryId::SetPageAlignment(PageAlignment::Left),
+                                                self.view_port.page_alignment == PageAlignment::Left),
+                         EntryKind::RadioButton("Center".to_string(),
+                                                EntryId::SetPageAlignment(PageAlignment::Center),
+                                                self.view_port.page_alignment == PageAlignment::Center),
+                         EntryKind::RadioButton("Right".to_string(),
+                                                EntryId::SetPageAlignment(PageAlignment::Right),
+                                                self.view_port.page_alignment == PageAlignment::Right)])]);
+            }
+
+            let mut entries = vec![EntryKind::SubMenu("Zoom Mode".to_string(), zoom_entries)];
 
             entries.push(EntryKind::SubMenu("Scroll Mode".to_string(), vec![
                  EntryKind::RadioButton("Screen".to_string(),
@@ -2016,12 +3575,43 @@ impl Reader {
 
             if self.info.reader.as_ref().map_or(false, |r| !r.annotations.is_empty()) {
                 entries.push(EntryKind::Command("Annotations".to_string(), EntryId::Annotations));
+                entries.push(EntryKind::Command("Search Annotations".to_string(), EntryId::SearchAnnotations));
+                entries.push(EntryKind::Command("Export Annotations".to_string(), EntryId::ExportAnnotations));
+            }
+
+            if self.annotations.values().any(|annots| !annots.is_empty()) {
+                entries.push(EntryKind::SubMenu("Visible Annotations".to_string(), vec![
+                    EntryKind::Command("Next".to_string(), EntryId::NextVisibleAnnotation),
+                    EntryKind::Command("Previous".to_string(), EntryId::PreviousVisibleAnnotation)]));
             }
 
             if self.info.reader.as_ref().map_or(false, |r| !r.bookmarks.is_empty()) {
                 entries.push(EntryKind::Command("Bookmarks".to_string(), EntryId::Bookmarks));
+                if !self.ephemeral {
+                    entries.push(EntryKind::Command("Export Bookmarks".to_string(), EntryId::ExportBookmarks));
+                }
+            }
+
+            if self.bookmarks_sidecar_path(context).map_or(false, |path| path.exists()) {
+                entries.push(EntryKind::Command("Import Bookmarks".to_string(), EntryId::ImportBookmarks));
             }
 
+            if !self.history.is_empty() {
+                entries.push(EntryKind::Command("Jump History".to_string(), EntryId::JumpHistory));
+            }
+
+            if !self.ephemeral {
+                entries.push(EntryKind::Command("Reading Statistics".to_string(), EntryId::ReadingStats));
+                entries.push(EntryKind::Command("Refresh Metadata".to_string(), EntryId::RefreshMetadata));
+            }
+
+            entries.push(EntryKind::Command("Word Position".to_string(), EntryId::WordPosition));
+            entries.push(EntryKind::Command("Touch Zones".to_string(), EntryId::EditTouchZones));
+
+            entries.push(EntryKind::CheckBox("Auto Turn".to_string(),
+                                             EntryId::ToggleAutoTurn,
+                                             self.auto_turn.is_some()));
+
             if !entries.is_empty() {
                 entries.push(EntryKind::Separator);
             }
@@ -2029,14 +3619,53 @@ impl Reader {
             entries.push(EntryKind::CheckBox("Apply Dithering".to_string(),
                                              EntryId::ToggleDithered,
                                              context.fb.dithered()));
+            entries.push(EntryKind::CheckBox("Invert Text Only".to_string(),
+                                             EntryId::ToggleImagePreserve,
+                                             self.image_preserve));
+            entries.push(EntryKind::CheckBox("Reading Ruler".to_string(),
+                                             EntryId::ToggleReadingRuler,
+                                             self.ruler.is_some()));
+            entries.push(EntryKind::CheckBox("Right to Left".to_string(),
+                                             EntryId::ToggleReadingDirection,
+                                             self.reading_direction == ReadingDirection::Rtl));
+            entries.push(EntryKind::Command("Refresh Pages".to_string(), EntryId::RefreshPages));
+
+            let backgrounds = [("Original", None), ("White", Some(WHITE)),
+                               ("Light Gray", Some(GRAY14)), ("Gray", Some(GRAY13))];
+            let backgrounds = backgrounds.iter()
+                                         .map(|&(name, gray)| EntryKind::RadioButton(name.to_string(),
+                                                                                     EntryId::SetBackgroundGray(gray),
+                                                                                     self.contrast.background == gray.map(f32::from)))
+                                         .collect::<Vec<EntryKind>>();
+            entries.push(EntryKind::SubMenu("Background".to_string(), backgrounds));
+
+            if !self.reflowable && !context.settings.contrast_presets.is_empty() {
+                let presets = context.settings.contrast_presets.iter().enumerate()
+                                     .map(|(i, p)| EntryKind::RadioButton(p.name.clone(),
+                                                                          EntryId::ApplyContrastPreset(i),
+                                                                          (p.exponent - self.contrast.exponent).abs() < f32::EPSILON &&
+                                                                          (p.gray - self.contrast.gray).abs() < f32::EPSILON))
+                                     .collect::<Vec<EntryKind>>();
+                entries.push(EntryKind::SubMenu("Contrast".to_string(), presets));
+            }
 
             if self.synthetic {
+                entries.push(EntryKind::Command("Typography".to_string(), EntryId::ShowTypography));
+                if self.info.file.kind == "epub" {
+                    entries.push(EntryKind::CheckBox("Publisher Fonts".to_string(),
+                                                     EntryId::TogglePublisherFonts,
+                                                     self.publisher_fonts(context)));
+                }
+                entries.push(EntryKind::CheckBox("Hyphenate".to_string(),
+                                                 EntryId::ToggleHyphenation,
+                                                 self.hyphenate(context)));
                 if self.info.reader.as_ref().map_or(false,
                                                     |r| r.font_family.is_some()
                                                     || r.font_size.is_some()
                                                     || r.margin_width.is_some()
                                                     || r.text_align.is_some()
-                                                    || r.line_height.is_some()) {
+                                                    || r.line_height.is_some()
+                                                    || r.hyphenate.is_some()) {
                     entries.push(EntryKind::Command("Use default settings".to_string(), EntryId::ResetToDefaults));
                 }
                 let mut themes = context.settings.themes.iter().enumerate()
@@ -2057,13 +3686,20 @@ impl Reader {
                 }
 
                 if self.info.file.kind == "epub" {
-                    if self.info.reader.as_ref().map_or(false, |r| r.extra_css.is_some()) {
-                        let tweaks = vec![
+                    let has_extra_css = self.info.reader.as_ref().map_or(false, |r| r.extra_css.is_some());
+                    if has_extra_css || self.css_stash.is_some() {
+                        let mut tweaks = vec![
                             EntryKind::Command("Show status".to_string(), EntryId::ShowCssTweaks),
                             EntryKind::Separator,
-                            EntryKind::Command("Undo last".to_string(), EntryId::UndoLastCssTweak),
-                            EntryKind::Command("Undo all".to_string(), EntryId::UndoAllCssTweaks),
                         ];
+                        if has_extra_css {
+                            tweaks.push(EntryKind::Command("Undo last".to_string(), EntryId::UndoLastCssTweak));
+                            tweaks.push(EntryKind::SubMenu("Undo all".to_string(),
+                                                           vec![EntryKind::Command("Confirm".to_string(), EntryId::UndoAllCssTweaks)]));
+                        }
+                        if self.css_stash.is_some() {
+                            tweaks.push(EntryKind::Command("Restore all".to_string(), EntryId::RestoreCssTweaks));
+                        }
                         entries.push(EntryKind::SubMenu("CSS tweaks".to_string(), tweaks));
                     } else if !context.settings.css_styles.is_empty() {
                         entries.push(EntryKind::Command("CSS tweaks".to_string(), EntryId::ShowCssTweaks));
@@ -2129,11 +3765,11 @@ impl Reader {
                 return;
             }
 
-            let font_size = self.info.reader.as_ref().and_then(|r| r.font_size)
+            let font_size = self.info.reader.as_ref().and_then(|r| r.font_size(context.settings.reader.font_size))
                                 .unwrap_or(context.settings.reader.font_size);
             let min_font_size = context.settings.reader.font_size / 2.0;
             let max_font_size = 3.0 * context.settings.reader.font_size / 2.0;
-            let entries = (0..=20).filter_map(|v| {
+            let mut entries = (0..=20).filter_map(|v| {
                 let fs = font_size - 1.0 + v as f32 / 10.0;
                 if fs >= min_font_size && fs <= max_font_size {
                     Some(EntryKind::RadioButton(format!("{:.1}", fs),
@@ -2142,7 +3778,13 @@ impl Reader {
                 } else {
                     None
                 }
-            }).collect();
+            }).collect::<Vec<EntryKind>>();
+            let relative = self.info.reader.as_ref()
+                               .map_or(false, |r| r.font_size_relative == Some(true));
+            entries.push(EntryKind::Separator);
+            entries.push(EntryKind::CheckBox("Relative to Default".to_string(),
+                                             EntryId::ToggleRelativeFontSize,
+                                             relative));
             let font_size_menu = Menu::new(rect, ViewId::FontSizeMenu, MenuKind::Contextual, entries, context);
             rq.add(RenderData::new(font_size_menu.id(), *font_size_menu.rect(), UpdateMode::Gui));
             self.children.push(Box::new(font_size_menu) as Box<dyn View>);
@@ -2192,12 +3834,16 @@ impl Reader {
                                   .and_then(|r| r.line_height).unwrap_or(context.settings.reader.line_height);
             let lh_gradient = context.settings.reader.line_height_gradient.clamp(MIN_LINE_HEIGHT_GRADIENT, MAX_LINE_HEIGHT_GRADIENT);
             let epsilon = lh_gradient / 2.0;
-            let entries = (0..=10).map(|x| {
+            let mut entries = (0..=10).map(|x| {
                 let lh = 1.0 + x as f32 * lh_gradient;
                 EntryKind::RadioButton(format!("{:.3}", lh),
                                        EntryId::SetLineHeight(x),
                                        (lh - line_height).abs() < epsilon)
-            }).collect();
+            }).collect::<Vec<EntryKind>>();
+            entries.push(EntryKind::Separator);
+            entries.push(EntryKind::CheckBox("Scale With Font Size".to_string(),
+                                             EntryId::ToggleScaledLineHeight,
+                                             self.scale_line_height(context)));
             let line_height_menu = Menu::new(rect, ViewId::LineHeightMenu, MenuKind::DropDown, entries, context);
             rq.add(RenderData::new(line_height_menu.id(), *line_height_menu.rect(), UpdateMode::Gui));
             self.children.push(Box::new(line_height_menu) as Box<dyn View>);
@@ -2294,7 +3940,7 @@ impl Reader {
         } else {
             if !enable { return; }
             self.toggle_bars(Some(false), hub, rq, context);
-            let font_size = self.info.reader.as_ref().and_then(|r| r.font_size)
+            let font_size = self.info.reader.as_ref().and_then(|r| r.font_size(context.settings.reader.font_size))
                                 .unwrap_or(context.settings.reader.font_size);
             let has_relative_fs = (font_size - context.settings.reader.font_size).abs() > f32::EPSILON;
             let thd = ThemeDialog::new(has_relative_fs, idx, context);
@@ -2303,6 +3949,44 @@ impl Reader {
         }
     }
 
+    fn toggle_typography_dialog(&mut self, enable: bool, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        if let Some(index) = locate::<TypographyDialog>(self) {
+            if enable { return; }
+            rq.add(RenderData::expose(*self.child(index).rect(), UpdateMode::Gui));
+            self.children.remove(index);
+        } else {
+            if !enable { return; }
+            self.toggle_bars(Some(false), hub, rq, context);
+            let settings = &context.settings.reader;
+            let reflowable = self.reflowable;
+            let (font_size, margin_width, line_height, text_align) = {
+                let r = self.info.reader.as_ref();
+                (r.and_then(|r| r.font_size(settings.font_size)).unwrap_or(settings.font_size),
+                 r.and_then(|r| r.margin_width(reflowable))
+                  .unwrap_or(if reflowable { settings.margin_width } else { 0 }),
+                 r.and_then(|r| r.line_height).unwrap_or(settings.line_height),
+                 r.and_then(|r| r.text_align).unwrap_or(settings.text_align))
+            };
+            let lh_gradient = settings.line_height_gradient.clamp(MIN_LINE_HEIGHT_GRADIENT, MAX_LINE_HEIGHT_GRADIENT);
+            let tpd = TypographyDialog::new(font_size, margin_width, line_height, text_align, lh_gradient, context);
+            rq.add(RenderData::new(tpd.id(), *tpd.rect(), UpdateMode::Gui));
+            self.children.push(Box::new(tpd) as Box<dyn View>);
+        }
+    }
+
+    fn toggle_touch_zones(&mut self, enable: bool, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        if let Some(index) = locate::<TouchZones>(self) {
+            if enable { return; }
+            rq.add(RenderData::expose(*self.child(index).rect(), UpdateMode::Gui));
+            self.children.remove(index);
+        } else {
+            if !enable { return; }
+            self.toggle_bars(Some(false), hub, rq, context);
+            let touch_zones = TouchZones::new(self.rect, rq, context);
+            self.children.push(Box::new(touch_zones) as Box<dyn View>);
+        }
+    }
+
     fn toggle_name_theme(&mut self, enable: bool, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
         if let Some(index) = locate_by_id(self, ViewId::NameTheme) {
             if enable { return; }
@@ -2339,13 +4023,13 @@ impl Reader {
                 }
                 if thd.is_on(ThemeProp::RelativeFontSize) {
                     theme.font_size = Some(self.info.reader.as_ref()
-                            .and_then(|r| r.font_size)
+                            .and_then(|r| r.font_size(context.settings.reader.font_size))
                             .unwrap_or(context.settings.reader.font_size)
                         - context.settings.reader.font_size);
                     theme.font_size_relative = Some(true);
                 } else if thd.is_on(ThemeProp::FontSize) {
                     theme.font_size = Some(self.info.reader.as_ref()
-                        .and_then(|r| r.font_size)
+                        .and_then(|r| r.font_size(context.settings.reader.font_size))
                         .unwrap_or(context.settings.reader.font_size));
                 }
                 if thd.is_on(ThemeProp::MarginWidth) {
@@ -2413,8 +4097,8 @@ impl Reader {
 
             let reflowable = self.reflowable;
             let margin_width = self.info.reader.as_ref()
-                                   .and_then(|r| if reflowable { r.margin_width } else { r.screen_margin_width })
-                                   .unwrap_or_else(|| if reflowable { context.settings.reader.margin_width } else { 0 });
+                                   .and_then(|r| r.margin_width(reflowable))
+                                   .unwrap_or(if reflowable { context.settings.reader.margin_width } else { 0 });
             let min_margin_width = context.settings.reader.min_margin_width;
             let max_margin_width = context.settings.reader.max_margin_width;
             let entries = (min_margin_width..=max_margin_width).map(|mw|
@@ -2448,6 +4132,7 @@ impl Reader {
             if has_name {
                 entries.push(EntryKind::Command("Remove Name".to_string(), EntryId::RemovePageName));
             }
+            entries.push(EntryKind::Command("Label Bookmark".to_string(), EntryId::LabelBookmark));
             let names = self.info.reader.as_ref()
                             .map(|r| r.page_names.iter()
                                       .map(|(i, s)| EntryKind::Command(s.to_string(), EntryId::GoTo(*i)))
@@ -2487,7 +4172,14 @@ impl Reader {
                                                           is_split.is_some() && !is_split.unwrap()),
                                    EntryKind::RadioButton("Even/Odd".to_string(),
                                                           EntryId::ApplyCroppings(current_page, PageScheme::EvenOdd),
-                                                          is_split.is_some() && is_split.unwrap())];
+                                                          is_split.is_some() && is_split.unwrap()),
+                                   EntryKind::Separator,
+                                   EntryKind::SubMenu("Detect This Page".to_string(),
+                                                      vec![EntryKind::Command("Any".to_string(),
+                                                                              EntryId::AutoCropMargins(PageScheme::Any)),
+                                                           EntryKind::Command("Even/Odd".to_string(),
+                                                                              EntryId::AutoCropMargins(PageScheme::EvenOdd))]),
+                                   EntryKind::Command("Detect Even/Odd".to_string(), EntryId::DetectCroppings)];
 
             let is_applied = self.info.reader.as_ref()
                                  .map(|r| r.cropping_margins.is_some())
@@ -2521,7 +4213,14 @@ impl Reader {
                                                       self.search_direction == LinearDir::Forward),
                                EntryKind::RadioButton("Backward".to_string(),
                                                       EntryId::SearchDirection(LinearDir::Backward),
-                                                      self.search_direction == LinearDir::Backward)];
+                                                      self.search_direction == LinearDir::Backward),
+                               EntryKind::Separator,
+                               EntryKind::CheckBox("Case Sensitive".to_string(),
+                                                   EntryId::ToggleSearchCaseSensitive,
+                                                   self.search_case_sensitive),
+                               EntryKind::CheckBox("Whole Word".to_string(),
+                                                   EntryId::ToggleSearchWholeWord,
+                                                   self.search_whole_word)];
 
             let search_menu = Menu::new(rect, ViewId::SearchMenu, MenuKind::Contextual, entries, context);
             rq.add(RenderData::new(search_menu.id(), *search_menu.rect(), UpdateMode::Gui));
@@ -2530,44 +4229,123 @@ impl Reader {
     }
 
     fn set_font_size(&mut self, font_size: f32, redraw: bool, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
-        if Arc::strong_count(&self.doc) > 1 {
+        if self.doc_is_shared() {
             return;
         }
 
+        let line_height = if self.scale_line_height(context) {
+            Some(scaled_line_height(font_size, &context.settings.reader))
+        } else {
+            None
+        };
+
         if let Some(ref mut r) = self.info.reader {
-            r.font_size = Some(font_size);
+            r.set_font_size(font_size, context.settings.reader.font_size);
+            if line_height.is_some() {
+                r.line_height = line_height;
+            }
         }
 
         let (width, height) = context.display.dims;
+        let height = layout_height(height, self.pinned_bars);
+        self.relayout(redraw, hub, rq, context, |doc| {
+            if let Some(line_height) = line_height {
+                doc.set_line_height(line_height);
+            }
+            doc.layout(width, height, font_size, CURRENT_DEVICE.dpi);
+        });
+    }
+
+    fn scale_line_height(&self, context: &Context) -> bool {
+        self.info.reader.as_ref().and_then(|r| r.scale_line_height)
+            .unwrap_or(context.settings.reader.scale_line_height)
+    }
+
+    fn toggle_scale_line_height(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        let enable = !self.scale_line_height(context);
+
+        if let Some(ref mut r) = self.info.reader {
+            r.scale_line_height = Some(enable);
+        }
+
+        if enable {
+            let font_size = self.info.reader.as_ref()
+                                .and_then(|r| r.font_size(context.settings.reader.font_size))
+                                .unwrap_or(context.settings.reader.font_size);
+            let line_height = scaled_line_height(font_size, &context.settings.reader);
+            self.set_line_height(line_height, true, hub, rq, context);
+        }
+    }
+
+    // Applies a layout change and returns to the word that was at the top of the screen.
+    // Without visible text, the current page is scaled by the page count ratio.
+    fn relayout<F>(&mut self, redraw: bool, hub: &Hub, rq: &mut RenderQueue, context: &mut Context, apply: F)
+                  where F: FnOnce(&mut dyn Document) {
+        let anchor = if !redraw {
+            None
+        } else if self.synthetic {
+            Some(TextLocation::Dynamic(self.layout_anchor()))
+        } else {
+            self.text_location_range().map(|[min, _]| min)
+        };
+
         {
             let mut doc = self.doc.lock().unwrap();
 
-            doc.layout(width, height, font_size, CURRENT_DEVICE.dpi);
+            apply(&mut **doc);
 
             if !redraw { return; }
 
-            if self.synthetic {
-                let current_page = self.current_page.min(doc.pages_count() - 1);
-                if let Some(location) = doc.resolve_location(Location::Exact(current_page)) {
-                    self.current_page = location;
-                }
-            } else {
-                let ratio = doc.pages_count() / self.pages_count;
-                self.pages_count = doc.pages_count();
-                self.current_page = (ratio * self.current_page).min(self.pages_count - 1);
+            let pages_count = doc.pages_count();
+
+            match anchor {
+                Some(TextLocation::Dynamic(offset)) => {
+                    if let Some(location) = page_of_offset(&mut **doc, offset) {
+                        self.current_page = location;
+                    }
+                },
+                _ => {
+                    // Position of the anchor within the former layout, in pages.
+                    let position = match anchor {
+                        Some(TextLocation::Static(page, index)) => {
+                            let count = self.text.get(&page).map_or(0, Vec::len);
+                            page as f32 + index as f32 / count.max(1) as f32
+                        },
+                        _ => self.current_page as f32,
+                    };
+                    let ratio = pages_count as f32 / self.pages_count.max(1) as f32;
+                    self.pages_count = pages_count;
+                    self.current_page = ((position * ratio) as usize).min(pages_count - 1);
+                },
             }
         }
 
         self.cache.clear();
-        self.text.clear();
+        self.clear_text();
         self.update(Some(UpdateMode::Partial), hub, rq, context);
         self.update_tool_bar(rq, context);
         self.update_bottom_bar(rq);
     }
 
+    // Returns the offset of the word that shall remain visible after a relayout.
+    // The previous anchor is kept while it's still on the current page, so that
+    // successive relayouts don't drift backward.
+    fn layout_anchor(&mut self) -> usize {
+        let mut doc = self.doc.lock().unwrap();
+        let next_page = doc.resolve_location(Location::Next(self.current_page));
+        if let Some(anchor) = self.layout_anchor {
+            if anchor >= self.current_page && next_page.map_or(true, |next| anchor < next) {
+                return anchor;
+            }
+        }
+        let anchor = first_word_offset(&mut **doc, self.current_page);
+        self.layout_anchor = Some(anchor);
+        anchor
+    }
+
     fn set_default(&mut self, prop: &ThemeProp, hub: &Hub, context: &mut Context) {
         let mut changed = false;
-        if let Some(ref r) = self.info.reader {
+        if let Some(ref mut r) = self.info.reader {
             let defaults = &mut context.settings.reader;
             match *prop {
                 ThemeProp::FontFamily => if let Some(ref font) = r.font_family {
@@ -2577,9 +4355,10 @@ impl Reader {
                         changed = true;
                     }
                 },
-                ThemeProp::FontSize => if let Some(font_size) = r.font_size {
+                ThemeProp::FontSize => if let Some(font_size) = r.font_size(defaults.font_size) {
                     if defaults.font_size != font_size {
                         defaults.font_size = font_size;
+                        r.set_font_size(font_size, font_size);
                         changed = true;
                     }
                 },
@@ -2622,7 +4401,7 @@ impl Reader {
                         self.set_font_family(&defaults.font_family[..], false, hub, rq, context);
                     }
                 }
-                if let Some(font_size) = r.font_size {
+                if let Some(font_size) = r.font_size(defaults.font_size) {
                     if defaults.font_size != font_size {
                         self.set_font_size(defaults.font_size, false, hub, rq, context);
                     }
@@ -2642,14 +4421,21 @@ impl Reader {
                         self.set_line_height(defaults.line_height, false, hub, rq, context);
                     }
                 }
+                if r.hyphenate.is_some() {
+                    let hyphen_penalty = book_hyphen_penalty(None, defaults);
+                    self.relayout(false, hub, rq, context, |doc| doc.set_hyphen_penalty(hyphen_penalty));
+                }
             }
         }
-        if let Some(ref mut r) = self.info.reader {
-            r.font_family = None;
-            r.font_size = None;
-            r.margin_width = None;
-            r.text_align = None;
-            r.line_height = None;
+        if self.reflowable {
+            if let Some(ref mut r) = self.info.reader {
+                r.font_family = None;
+                r.font_size = None;
+                r.margin_width = None;
+                r.text_align = None;
+                r.line_height = None;
+                r.hyphenate = None;
+            }
         }
         {
             let mut doc = self.doc.lock().unwrap();
@@ -2659,14 +4445,14 @@ impl Reader {
             }
         }
         self.cache.clear();
-        self.text.clear();
+        self.clear_text();
         self.update(Some(UpdateMode::Partial), hub, rq, context);
         self.update_tool_bar(rq, context);
         self.update_bottom_bar(rq);
     }
 
     fn apply_theme(&mut self, idx: usize, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
-        if Arc::strong_count(&self.doc) > 1 {
+        if self.doc_is_shared() {
             return;
         }
 
@@ -2682,7 +4468,7 @@ impl Reader {
             }
             if let Some(v) = theme.font_size {
                 let v = if v < 0.0 || theme.font_size_relative.unwrap_or(false) {
-                    let font_size = self.info.reader.as_ref().and_then(|r| r.font_size)
+                    let font_size = self.info.reader.as_ref().and_then(|r| r.font_size(context.settings.reader.font_size))
                                         .unwrap_or(context.settings.reader.font_size);
                     v + font_size
                 } else {
@@ -2739,7 +4525,7 @@ impl Reader {
                     }
                 }
                 self.cache.clear();
-                self.text.clear();
+                self.clear_text();
                 self.update(Some(UpdateMode::Partial), hub, rq, context);
                 self.update_bottom_bar(rq);
             }
@@ -2747,31 +4533,28 @@ impl Reader {
     }
 
     fn apply_css_tweak(&mut self, index: usize, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
-        if Arc::strong_count(&self.doc) > 1 {
+        if self.doc_is_shared() {
             return;
         }
         if let Some(Selection { anchor: TextLocation::Dynamic(offset), .. }) = self.selection {
-            let (div_sel, span_sel);
+            let (div_sel, span_sel, ancestors);
             {
                 let mut doc = self.doc.lock().unwrap();
-                if let Some((dsel, ssel, _, _)) = doc.get_node_data_at(offset, 0) {
-                    (div_sel, span_sel) = (dsel, ssel);
+                if let Some((dsel, ssel, _, _, anc)) = doc.get_node_data_at(offset, 0) {
+                    (div_sel, span_sel, ancestors) = (dsel, ssel, anc);
                 } else {
                     hub.send(Event::Notify("Unable to determine CSS selector".to_string())).ok();
                     return;
                 }
             }
-            if span_sel.is_empty() {
-                self.apply_css_tweak_aux(&div_sel, index, hub, context);
+            let mut selectors = css_selectors(&div_sel, &span_sel, &ancestors);
+            if selectors.len() == 1 {
+                let (style, selector) = selectors.remove(0);
+                self.apply_css_tweak_aux(&selector, style, index, hub, context);
             } else {
-                let entries = vec![div_sel.to_owned(),
-                                   span_sel.to_owned(),
-                                   format!("{} {}", div_sel, span_sel),
-                                   format!("{}, {}", div_sel, span_sel),
-                                   format!("{0}, {0} {1}", div_sel, span_sel)];
-                let entries = entries.iter()
-                    .map(|x| { EntryKind::Command(x.clone(),
-                                                  EntryId::SetCssTweakEx(x.clone(), index))
+                let entries = selectors.into_iter()
+                    .map(|(style, x)| { EntryKind::Command(x.clone(),
+                                                           EntryId::SetCssTweakEx(x, style, index))
                 }).collect();
                 let pt = pt!(self.rect().width() as i32 / 2, self.rect().height() as i32 / 3);
                 let menu = Menu::new(rect![pt, pt], ViewId::CssSelectorMenu, MenuKind::Contextual, entries, context);
@@ -2781,38 +4564,57 @@ impl Reader {
         }
     }
 
-    fn apply_css_tweak_aux(&mut self, selector: &str, index: usize, hub: &Hub, context: &mut Context) {
-        if Arc::strong_count(&self.doc) > 1 {
+    fn repeat_css_tweak(&mut self, hub: &Hub, context: &mut Context) {
+        if self.doc_is_shared() {
+            return;
+        }
+        let (style, index) = match self.last_css_tweak {
+            Some(last) => last,
+            None => return,
+        };
+        if let Some(Selection { anchor: TextLocation::Dynamic(offset), .. }) = self.selection {
+            let data = self.doc.lock().unwrap().get_node_data_at(offset, 0);
+            if let Some((div_sel, span_sel, _, _, ancestors)) = data {
+                // Styles that don't apply to this node fall back to the block.
+                let selector = css_selectors(&div_sel, &span_sel, &ancestors).into_iter()
+                                   .find(|(s, _)| *s == style)
+                                   .map_or(div_sel, |(_, selector)| selector);
+                self.apply_css_tweak_aux(&selector, style, index, hub, context);
+            } else {
+                hub.send(Event::Notify("Unable to determine CSS selector".to_string())).ok();
+            }
+        }
+    }
+
+    fn apply_css_tweak_aux(&mut self, selector: &str, style: usize, index: usize, hub: &Hub, context: &mut Context) {
+        if self.doc_is_shared() {
             return;
         }
         let mut dirty = false;
         let mut doc = self.doc.lock().unwrap();
         if let Some(ref mut r) = self.info.reader {
-            let mut css = context.settings.css_styles[index].css.trim().to_string();
             // \n used to separate rules
-            css = format!("\n{} {}{}{}",
-                          selector,
-                          if css.starts_with('{') {""} else {"{"},
-                          css,
-                          if css.ends_with('}') {""} else {"}"});
+            let mut css = css_rule(selector, &context.settings.css_styles[index].css);
             if let Some(ref old_css) = r.extra_css {
                 css = str::replacen(old_css, &css, "", 1) + &css;
             }
             r.extra_css = Some(css.to_string());
             set_extra_css!(doc, css, &context.settings);
             dirty = true;
+            self.last_css_tweak = Some((style, index));
             hub.send(Event::Notify(format!("{} applied to {}",
                                            context.settings.css_styles[index].name,
                                            selector))).ok();
         }
+        drop(doc);
         if dirty {
             self.cache.clear();
-            self.text.clear();
+            self.clear_text();
         }
     }
 
     fn css_tweaks_as_html(&mut self, context: &mut Context) -> Option<String> {
-        if Arc::strong_count(&self.doc) > 1 {
+        if self.doc_is_shared() {
             return None;
         }
 
@@ -2822,11 +4624,17 @@ impl Reader {
                             </head>\n<body>\n".to_string();
             if let Some(Selection { anchor: TextLocation::Dynamic(offset), .. }) = self.selection {
                 let mut doc = self.doc.lock().unwrap();
-                if let Some((div_sel, span_sel, txt, html)) = doc.get_node_data_at(offset, 700) {
+                if let Some((div_sel, span_sel, txt, html, ancestors)) = doc.get_node_data_at(offset, 700) {
                     let selector = format!("{}{}{}",
                                            div_sel,
                                            if span_sel.is_empty() {""} else {", "},
                                            span_sel);
+                    let chain = ancestors.iter().rev().map(String::as_str)
+                                         .chain(Some(div_sel.as_str()))
+                                         .chain(Some(span_sel.as_str()).filter(|s| !s.is_empty()))
+                                         .collect::<Vec<&str>>()
+                                         .join(" › ");
+                    buf.push_str(&format!("<p><strong>ancestors</strong>: {}</p>\n", encode_entities(&chain)));
                     buf.push_str(&format!("<p><strong>selector</strong>: {}<br />\n\
                                            <strong>text</strong>: {}{}<br />\n\
                                            <strong>html</strong>: <pre>... {} ...</pre></p>\n",
@@ -2859,7 +4667,7 @@ impl Reader {
     }
 
     fn undo_last_tweak(&mut self, hub: &Hub, context: &mut Context) {
-        if Arc::strong_count(&self.doc) > 1 {
+        if self.doc_is_shared() {
             return;
         }
 
@@ -2867,10 +4675,7 @@ impl Reader {
         let mut changed = false;
         if let Some(ref mut r) = self.info.reader {
             let old_css = r.extra_css.as_ref().unwrap().trim().to_string();
-            // locate the next to last } (the last } isn't followed by \n thanks to trim() )
-            if let Some(i) = old_css.rfind("}\n") {
-                css = old_css[..=i].to_string();
-            }
+            css = peel_last_rule(&old_css);
             if css != old_css {
                 r.extra_css = if !css.is_empty() {
                     Some(css.to_string())
@@ -2887,130 +4692,136 @@ impl Reader {
             }
             hub.send(Event::Notify("Last tweak removed".to_string())).ok();
             self.cache.clear();
-            self.text.clear();
+            self.clear_text();
         }
     }
 
-    fn set_text_align(&mut self, text_align: TextAlign, redraw: bool, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
-        if Arc::strong_count(&self.doc) > 1 {
-            return;
-        }
+    // The document is locked rather than owned, so that a running search doesn't prevent the restoration.
+    fn restore_css_tweaks(&mut self, hub: &Hub, context: &mut Context) {
+        let stash = match self.css_stash.take() {
+            Some(stash) => stash,
+            None => return,
+        };
 
+        let mut css = String::new();
         if let Some(ref mut r) = self.info.reader {
-            r.text_align = Some(text_align);
+            // Tweaks applied since the removal come after the restored ones.
+            css = stash + r.extra_css.as_deref().unwrap_or("");
+            r.extra_css = Some(css.clone());
         }
-
         {
             let mut doc = self.doc.lock().unwrap();
-            doc.set_text_align(text_align);
+            set_extra_css!(doc, css, &context.settings);
+        }
+        hub.send(Event::Notify("All tweaks restored".to_string())).ok();
+        self.cache.clear();
+        self.clear_text();
+    }
 
-            if !redraw { return; }
+    fn set_text_align(&mut self, text_align: TextAlign, redraw: bool, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        if self.doc_is_shared() {
+            return;
+        }
 
-            if self.synthetic {
-                let current_page = self.current_page.min(doc.pages_count() - 1);
-                if let Some(location) =  doc.resolve_location(Location::Exact(current_page)) {
-                    self.current_page = location;
-                }
-            } else {
-                self.pages_count = doc.pages_count();
-                self.current_page = self.current_page.min(self.pages_count - 1);
-            }
+        if let Some(ref mut r) = self.info.reader {
+            r.text_align = Some(text_align);
         }
 
-        self.cache.clear();
-        self.text.clear();
-        self.update(Some(UpdateMode::Partial), hub, rq, context);
-        self.update_tool_bar(rq, context);
-        self.update_bottom_bar(rq);
+        self.relayout(redraw, hub, rq, context, |doc| doc.set_text_align(text_align));
     }
 
     fn set_font_family(&mut self, font_family: &str, redraw: bool, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
-        if Arc::strong_count(&self.doc) > 1 {
+        if self.doc_is_shared() {
             return;
         }
 
+        // Picking a family takes precedence over the document's fonts.
+        let publisher_fonts = self.publisher_fonts(context);
+
         if let Some(ref mut r) = self.info.reader {
+            let current = r.font_family.clone()
+                           .unwrap_or_else(|| context.settings.reader.font_family.clone());
+            if current != font_family {
+                self.previous_font_family = Some(current);
+            }
             r.font_family = Some(font_family.to_string());
+            if publisher_fonts {
+                r.publisher_fonts = Some(false);
+            }
         }
 
-        {
-            let mut doc = self.doc.lock().unwrap();
-            let font_path = if font_family == DEFAULT_FONT_FAMILY {
-                "fonts"
-            } else {
-                &context.settings.reader.font_path
-            };
+        let font_path = if font_family == DEFAULT_FONT_FAMILY {
+            "fonts".to_string()
+        } else {
+            context.settings.reader.font_path.clone()
+        };
 
-            doc.set_font_family(font_family, font_path);
+        self.relayout(redraw, hub, rq, context, |doc| {
+            if publisher_fonts {
+                doc.use_document_fonts(false);
+            }
+            doc.set_font_family(font_family, &font_path);
+        });
+    }
 
-            if !redraw { return; }
+    fn publisher_fonts(&self, context: &Context) -> bool {
+        self.info.reader.as_ref().and_then(|r| r.publisher_fonts)
+            .unwrap_or(context.settings.reader.publisher_fonts)
+    }
 
-            if self.synthetic {
-                let current_page = self.current_page.min(doc.pages_count() - 1);
-                if let Some(location) =  doc.resolve_location(Location::Exact(current_page)) {
-                    self.current_page = location;
-                }
-            } else {
-                self.pages_count = doc.pages_count();
-                self.current_page = self.current_page.min(self.pages_count - 1);
-            }
+    fn toggle_publisher_fonts(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        if self.doc_is_shared() {
+            return;
         }
 
-        self.cache.clear();
-        self.text.clear();
-        self.update(Some(UpdateMode::Partial), hub, rq, context);
-        self.update_tool_bar(rq, context);
-        self.update_bottom_bar(rq);
+        let enable = !self.publisher_fonts(context);
+
+        if let Some(ref mut r) = self.info.reader {
+            r.publisher_fonts = Some(enable);
+        }
+
+        self.relayout(true, hub, rq, context, |doc| doc.use_document_fonts(enable));
     }
 
-    fn set_line_height(&mut self, line_height: f32, redraw: bool, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
-        if Arc::strong_count(&self.doc) > 1 {
+    fn hyphenate(&self, context: &Context) -> bool {
+        self.info.reader.as_ref().and_then(|r| r.hyphenate)
+            .unwrap_or(context.settings.reader.paragraph_breaker.hyphen_penalty < INFINITE_PENALTY)
+    }
+
+    fn toggle_hyphenation(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        if self.doc_is_shared() {
             return;
         }
 
+        let enable = !self.hyphenate(context);
+
         if let Some(ref mut r) = self.info.reader {
-            r.line_height = Some(line_height);
+            r.hyphenate = Some(enable);
         }
 
-        {
-            let mut doc = self.doc.lock().unwrap();
-            doc.set_line_height(line_height);
+        let hyphen_penalty = book_hyphen_penalty(Some(enable), &context.settings.reader);
+        self.relayout(true, hub, rq, context, |doc| doc.set_hyphen_penalty(hyphen_penalty));
+    }
 
-            if !redraw { return; }
+    fn set_line_height(&mut self, line_height: f32, redraw: bool, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        if self.doc_is_shared() {
+            return;
+        }
 
-            if self.synthetic {
-                let current_page = self.current_page.min(doc.pages_count() - 1);
-                if let Some(location) =  doc.resolve_location(Location::Exact(current_page)) {
-                    self.current_page = location;
-                }
-            } else {
-                self.pages_count = doc.pages_count();
-                self.current_page = self.current_page.min(self.pages_count - 1);
-            }
+        if let Some(ref mut r) = self.info.reader {
+            r.line_height = Some(line_height);
         }
 
-        self.cache.clear();
-        self.text.clear();
-        self.update(Some(UpdateMode::Partial), hub, rq, context);
-        self.update_tool_bar(rq, context);
-        self.update_bottom_bar(rq);
+        self.relayout(redraw, hub, rq, context, |doc| doc.set_line_height(line_height));
     }
 
     fn set_margin_width(&mut self, width: i32, redraw: bool, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
-        if Arc::strong_count(&self.doc) > 1 {
+        if self.doc_is_shared() {
             return;
         }
 
         if let Some(ref mut r) = self.info.reader {
-            if self.reflowable {
-                r.margin_width = Some(width);
-            } else {
-                if width == 0 {
-                    r.screen_margin_width = None;
-                } else {
-                    r.screen_margin_width = Some(width);
-                }
-            }
+            r.set_margin_width(self.reflowable, width);
         }
 
         if self.reflowable {
@@ -3041,7 +4852,7 @@ impl Reader {
         }
 
         if redraw {
-            self.text.clear();
+            self.clear_text();
             self.cache.clear();
             self.update(Some(UpdateMode::Partial), hub, rq, context);
             self.update_tool_bar(rq, context);
@@ -3051,14 +4862,39 @@ impl Reader {
 
     fn toggle_bookmark(&mut self, rq: &mut RenderQueue) {
         if let Some(ref mut r) = self.info.reader {
-            if !r.bookmarks.insert(self.current_page) {
-                r.bookmarks.remove(&self.current_page);
+            if r.bookmarks.remove(&self.current_page).is_none() {
+                r.bookmarks.insert(self.current_page, String::new());
             }
         }
+        rq.add(RenderData::new(self.id, self.bookmark_rect(), UpdateMode::Gui));
+    }
+
+    // Bounding rectangle of the triangle marking a bookmarked page.
+    fn bookmark_rect(&self) -> Rectangle {
         let w = self.rect.width() as i32 / 25;
-        let min = pt!(self.rect.max.x - w, self.rect.min.y);
-        let max = pt!(self.rect.max.x, self.rect.min.y + w);
-        rq.add(RenderData::new(self.id, rect![min, max], UpdateMode::Gui));
+        rect![self.rect.max.x - w, self.rect.min.y,
+              self.rect.max.x, self.rect.min.y + w]
+    }
+
+    // The bookmark triangle is hidden behind the close icon when they share a corner.
+    fn shows_bookmark(&self) -> bool {
+        let close_icon_right = self.shows_close_icon() && self.close_icon_corner == CloseIconCorner::TopRight;
+        !close_icon_right && self.info.reader.as_ref().map_or(false, |r| r.bookmarks.contains_key(&self.current_page))
+    }
+
+    fn shows_close_icon(&self) -> bool {
+        self.ephemeral || self.search.is_some() && locate::<SearchBar>(self).is_none()
+    }
+
+    fn remove_bookmark(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        if let Some(ref mut r) = self.info.reader {
+            if r.bookmarks.remove(&self.current_page).is_none() {
+                return;
+            }
+        }
+        rq.add(RenderData::new(self.id, self.bookmark_rect(), UpdateMode::Gui));
+        let notif = Notification::new("Bookmark removed.".to_string(), hub, rq, context);
+        push_notification(&mut self.children, notif, rq, context);
     }
 
     fn set_contrast_exponent(&mut self, exponent: f32, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
@@ -3070,6 +4906,21 @@ impl Reader {
         self.update_tool_bar(rq, context);
     }
 
+    // Moving up raises the exponent, moving down lowers it, within the bounds of the tool bar's slider.
+    fn drag_contrast_exponent(&mut self, dy: i32, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        let step_height = (self.rect.height() as i32 / CONTRAST_DRAG_STEPS).max(1);
+        let steps = -dy / step_height;
+        if steps == 0 {
+            return;
+        }
+        let exponent = self.contrast.exponent + steps as f32 * CONTRAST_EXPONENT_STEP;
+        let exponent = ((exponent / CONTRAST_EXPONENT_STEP).round() * CONTRAST_EXPONENT_STEP).clamp(1.0, 5.0);
+        if (exponent - self.contrast.exponent).abs() < f32::EPSILON {
+            return;
+        }
+        self.set_contrast_exponent(exponent, hub, rq, context);
+    }
+
     fn set_contrast_gray(&mut self, gray: f32, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
         if let Some(ref mut r) = self.info.reader {
             r.contrast_gray = Some(gray);
@@ -3079,6 +4930,30 @@ impl Reader {
         self.update_tool_bar(rq, context);
     }
 
+    fn set_background_gray(&mut self, gray: Option<u8>, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        let background = gray.map(f32::from);
+        if let Some(ref mut r) = self.info.reader {
+            r.background_gray = background;
+        }
+        self.contrast.background = background;
+        self.update(Some(UpdateMode::Partial), hub, rq, context);
+    }
+
+    fn apply_contrast_preset(&mut self, index: usize, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        let (exponent, gray) = match context.settings.contrast_presets.get(index) {
+            Some(preset) => (preset.exponent, preset.gray),
+            None => return,
+        };
+        if let Some(ref mut r) = self.info.reader {
+            r.contrast_exponent = Some(exponent);
+            r.contrast_gray = Some(gray);
+        }
+        self.contrast.exponent = exponent;
+        self.contrast.gray = gray;
+        self.update(Some(UpdateMode::Partial), hub, rq, context);
+        self.update_tool_bar(rq, context);
+    }
+
     fn set_zoom_mode(&mut self, zoom_mode: ZoomMode, reset_page_offset: bool, hub: &Hub, rq: &mut RenderQueue, context: &Context) {
         if self.view_port.zoom_mode == zoom_mode {
             return;
@@ -3095,13 +4970,120 @@ impl Reader {
         if reset_page_offset {
             self.view_port.page_offset = pt!(0, 0);
         }
-        self.cache.clear();
-        self.update(Some(UpdateMode::Partial), hub, rq, context);
+        // The remembered offsets only make sense for the zoom mode they were recorded in.
+        if let Some(ref mut r) = self.info.reader {
+            r.page_offsets.clear();
+            r.offset_visits.clear();
+        }
+        self.cache.clear();
+        self.update(Some(UpdateMode::Partial), hub, rq, context);
+    }
+
+    fn toggle_spread(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &Context) {
+        self.view_port.spread = !self.view_port.spread;
+        self.cache.clear();
+        self.update(Some(UpdateMode::Partial), hub, rq, context);
+    }
+
+    fn set_page_alignment(&mut self, page_alignment: PageAlignment, hub: &Hub, rq: &mut RenderQueue, context: &Context) {
+        if self.view_port.page_alignment == page_alignment {
+            return;
+        }
+        self.view_port.page_alignment = page_alignment;
+        if self.view_port.zoom_mode == ZoomMode::FitToPage && !self.is_spread() {
+            self.update(Some(UpdateMode::Partial), hub, rq, context);
+        }
+    }
+
+    fn toggle_image_preserve(&mut self, rq: &mut RenderQueue, context: &Context) {
+        self.image_preserve = !self.image_preserve;
+        if let Some(ref mut r) = self.info.reader {
+            r.image_preserve = Some(true).filter(|_| self.image_preserve);
+        }
+        self.update_noninverted_regions(context.fb.inverted(), context);
+        rq.add(RenderData::new(self.id, self.rect, UpdateMode::Full));
+    }
+
+    fn toggle_reading_ruler(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        if self.ruler.take().is_none() {
+            if self.ruler_band(self.rect.min.y).is_none() {
+                let notif = Notification::new("No text on this page.".to_string(), hub, rq, context);
+                push_notification(&mut self.children, notif, rq, context);
+                return;
+            }
+            self.ruler = Some(self.rect.min.y);
+        }
+        rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+    }
+
+    fn toggle_reading_direction(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        self.reading_direction = match self.reading_direction {
+            ReadingDirection::Ltr => ReadingDirection::Rtl,
+            ReadingDirection::Rtl => ReadingDirection::Ltr,
+        };
+        if let Some(ref mut r) = self.info.reader {
+            r.reading_direction = Some(self.reading_direction);
+        }
+        // Rebuild the bars so that their arrows follow the new direction.
+        if locate::<BottomBar>(self).is_some() {
+            self.toggle_bars(Some(false), hub, rq, context);
+            self.toggle_bars(Some(true), hub, rq, context);
+        }
+    }
+
+    fn hide_ruler(&mut self, rq: &mut RenderQueue) {
+        if self.ruler.take().is_some() {
+            rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+        }
+    }
+
+    // Screen rectangle of the line nearest to `y`.
+    fn ruler_band(&self, y: i32) -> Option<Rectangle> {
+        let mut nearest: Option<(&RenderChunk, i32, i32)> = None;
+        for chunk in &self.chunks {
+            let dy = chunk.position.y - chunk.frame.min.y;
+            for word in self.text.get(&chunk.location).into_iter().flatten() {
+                let rect = (word.rect * chunk.scale).to_rect();
+                if !rect.overlaps(&chunk.frame) {
+                    continue;
+                }
+                let center = (rect.min.y + rect.max.y) / 2;
+                let d = (center + dy - y).abs();
+                if nearest.map_or(true, |(_, _, dmin)| d < dmin) {
+                    nearest = Some((chunk, center, d));
+                }
+            }
+        }
+        let (chunk, y_pos, _) = nearest?;
+        let frame = self.cache.get(&chunk.location)?.frame;
+        let words = &self.text[&chunk.location];
+        let top = find_cut(&frame, y_pos, chunk.scale, LinearDir::Backward, words)?;
+        let bottom = find_cut(&frame, y_pos, chunk.scale, LinearDir::Forward, words)?;
+        let dy = chunk.position.y - chunk.frame.min.y;
+        Some(rect![self.rect.min.x, (top + dy).max(chunk.position.y),
+                   self.rect.max.x, (bottom + dy).min(chunk.position.y + chunk.frame.height() as i32)])
+    }
+
+    fn move_ruler(&mut self, dir: LinearDir, rq: &mut RenderQueue) {
+        if let Some(band) = self.ruler.and_then(|y| self.ruler_band(y)) {
+            let half_height = band.height() as i32 / 2;
+            let y = if dir == LinearDir::Forward {
+                band.max.y + half_height
+            } else {
+                band.min.y - half_height
+            };
+            if let Some(next_band) = self.ruler_band(y).filter(|b| *b != band) {
+                self.ruler = Some(y);
+                let mut rect = band;
+                rect.absorb(&next_band);
+                rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+            }
+        }
     }
 
     fn toggle_inverted(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
         let inverted = !context.fb.inverted();
-        self.update_noninverted_regions(inverted);
+        self.update_noninverted_regions(inverted, context);
         context.fb.toggle_inverted();
         context.settings.inverted = inverted;
         rq.add(RenderData::new(self.id(), context.fb.rect(), UpdateMode::Full));
@@ -3149,19 +5131,202 @@ impl Reader {
             }
             for c in r.cropping_margins.iter_mut() {
                 *c.margin_mut(index) = margin.clone();
+                if let Some(scheme) = self.cropping_scheme.take() {
+                    c.apply(index, scheme);
+                }
             }
         }
         self.cache.clear();
         self.update(Some(UpdateMode::Partial), hub, rq, context);
     }
 
-    fn toc(&self) -> Option<Vec<TocEntry>> {
+    fn detect_croppings(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        let rect = rect![self.rect.min, self.rect.min + pt!(self.rect.width() as i32 / 2,
+                                                            self.rect.height() as i32 / 2)];
+        let mut samples = [Vec::new(), Vec::new()];
+
+        {
+            let mut doc = self.doc.lock().unwrap();
+            for k in 1..=CROPPING_SAMPLES {
+                let index = k * self.pages_count / (CROPPING_SAMPLES + 1);
+                for location in index..(index + 2).min(self.pages_count) {
+                    if doc.dims(location).is_none() {
+                        continue;
+                    }
+                    let (pixmap, _) = build_pixmap(&rect, doc.as_mut(), location);
+                    if let Some(margin) = detect_margin(&pixmap, context.settings.reader.cropping_threshold) {
+                        samples[location % 2].push(margin);
+                    }
+                }
+            }
+        }
+
+        if let Some(cropping_margins) = CroppingMargins::from_samples(&samples[0], &samples[1]) {
+            if let Some(r) = self.info.reader.as_mut() {
+                r.cropping_margins = Some(cropping_margins);
+            }
+            self.cache.clear();
+            self.update(Some(UpdateMode::Partial), hub, rq, context);
+        } else {
+            let notif = Notification::new("No content detected.".to_string(), hub, rq, context);
+            push_notification(&mut self.children, notif, rq, context);
+        }
+    }
+
+    // Previews the detected margins in the margin cropper: they're applied
+    // with the given scheme once validated, and discarded when cancelled.
+    fn auto_crop_margins(&mut self, scheme: PageScheme, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        self.toggle_margin_cropper(false, hub, rq, context);
+        let pixmap = self.margin_cropper_pixmap();
+
+        if let Some(margin) = detect_margin(&pixmap, context.settings.reader.cropping_threshold) {
+            let msg = format!("Margins: top {:.1}%, right {:.1}%, bottom {:.1}%, left {:.1}%.",
+                              100.0 * margin.top, 100.0 * margin.right,
+                              100.0 * margin.bottom, 100.0 * margin.left);
+            self.push_margin_cropper(pixmap, &margin, hub, rq, context);
+            self.cropping_scheme = Some(scheme);
+            let notif = Notification::new(msg, hub, rq, context);
+            push_notification(&mut self.children, notif, rq, context);
+        } else {
+            let notif = Notification::new("No content detected.".to_string(), hub, rq, context);
+            push_notification(&mut self.children, notif, rq, context);
+        }
+    }
+
+    // Extracts the paragraph starting at `target` when the label of `link`
+    // is short enough to be a note reference, e.g. *3*, *[12]* or *†*.
+    fn note_from_words(&self, link: &BoundedText, location: usize, target: usize) -> Option<String> {
+        let mut doc = self.doc.lock().ok()?;
+        let label = doc.words(Location::Exact(location))
+                       .map(|(words, _)| words.into_iter()
+                                              .filter(|w| link.rect.overlaps(&w.rect))
+                                              .map(|w| w.text)
+                                              .collect::<String>())?;
+        let label = label.trim();
+        if label.is_empty() || trim_non_alphanumeric(label).chars().count() > NOTE_LABEL_MAX_LEN ||
+           label.chars().count() > NOTE_LABEL_MAX_LEN + 2 {
+            return None;
+        }
+
+        let (words, _) = doc.words(Location::Exact(target))?;
+        let mut text = String::new();
+        let mut last_rect: Option<Boundary> = None;
+
+        for word in words.into_iter()
+                         .skip_while(|w| !matches!(w.location, TextLocation::Dynamic(offset) if offset >= target)) {
+            if let Some(rect) = last_rect {
+                // A vertical gap larger than half a line ends the paragraph.
+                if word.rect.min.y - rect.max.y > (rect.max.y - rect.min.y) / 2.0 {
+                    break;
+                }
+                if text.ends_with('\u{00AD}') {
+                    text.pop();
+                } else if !text.ends_with('-') {
+                    text.push(' ');
+                }
+            }
+            text += &word.text;
+            last_rect = Some(word.rect);
+        }
+
+        Some(text).filter(|t| !t.is_empty())
+    }
+
+    fn handle_unresolved_link(&mut self, link: &BoundedText, location: usize, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        let action = context.settings.reader.unresolved_link;
+
+        if let UnresolvedLinkAction::Search = action {
+            let text = self.doc.lock().ok()
+                           .and_then(|mut doc| doc.words(Location::Exact(location)))
+                           .map(|(words, _)| words.into_iter()
+                                                  .filter(|w| link.rect.overlaps(&w.rect))
+                                                  .map(|w| w.text)
+                                                  .collect::<Vec<String>>()
+                                                  .join(" "))
+                           .unwrap_or_default();
+            let text = trim_non_alphanumeric(&text);
+            if let Some(query) = self.search_query(&text).filter(|_| !text.is_empty()) {
+                self.search(&text, query, context.settings.reader.max_search_results, hub, rq);
+                self.toggle_results_bar(true, rq, context);
+                return;
+            }
+        }
+
+        if let UnresolvedLinkAction::None = action {
+            eprintln!("Can't resolve URI: {}.", link.text);
+        } else {
+            let message = format!("Can't resolve {}.", link.text);
+            let notif = Notification::new(message, hub, rq, context);
+            push_notification(&mut self.children, notif, rq, context);
+        }
+    }
+
+    // Location of the top-level entry of the table of contents numbered `number`, starting at 1.
+    fn find_chapter(&self, number: usize) -> Option<usize> {
+        let mut doc = self.doc.lock().unwrap();
+        let toc = self.toc(doc.as_ref()).or_else(|| doc.toc())?;
+        let entry = toc.get(number.checked_sub(1)?)?;
+        doc.resolve_location(entry.location.clone())
+    }
+
+    // The entries that don't lead to the current chapter are initially collapsed,
+    // and the ones that do are expanded each time the table of contents is opened.
+    // When an entry was just toggled, the table of contents opens on it.
+    fn show_table_of_contents(&mut self, toggled: Option<usize>, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        self.toggle_bars(Some(false), hub, rq, context);
+        let mut doc = self.doc.lock().unwrap();
+        if let Some(toc) = self.toc(doc.as_ref())
+                               .or_else(|| doc.toc())
+                               .filter(|toc| !toc.is_empty()) {
+            let chap = doc.chapter(self.current_page, &toc)
+                          .map(|(c, _, _)| c);
+            let chap_index = chap.map_or(usize::MAX, |chap| chap.index);
+            let collapsed = self.toc_collapsed.get_or_insert_with(|| toc_collapsed_entries(&toc, chap_index));
+            if toggled.is_none() {
+                for index in toc_chapter_path(&toc, chap_index) {
+                    collapsed.remove(&index);
+                }
+            }
+            let html = toc_as_html(&toc, chap_index, collapsed);
+            let link_uri = if let Some(index) = toggled {
+                Some(format!("{}{}", TOGGLE_TOC_ENTRY_URI, index))
+            } else {
+                chap.and_then(|chap| {
+                    match chap.location {
+                        Location::Uri(ref uri) => Some(format!("@{}", uri)),
+                        Location::Exact(offset) => Some(format!("@{}", offset)),
+                        _ => None,
+                    }
+                })
+            };
+            hub.send(Event::OpenHtml(html, link_uri)).ok();
+        }
+    }
+
+    // Locations of all the entries of the table of contents, in order.
+    // They are resolved once per layout.
+    fn chapter_locations(&self, doc: &mut dyn Document) -> Vec<usize> {
+        self.chapter_ticks.borrow_mut().get_or_insert_with(|| {
+            let mut entries = Vec::new();
+            if let Some(toc) = self.toc(doc).or_else(|| doc.toc()) {
+                toc_locations(&toc, &mut entries);
+            }
+            let mut locations = doc.resolve_locations(entries).into_iter()
+                                   .flatten().collect::<Vec<usize>>();
+            locations.sort_unstable();
+            locations.dedup();
+            locations
+        }).clone()
+    }
+
+    // `doc` is the already locked document.
+    fn toc(&self, doc: &dyn Document) -> Option<Vec<TocEntry>> {
         let mut index = 0;
         self.info.toc.as_ref()
-            .map(|simple_toc| self.toc_aux(simple_toc, &mut index))
+            .map(|simple_toc| self.toc_aux(doc, simple_toc, &mut index))
     }
 
-    fn toc_aux(&self, simple_toc: &[SimpleTocEntry], index: &mut usize) -> Vec<TocEntry> {
+    fn toc_aux(&self, doc: &dyn Document, simple_toc: &[SimpleTocEntry], index: &mut usize) -> Vec<TocEntry> {
         let mut toc = Vec::new();
         for entry in simple_toc {
             *index += 1;
@@ -3170,7 +5335,8 @@ impl Reader {
                     let current_title = title.clone();
                     let current_location = match location {
                         TocLocation::Uri(uri) if uri.starts_with('\'') => {
-                            self.find_page_by_name(&uri[1..])
+                            self.find_page_by_user_name(&uri[1..])
+                                .or_else(|| self.find_page_by_label_in(doc, &uri[1..]))
                                 .map(Location::Exact)
                                 .unwrap_or_else(|| location.clone().into())
                         },
@@ -3178,7 +5344,7 @@ impl Reader {
                     };
                     let current_index = *index;
                     let current_children = if let SimpleTocEntry::Container(_, _, children) = entry {
-                        self.toc_aux(children, index)
+                        self.toc_aux(doc, children, index)
                     } else {
                         Vec::new()
                     };
@@ -3194,7 +5360,43 @@ impl Reader {
         toc
     }
 
+    // The printed page number of the current page.
+    fn page_label(&self) -> Option<String> {
+        if self.synthetic {
+            return None;
+        }
+        self.doc.lock().ok().and_then(|doc| doc.page_label(self.current_page))
+    }
+
+    fn find_page_by_label(&self, label: &str) -> Option<usize> {
+        let doc = self.doc.lock().ok()?;
+        self.find_page_by_label_in(doc.as_ref(), label)
+    }
+
+    // The labels are indexed on the first lookup, the first page bearing a label wins.
+    fn find_page_by_label_in(&self, doc: &dyn Document, label: &str) -> Option<usize> {
+        if self.synthetic {
+            return None;
+        }
+        let mut page_labels = self.page_labels.borrow_mut();
+        let page_labels = page_labels.get_or_insert_with(|| {
+            let mut map = FxHashMap::default();
+            for index in 0..self.pages_count {
+                if let Some(label) = doc.page_label(index) {
+                    map.entry(label).or_insert(index);
+                }
+            }
+            map
+        });
+        page_labels.get(label).cloned()
+    }
+
     fn find_page_by_name(&self, name: &str) -> Option<usize> {
+        self.find_page_by_user_name(name)
+            .or_else(|| self.find_page_by_label(name))
+    }
+
+    fn find_page_by_user_name(&self, name: &str) -> Option<usize> {
         self.info.reader.as_ref().and_then(|r| {
             if let Ok(a) = name.parse::<u32>() {
                 r.page_names
@@ -3222,6 +5424,25 @@ impl Reader {
         })
     }
 
+    // Accepts a page name optionally followed by a page offset, e.g. `xiv+3`.
+    fn find_page_by_name_with_offset(&self, text: &str) -> Option<usize> {
+        if let Some(location) = self.find_page_by_name(text) {
+            return Some(location);
+        }
+
+        let re = Regex::new(r"^(.+?)([-+])(\d+)$").unwrap();
+        let caps = re.captures(text)?;
+        let location = self.find_page_by_name(&caps[1])?;
+        let bpp = if self.synthetic { BYTES_PER_PAGE } else { 1.0 };
+        let delta = (caps[3].parse::<f64>().ok()? * bpp).round() as usize;
+
+        if &caps[2] == "-" {
+            Some(location.saturating_sub(delta))
+        } else {
+            Some((location + delta).min(self.pages_count.saturating_sub(1)))
+        }
+    }
+
     fn text_excerpt(&self, sel: [TextLocation; 2]) -> Option<String> {
         let [start, end] = sel;
         let parts = self.text.values().flatten()
@@ -3260,6 +5481,79 @@ impl Reader {
         self.selection.as_ref().and_then(|sel| self.text_excerpt([sel.start, sel.end]))
     }
 
+    // Grows the selection to the boundaries of the sentences it overlaps, within the visible text.
+    fn extend_selection_to_sentence(&mut self, rq: &mut RenderQueue) {
+        let [new_start, new_end] = match self.selection.as_ref()
+                                             .and_then(|sel| self.sentence_bounds([sel.start, sel.end])) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        if let Some(rect) = self.text_rect([new_start, new_end]) {
+            rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+        }
+
+        if let Some(sel) = self.selection.as_mut() {
+            sel.start = new_start;
+            sel.end = new_end;
+            sel.anchor = new_start;
+        }
+    }
+
+    // Bounds of the sentences overlapped by the given range, within the visible text.
+    fn sentence_bounds(&self, sel: [TextLocation; 2]) -> Option<[TextLocation; 2]> {
+        let [start, end] = sel;
+        let words = self.chunks.iter()
+                        .filter_map(|chunk| self.text.get(&chunk.location))
+                        .flatten()
+                        .collect::<Vec<&BoundedText>>();
+
+        let first = words.iter().position(|w| w.location >= start)?;
+        let last = words.iter().rposition(|w| w.location <= end).unwrap_or(first).max(first);
+
+        let mut i = first;
+        while i > 0 && !ends_sentence(&words[i - 1].text) {
+            i -= 1;
+        }
+
+        let mut j = last;
+        while j + 1 < words.len() && !ends_sentence(&words[j].text) {
+            j += 1;
+        }
+
+        Some([words[i].location, words[j].location])
+    }
+
+    fn add_vocabulary(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        let (word, sentence) = match self.selection.as_ref() {
+            Some(sel) => (self.text_excerpt([sel.start, sel.end]),
+                          self.sentence_bounds([sel.start, sel.end])
+                              .and_then(|bounds| self.text_excerpt(bounds))),
+            None => return,
+        };
+        let word = match word {
+            Some(word) => word.split_whitespace().collect::<Vec<&str>>().join(" "),
+            None => return,
+        };
+        let sentence = sentence.map(|s| s.split_whitespace().collect::<Vec<&str>>().join(" "))
+                               .unwrap_or_default();
+        if let Some(path) = context.settings.vocabulary_file.clone() {
+            let entry = json!({
+                "word": word,
+                "sentence": sentence,
+                "language": self.info.language,
+                "title": self.info.title,
+                "added": Local::now().naive_local(),
+            });
+            let message = match append_line(&path, &entry.to_string()) {
+                Ok(()) => format!("Added {} to the vocabulary.", word),
+                Err(e) => format!("Couldn't write to {}: {}.", path.display(), e),
+            };
+            let notif = Notification::new(message, hub, rq, context);
+            push_notification(&mut self.children, notif, rq, context);
+        }
+    }
+
     fn text_rect(&self, sel: [TextLocation; 2]) -> Option<Rectangle> {
         let [start, end] = sel;
         let mut result: Option<Rectangle> = None;
@@ -3303,8 +5597,79 @@ impl Reader {
         }
     }
 
+    // Single taps in the center region are held back while a double tap is possible.
+    fn holds_back_tap(&self, center: Point, context: &Context) -> bool {
+        context.settings.reader.double_tap_highlight && self.held_tap != Some(center) &&
+        self.focus.is_none() && self.state == State::Idle && self.selection.is_none() &&
+        locate::<Menu>(self).is_none() &&
+        Region::from_point(center, self.rect,
+                           context.settings.reader.strip_width,
+                           context.settings.reader.corner_width) == Region::Center
+    }
+
+    // The held tap is still set while it's handled, so that it isn't held back again.
+    fn release_tap(&mut self, hub: &Hub, bus: &mut Bus, rq: &mut RenderQueue, context: &mut Context) {
+        if let Some(center) = self.held_tap {
+            self.handle_event(&Event::Gesture(GestureEvent::Tap(center)), hub, bus, rq, context);
+            self.held_tap = None;
+        }
+    }
+
+    // The first tap was either handled or held back: the second one is handled as a tap.
+    fn double_tap_as_taps(&mut self, center: Point, hub: &Hub, bus: &mut Bus, rq: &mut RenderQueue, context: &mut Context) -> bool {
+        self.release_tap(hub, bus, rq, context);
+        self.handle_event(&Event::Gesture(GestureEvent::Tap(center)), hub, bus, rq, context)
+    }
+
     fn selection_rect(&self) -> Option<Rectangle> {
         self.selection.as_ref().and_then(|sel| self.text_rect([sel.start, sel.end]))
+            .map(|mut rect| {
+                for handle in self.handle_rects() {
+                    rect.absorb(&handle);
+                }
+                rect
+            })
+    }
+
+    fn shows_handles(&self) -> bool {
+        matches!(self.state, State::AdjustSelection | State::DragHandle(..) | State::HandleReleased)
+    }
+
+    // The centers of the start and end handles: at the bottom left corner of the first word
+    // and the bottom right corner of the last word.
+    fn handle_centers(&self) -> Option<[Point; 2]> {
+        if !self.shows_handles() {
+            return None;
+        }
+        let sel = self.selection.as_ref()?;
+        let first = self.text_rect([sel.start, sel.start])?;
+        let last = self.text_rect([sel.end, sel.end])?;
+        Some([pt!(first.min.x, first.max.y), last.max])
+    }
+
+    fn handle_rects(&self) -> Vec<Rectangle> {
+        let radius = scale_by_dpi(SELECTION_HANDLE_RADIUS, CURRENT_DEVICE.dpi) as i32;
+        self.handle_centers().map_or_else(Vec::new, |centers| {
+            centers.iter().map(|c| Rectangle::from_disk(*c, radius)).collect()
+        })
+    }
+
+    // Anchors the selection at the end opposite to the handle under `position`, if any,
+    // so that moving the finger only extends the grabbed end.
+    fn grab_handle(&mut self, position: Point) -> bool {
+        let dmax = (scale_by_dpi(RECT_DIST_JITTER, CURRENT_DEVICE.dpi) as i32).pow(2) as u32;
+        let handles = self.handle_rects();
+        let nearest = handles.iter().enumerate()
+                             .map(|(i, rect)| (i, position.rdist2(rect)))
+                             .filter(|(_, d)| *d < dmax)
+                             .min_by_key(|(_, d)| *d)
+                             .map(|(i, _)| i);
+        if let (Some(index), Some(sel)) = (nearest, self.selection.as_mut()) {
+            sel.anchor = if index == 0 { sel.end } else { sel.start };
+            true
+        } else {
+            false
+        }
     }
 
     fn find_annotation_ref(&mut self, sel: [TextLocation; 2]) -> Option<&Annotation> {
@@ -3319,6 +5684,40 @@ impl Reader {
                            .find(|a| a.selection[0] == sel[0] && a.selection[1] == sel[1]))
     }
 
+    // Reads the metadata of the document again, after it was edited on another device.
+    // The library picks up the new version of the file through an import.
+    fn refresh_metadata(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        if self.ephemeral {
+            return;
+        }
+
+        extract_metadata_from_document(&context.library.home, &mut self.info);
+
+        // Only this book's entry is updated: a full reimport would scan the whole library.
+        let refreshed = &self.info;
+        context.library.apply(|_, info| {
+            if info.file.path == refreshed.file.path {
+                info.title = refreshed.title.clone();
+                info.author = refreshed.author.clone();
+                info.year = refreshed.year.clone();
+                info.publisher = refreshed.publisher.clone();
+                info.series = refreshed.series.clone();
+                info.number = refreshed.number.clone();
+                info.language = refreshed.language.clone();
+                info.categories = refreshed.categories.clone();
+            }
+        });
+
+        let title = self.info.title();
+        if let Some(top_bar) = locate::<TopBar>(self).and_then(|index| self.child_mut(index).downcast_mut::<TopBar>()) {
+            top_bar.update_title_label(&title, rq);
+        }
+        self.reseed(rq, context);
+
+        let notif = Notification::new(format!("Refreshed the metadata of {}.", title), hub, rq, context);
+        push_notification(&mut self.children, notif, rq, context);
+    }
+
     fn reseed(&mut self, rq: &mut RenderQueue, context: &mut Context) {
         if let Some(index) = locate::<TopBar>(self) {
             if let Some(top_bar) = self.child_mut(index).downcast_mut::<TopBar>() {
@@ -3329,11 +5728,50 @@ impl Reader {
         rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
     }
 
+    // Lowers the frontlight intensity according to the dimming schedule.
+    // A change made elsewhere restarts the schedule from the new intensity.
+    fn dim_frontlight(&mut self, context: &mut Context) {
+        let dimming = &context.settings.dimming;
+        if !dimming.enabled || !context.settings.frontlight {
+            self.dimming = None;
+            return;
+        }
+
+        let current = context.frontlight.levels().intensity;
+        let state = match self.dimming {
+            Some(d) if (d.applied - current).abs() < f32::EPSILON => d,
+            _ => {
+                self.dimming = Some(Dimming { started: Instant::now(), intensity: current, applied: current });
+                return;
+            },
+        };
+
+        let periods = state.started.elapsed().as_secs_f32() / (60.0 * dimming.interval.max(1.0));
+        let target = (state.intensity - dimming.step * periods).max(dimming.floor.min(state.intensity));
+        if (target - current).abs() >= f32::EPSILON {
+            context.frontlight.set_intensity(target);
+            self.dimming = Some(Dimming { applied: target, .. state });
+        }
+    }
+
+    // Brings back the intensity the dimming schedule started from, unless it was changed since.
+    fn restore_frontlight(&mut self, context: &mut Context) {
+        if let Some(d) = self.dimming.take() {
+            if context.settings.frontlight &&
+               (d.applied - context.frontlight.levels().intensity).abs() < f32::EPSILON {
+                context.frontlight.set_intensity(d.intensity);
+            }
+        }
+    }
+
     fn quit(&mut self, context: &mut Context) {
-        if let Some(ref mut s) = self.search {
-            s.running.store(false, AtomicOrdering::Relaxed);
+        if let Some(ref s) = self.search {
+            stop_thread(&s.running, self.search_thread.take());
         }
 
+        self.stop_auto_turn();
+        self.restore_frontlight(context);
+
         if self.ephemeral {
             return;
         }
@@ -3358,8 +5796,21 @@ impl Reader {
                 r.scroll_mode = None;
             }
 
+            r.spread = Some(true).filter(|_| self.view_port.spread);
+            r.page_alignment = Some(self.view_port.page_alignment)
+                                   .filter(|a| *a != context.settings.reader.page_alignment);
+
             r.rotation = Some(CURRENT_DEVICE.to_canonical(context.display.rotation));
 
+            if let Some(ref s) = self.search {
+                r.search_query = Some(s.query.clone());
+            }
+
+            let now = Local::now().naive_local();
+            r.record_session(self.session.start, now, self.session.page_turns);
+            self.session.start = now;
+            self.session.page_turns = 0;
+
             if (self.contrast.exponent - DEFAULT_CONTRAST_EXPONENT).abs() > f32::EPSILON {
                 r.contrast_exponent = Some(self.contrast.exponent);
                 if (self.contrast.gray - DEFAULT_CONTRAST_GRAY).abs() > f32::EPSILON {
@@ -3376,30 +5827,55 @@ impl Reader {
         }
     }
 
+    // The point under `center` stays in place. The chunk under `center` is used instead of
+    // the current page since, when scrolling continuously, it can be any of the visible pages.
     fn scale_page(&mut self, center: Point, factor: f32, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        self.scale_page_with(center, |current_factor| current_factor * factor, hub, rq, context);
+    }
+
+    // The point under `center` stays in place while the scale factor goes from its current value to `scale(current)`.
+    fn scale_page_with<F: FnOnce(f32) -> f32>(&mut self, center: Point, scale: F, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
         if self.cache.is_empty() {
             return;
         }
 
-        let current_factor = if let ZoomMode::Custom(sf) = self.view_port.zoom_mode {
-            sf
-        } else {
-            self.cache[&self.current_page].scale
-        };
-
         if let Some(chunk) = self.chunks.iter().find(|chunk| {
             let chunk_rect = chunk.frame - chunk.frame.min + chunk.position;
             chunk_rect.includes(center)
-        }) {
+        }).cloned() {
+            let current_factor = if let ZoomMode::Custom(sf) = self.view_port.zoom_mode {
+                sf
+            } else {
+                chunk.scale
+            };
+            let new_factor = scale(current_factor);
+            let factor = new_factor / current_factor;
             let smw = self.view_port.margin_width;
             let frame = self.cache[&chunk.location].frame;
+            // The position of the point under `center`, relative to the page's frame.
+            let anchor = center - chunk.position + chunk.frame.min - frame.min;
             self.current_page = chunk.location;
-            self.view_port.page_offset = Point::from(factor * Vec2::from(center - chunk.position + chunk.frame.min - frame.min)) -
-                                         pt!(self.rect.width() as i32 / 2 - smw,
-                                             self.rect.height() as i32 / 2 - smw);
+            self.view_port.page_offset = Point::from(factor * Vec2::from(anchor)) - center + pt!(smw);
+
+            self.set_zoom_mode(ZoomMode::Custom(new_factor), false, hub, rq, context);
+        }
+    }
 
-            self.set_zoom_mode(ZoomMode::Custom(current_factor * factor), false, hub, rq, context);
+    fn set_zoom_preset(&mut self, factor: f32, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        let center = self.rect.center();
+        self.scale_page_with(center, |_| factor, hub, rq, context);
+    }
+
+    // Goes to the preset following the current scale factor, or to the first one.
+    fn cycle_zoom_preset(&mut self, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
+        let presets = &context.settings.reader.zoom_presets;
+        if presets.is_empty() {
+            return;
         }
+        let factor = presets.iter()
+                            .position(|p| self.view_port.zoom_mode == ZoomMode::Custom(*p))
+                            .map_or(presets[0], |index| presets[(index + 1) % presets.len()]);
+        self.set_zoom_preset(factor, hub, rq, context);
     }
 
     fn has_progress_bar(&self) -> bool {
@@ -3433,8 +5909,18 @@ impl Reader {
 }
 
 impl View for Reader {
-    fn handle_event(&mut self, evt: &Event, hub: &Hub, _bus: &mut Bus, rq: &mut RenderQueue, context: &mut Context) -> bool {
+    fn handle_event(&mut self, evt: &Event, hub: &Hub, bus: &mut Bus, rq: &mut RenderQueue, context: &mut Context) -> bool {
+        // Any manual interaction takes over from the auto turn mode.
+        if let Event::Gesture(..) = *evt {
+            self.stop_auto_turn();
+        }
+
         match *evt {
+            // The gesture of a handle drag doesn't turn pages nor adjust the selection.
+            Event::Gesture(..) if self.state == State::HandleReleased => {
+                self.state = State::AdjustSelection;
+                true
+            },
             Event::Gesture(GestureEvent::Rotate { quarter_turns, .. }) if quarter_turns != 0 => {
                 let (_, dir) = CURRENT_DEVICE.mirroring_scheme();
                 let n = (4 + (context.display.rotation - dir * quarter_turns)) % 4;
@@ -3444,10 +5930,20 @@ impl View for Reader {
             Event::Gesture(GestureEvent::Swipe { dir, start, end }) if self.rect.includes(start) => {
                 match self.view_port.zoom_mode {
                     ZoomMode::FitToPage | ZoomMode::FitToWidth => {
+                        let min_page_swipe = mm_to_px(context.settings.reader.min_page_swipe, CURRENT_DEVICE.dpi);
                         match dir {
-                            Dir::West => self.go_to_neighbor(CycleDir::Next, hub, rq, context),
-                            Dir::East => self.go_to_neighbor(CycleDir::Previous, hub, rq, context),
-                            Dir::South | Dir::North => self.vertical_scroll(start.y - end.y, hub, rq, context),
+                            Dir::West | Dir::East if ((start.x - end.x).abs() as f32) < min_page_swipe => (),
+                            Dir::West => self.go_to_neighbor(self.page_dir(CycleDir::Next), hub, rq, context),
+                            Dir::East => self.go_to_neighbor(self.page_dir(CycleDir::Previous), hub, rq, context),
+                            Dir::South if self.ruler.is_some() => self.move_ruler(LinearDir::Forward, rq),
+                            Dir::North if self.ruler.is_some() => self.move_ruler(LinearDir::Backward, rq),
+                            Dir::South | Dir::North => {
+                                let delta_y = start.y - end.y;
+                                let min_delta = scale_by_dpi(context.settings.reader.min_scroll_swipe, CURRENT_DEVICE.dpi) as i32;
+                                if delta_y.abs() >= min_delta {
+                                    self.vertical_scroll(delta_y, hub, rq, context);
+                                }
+                            },
                         };
                     },
                     ZoomMode::Custom(_) => {
@@ -3504,18 +6000,36 @@ impl View for Reader {
                 self.scale_page(center, factor, hub, rq, context);
                 true
             },
+            Event::Gesture(GestureEvent::MultiTap(points)) if self.reflowable && self.rect.includes(points[0]) => {
+                hub.send(Event::Select(EntryId::SwapFontFamily)).ok();
+                true
+            },
+            // The contrast only applies to fixed-layout documents: a vertical drag of two fingers along
+            // the right third of the page adjusts it, leaving the rest of the page to the search gesture.
+            Event::Gesture(GestureEvent::MultiSwipe { dir: Dir::North, starts, ends }) |
+            Event::Gesture(GestureEvent::MultiSwipe { dir: Dir::South, starts, ends }) if !self.reflowable &&
+                                                                                           starts.iter().all(|pt| self.rect.includes(*pt) &&
+                                                                                                                  pt.x >= self.rect.max.x - self.rect.width() as i32 / 3) => {
+                let dy = (ends[0].y - starts[0].y + ends[1].y - starts[1].y) / 2;
+                self.drag_contrast_exponent(dy, hub, rq, context);
+                true
+            },
+            Event::Gesture(GestureEvent::MultiSwipe { dir: Dir::South, starts, .. }) if self.rect.includes(starts[0]) => {
+                self.toggle_search_mode(hub, rq, context);
+                true
+            },
             Event::Gesture(GestureEvent::Arrow { dir, .. }) => {
                 match dir {
                     Dir::West => {
                         if self.search.is_none() {
-                            self.go_to_chapter(CycleDir::Previous, hub, rq, context);
+                            self.go_to_chapter(self.page_dir(CycleDir::Previous), hub, rq, context);
                         } else {
                             self.go_to_results_page(0, hub, rq, context);
                         }
                     },
                     Dir::East => {
                         if self.search.is_none() {
-                            self.go_to_chapter(CycleDir::Next, hub, rq, context);
+                            self.go_to_chapter(self.page_dir(CycleDir::Next), hub, rq, context);
                         } else {
                             let last_page = self.search.as_ref().unwrap().highlights.len() - 1;
                             self.go_to_results_page(last_page, hub, rq, context);
@@ -3569,7 +6083,8 @@ impl View for Reader {
                 match dir {
                     DiagDir::NorthWest => self.go_to_annotation(CycleDir::Previous, hub, rq, context),
                     DiagDir::NorthEast => self.go_to_annotation(CycleDir::Next, hub, rq, context),
-                    _ => (),
+                    DiagDir::SouthWest => self.focus_visible_annotation(CycleDir::Previous, rq),
+                    DiagDir::SouthEast => self.focus_visible_annotation(CycleDir::Next, rq),
                 }
                 true
             },
@@ -3582,30 +6097,54 @@ impl View for Reader {
                 self.toggle_bars(None, hub, rq, context);
                 true
             },
+            // Holding both page turn buttons brings up the go to page input.
+            Event::Gesture(GestureEvent::HoldButtonShort(code, ..)) if (code == ButtonCode::Backward ||
+                                                                        code == ButtonCode::Forward) &&
+                                                                       self.pressed_buttons.contains(&ButtonCode::Backward) &&
+                                                                       self.pressed_buttons.contains(&ButtonCode::Forward) => {
+                if !self.held_buttons.contains(&ButtonCode::Backward) &&
+                   !self.held_buttons.contains(&ButtonCode::Forward) {
+                    hub.send(Event::Toggle(ViewId::GoToPage)).ok();
+                }
+                // Neither release should turn the page.
+                self.held_buttons.insert(ButtonCode::Backward);
+                self.held_buttons.insert(ButtonCode::Forward);
+                true
+            },
             Event::Gesture(GestureEvent::HoldButtonShort(code, ..)) => {
                 match code {
-                    ButtonCode::Backward => self.go_to_chapter(CycleDir::Previous, hub, rq, context),
-                    ButtonCode::Forward => self.go_to_chapter(CycleDir::Next, hub, rq, context),
+                    ButtonCode::Backward => self.go_to_chapter(self.page_dir(CycleDir::Previous), hub, rq, context),
+                    ButtonCode::Forward => self.go_to_chapter(self.page_dir(CycleDir::Next), hub, rq, context),
                     _ => (),
                 }
                 self.held_buttons.insert(code);
                 true
             },
+            Event::Device(DeviceEvent::Button { code, status: ButtonStatus::Pressed, .. }) if code == ButtonCode::Backward ||
+                                                                                               code == ButtonCode::Forward => {
+                self.pressed_buttons.insert(code);
+                true
+            },
             Event::Device(DeviceEvent::Button { code, status: ButtonStatus::Released, .. }) => {
+                self.pressed_buttons.remove(&code);
                 if !self.held_buttons.remove(&code) {
                     match code {
                         ButtonCode::Backward => {
                             if self.search.is_none() {
-                                self.go_to_neighbor(CycleDir::Previous, hub, rq, context);
+                                let (title, location) = (self.chapter().title.clone(), self.current_page);
+                                self.go_to_neighbor(self.page_dir(CycleDir::Previous), hub, rq, context);
+                                self.notify_chapter_countdown(&title, location, hub, rq, context);
                             } else {
-                                self.go_to_results_neighbor(CycleDir::Previous, hub, rq, context);
+                                self.go_to_results_neighbor(self.page_dir(CycleDir::Previous), hub, rq, context);
                             }
                         },
                         ButtonCode::Forward => {
                             if self.search.is_none() {
-                                self.go_to_neighbor(CycleDir::Next, hub, rq, context);
+                                let (title, location) = (self.chapter().title.clone(), self.current_page);
+                                self.go_to_neighbor(self.page_dir(CycleDir::Next), hub, rq, context);
+                                self.notify_chapter_countdown(&title, location, hub, rq, context);
                             } else {
-                                self.go_to_results_neighbor(CycleDir::Next, hub, rq, context);
+                                self.go_to_results_neighbor(self.page_dir(CycleDir::Next), hub, rq, context);
                             }
                         },
                         _ => (),
@@ -3613,7 +6152,26 @@ impl View for Reader {
                 }
                 true
             },
-            Event::Device(DeviceEvent::Finger { position, status: FingerStatus::Motion, id, .. }) if self.state == State::Selection(id) => {
+            Event::Device(DeviceEvent::Finger { position, status: FingerStatus::Down, id, .. }) if self.state == State::AdjustSelection &&
+                                                                                                    self.grab_handle(position) => {
+                self.state = State::DragHandle(id, false);
+                true
+            },
+            Event::Gesture(GestureEvent::HoldFingerShort(_, id)) if self.state == State::DragHandle(id, false) => {
+                self.state = State::DragHandle(id, true);
+                true
+            },
+            Event::Gesture(GestureEvent::HoldFingerLong(_, id)) if self.state == State::DragHandle(id, true) => true,
+            Event::Device(DeviceEvent::Finger { status: FingerStatus::Up, id, .. }) if matches!(self.state, State::DragHandle(i, _) if i == id) => {
+                // A held finger doesn't produce any gesture.
+                self.state = if self.state == State::DragHandle(id, true) {
+                    State::AdjustSelection
+                } else {
+                    State::HandleReleased
+                };
+                true
+            },
+            Event::Device(DeviceEvent::Finger { position, status: FingerStatus::Motion, id, .. }) if matches!(self.state, State::Selection(i) | State::DragHandle(i, _) if i == id) => {
                 let mut nearest_word = None;
                 let mut dmin = u32::MAX;
                 let dmax = (scale_by_dpi(RECT_DIST_JITTER, CURRENT_DEVICE.dpi) as i32).pow(2) as u32;
@@ -3631,6 +6189,7 @@ impl View for Reader {
                     }
                 }
 
+                let old_handles = self.handle_rects();
                 let selection = self.selection.as_mut().unwrap();
 
                 if let Some(word) = nearest_word {
@@ -3703,6 +6262,10 @@ impl View for Reader {
 
                     selection.start = start;
                     selection.end = end;
+
+                    for rect in old_handles.into_iter().chain(self.handle_rects()) {
+                        rq.add(RenderData::new(self.id, rect, UpdateMode::Fast));
+                    }
                 }
                 true
             },
@@ -3730,6 +6293,7 @@ impl View for Reader {
                     }
                 }
 
+                let old_handles = self.handle_rects();
                 let selection = self.selection.as_mut().unwrap();
 
                 if let Some((word, index)) = found {
@@ -3820,6 +6384,105 @@ impl View for Reader {
 
                     selection.start = start;
                     selection.end = end;
+
+                    for rect in old_handles.into_iter().chain(self.handle_rects()) {
+                        rq.add(RenderData::new(self.id, rect, UpdateMode::Fast));
+                    }
+                }
+                true
+            },
+            // Two quick taps outside of the center region are two taps, e.g. two page turns.
+            Event::Gesture(GestureEvent::DoubleTap(center)) if !context.settings.reader.double_tap_highlight ||
+                                                               !matches!(Region::from_point(center, self.rect,
+                                                                                           context.settings.reader.strip_width,
+                                                                                           context.settings.reader.corner_width),
+                                                                         Region::Center) => {
+                self.double_tap_as_taps(center, hub, bus, rq, context)
+            },
+            // A double tap moves the reading ruler to the tapped line.
+            Event::Gesture(GestureEvent::DoubleTap(center)) if self.ruler.is_some() && self.focus.is_none() &&
+                                                               self.rect.includes(center) => {
+                self.held_tap = None;
+                if let (Some(band), Some(next_band)) = (self.ruler.and_then(|y| self.ruler_band(y)),
+                                                        self.ruler_band(center.y)) {
+                    self.ruler = Some(center.y);
+                    let mut rect = band;
+                    rect.absorb(&next_band);
+                    rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+                }
+                true
+            },
+            // Keep the search match under a double tap as a highlight.
+            Event::Gesture(GestureEvent::DoubleTap(center)) if self.search.is_some() && self.focus.is_none() &&
+                                                               self.rect.includes(center) &&
+                                                               self.search_match_at(center).is_some() => {
+                self.held_tap = None;
+                if let Some(sel) = self.search_match_at(center) {
+                    let text = self.text_excerpt(sel).unwrap_or_default();
+                    if let Some(r) = self.info.reader.as_mut() {
+                        r.annotations.push(Annotation {
+                            selection: sel,
+                            note: String::new(),
+                            text,
+                            modified: Local::now().naive_local(),
+                            color: HIGHLIGHT_DRIFT,
+                        });
+                    }
+                    if let Some(rect) = self.text_rect(sel) {
+                        rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+                    }
+                    self.update_annotations();
+                }
+                true
+            },
+            // Highlight the word under a double tap, or edit the annotation it belongs to.
+            Event::Gesture(GestureEvent::DoubleTap(center)) if self.rect.includes(center) && self.focus.is_none() &&
+                                                               self.state == State::Idle && self.selection.is_none() &&
+                                                               locate::<Menu>(self).is_none() &&
+                                                               self.nearest_word(center).is_some() => {
+                self.held_tap = None;
+                let (nearest_word, rect) = match self.nearest_word(center) {
+                    Some(found) => found,
+                    None => return true,
+                };
+
+                let anchor = nearest_word.location;
+                let radius = scale_by_dpi(24.0, CURRENT_DEVICE.dpi) as i32;
+                if let Some(annot) = self.annotations.values().flatten()
+                                         .find(|annot| anchor >= annot.selection[0] && anchor <= annot.selection[1]).cloned() {
+                    self.toggle_annotation_menu(&annot, Rectangle::from_disk(center, radius), Some(true), rq, context);
+                } else if let Some(r) = self.info.reader.as_mut() {
+                    r.annotations.push(Annotation {
+                        selection: [anchor, anchor],
+                        note: String::new(),
+                        text: nearest_word.text,
+                        modified: Local::now().naive_local(),
+                        color: HIGHLIGHT_DRIFT,
+                    });
+                    rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+                    self.update_annotations();
+                }
+
+                true
+            },
+            Event::Gesture(GestureEvent::DoubleTap(center)) => self.double_tap_as_taps(center, hub, bus, rq, context),
+            Event::Gesture(GestureEvent::Tap(center)) if self.rect.includes(center) && self.holds_back_tap(center, context) => {
+                self.release_tap(hub, bus, rq, context);
+                self.held_tap = Some(center);
+                arm_held_tap(center, hub);
+                true
+            },
+            Event::ReleaseTap(center) => {
+                if self.held_tap == Some(center) {
+                    self.release_tap(hub, bus, rq, context);
+                }
+                true
+            },
+            Event::Gesture(GestureEvent::Tap(center)) if self.focused_annotation.is_some() && self.rect.includes(center) &&
+                                                         self.focused_annotation_at(center).is_some() => {
+                if let Some(annot) = self.focused_annotation_at(center) {
+                    let radius = scale_by_dpi(24.0, CURRENT_DEVICE.dpi) as i32;
+                    self.toggle_annotation_menu(&annot, Rectangle::from_disk(center, radius), Some(true), rq, context);
                 }
                 true
             },
@@ -3831,6 +6494,9 @@ impl View for Reader {
                 let mut nearest_link = None;
                 let mut dmin = u32::MAX;
                 let dmax = (scale_by_dpi(RECT_DIST_JITTER, CURRENT_DEVICE.dpi) as i32).pow(2) as u32;
+                // A link under the tap wins over the nearby ones.
+                let mut contained_link = None;
+                let mut contained_dmin = u32::MAX;
 
                 for chunk in &self.chunks {
                     let (links, _) = self.doc.lock().ok()
@@ -3838,19 +6504,42 @@ impl View for Reader {
                                          .unwrap_or((Vec::new(), 0));
                     for link in links {
                         let rect = (link.rect * chunk.scale).to_rect() - chunk.frame.min + chunk.position;
+                        if rect.includes(center) {
+                            let d = center.dist2(rect.center());
+                            if d < contained_dmin {
+                                contained_dmin = d;
+                                contained_link = Some((link, chunk.location));
+                            }
+                            continue;
+                        }
                         let d = center.rdist2(&rect);
                         if d < dmax && d < dmin {
                             dmin = d;
-                            nearest_link = Some(link.clone());
+                            nearest_link = Some((link.clone(), chunk.location));
                         }
                     }
                 }
 
-                if let Some(link) = nearest_link.take() {
+                if let Some((link, location)) = contained_link.or(nearest_link) {
                     let pdf_page = Regex::new(r"^#page=(\d+).*$").unwrap();
                     let djvu_page = Regex::new(r"^#([+-])?(\d+)$").unwrap();
                     let toc_page = Regex::new(r"^@(.+)$").unwrap();
-                    if let Some(caps) = toc_page.captures(&link.text) {
+                    // The actions of the annotations page and of the table of contents are forwarded to the reader of the document.
+                    if let Some(sel) = link.text.strip_prefix(EDIT_NOTE_URI).and_then(selection_from_uri) {
+                        self.quit(context);
+                        hub.send(Event::Back).ok();
+                        hub.send(Event::GoToLocation(Location::Exact(sel[0].location()))).ok();
+                        hub.send(Event::Select(EntryId::EditAnnotationNote(sel))).ok();
+                    } else if let Some(sel) = link.text.strip_prefix(REMOVE_ANNOTATION_URI).and_then(selection_from_uri) {
+                        self.quit(context);
+                        hub.send(Event::Back).ok();
+                        hub.send(Event::Select(EntryId::RemoveAnnotation(sel))).ok();
+                        hub.send(Event::Select(EntryId::Annotations)).ok();
+                    } else if let Some(index) = link.text.strip_prefix(TOGGLE_TOC_ENTRY_URI).and_then(|s| s.parse::<usize>().ok()) {
+                        self.quit(context);
+                        hub.send(Event::Back).ok();
+                        hub.send(Event::Select(EntryId::ToggleTocEntry(index))).ok();
+                    } else if let Some(caps) = toc_page.captures(&link.text) {
                         let loc_opt = if caps[1].chars().all(|c| c.is_digit(10)) {
                             caps[1].parse::<usize>()
                                    .map(Location::Exact)
@@ -3878,12 +6567,33 @@ impl View for Reader {
                             self.go_to_page(index, true, hub, rq, context);
                         }
                     } else {
-                        let mut doc = self.doc.lock().unwrap();
                         let loc = Location::LocalUri(self.current_page, link.text.clone());
-                        if let Some(location) = doc.resolve_location(loc) {
-                            hub.send(Event::GoTo(location)).ok();
+                        let note = if context.settings.reader.footnote_popups {
+                            self.doc.lock().unwrap().footnote(loc.clone())
+                        } else {
+                            None
+                        };
+                        let resolved = self.doc.lock().unwrap().resolve_location(loc);
+                        let note = note.or_else(|| {
+                            resolved.filter(|_| context.settings.reader.footnote_popups)
+                                    .and_then(|target| self.note_from_words(&link, location, target))
+                        });
+                        if let Some(text) = note {
+                            let footnote = Footnote::new(self.rect, center, &text, rq, context);
+                            self.children.push(Box::new(footnote) as Box<dyn View>);
+                            return true;
+                        }
+                        if let Some(location) = resolved {
+                            // Recorded in the history, so that the north west corner brings us back.
+                            self.go_to_page(location, true, hub, rq, context);
                         } else {
                             if link.text.starts_with("https:") || link.text.starts_with("http:") {
+                                if context.settings.external_urls_qr_code {
+                                    if let Some(code) = UrlQrCode::new(self.rect, &link.text, rq, context) {
+                                        self.children.push(Box::new(code) as Box<dyn View>);
+                                        return true;
+                                    }
+                                }
                                 if let Some(path) = context.settings.external_urls_queue.as_ref() {
                                     if let Ok(mut file) = OpenOptions::new().create(true)
                                                                             .append(true)
@@ -3893,12 +6603,12 @@ impl View for Reader {
                                         } else {
                                             let message = format!("Queued {}.", link.text);
                                             let notif = Notification::new(message, hub, rq, context);
-                                            self.children.push(Box::new(notif) as Box<dyn View>);
+                                            push_notification(&mut self.children, notif, rq, context);
                                         }
                                     }
                                 }
                             } else {
-                                eprintln!("Can't resolve URI: {}.", link.text);
+                                self.handle_unresolved_link(&link, location, hub, rq, context);
                             }
                         }
                     }
@@ -3907,7 +6617,7 @@ impl View for Reader {
 
                 if let ZoomMode::Custom(_) = self.view_port.zoom_mode {
                     let dx = self.rect.width() as i32 - 2 * self.view_port.margin_width;
-                    let dy = self.rect.height() as i32 - 2 * self.view_port.margin_width;
+                    let dy = self.page_height() as i32 - 2 * self.view_port.margin_width;
                     match Region::from_point(center, self.rect,
                                              context.settings.reader.strip_width,
                                              context.settings.reader.corner_width) {
@@ -3937,18 +6647,25 @@ impl View for Reader {
                                          context.settings.reader.strip_width,
                                          context.settings.reader.corner_width) {
                     Region::Corner(diag_dir) => {
+                        let icon_dir = match self.close_icon_corner {
+                            CloseIconCorner::TopRight => DiagDir::NorthEast,
+                            CloseIconCorner::TopLeft => DiagDir::NorthWest,
+                        };
+                        if diag_dir == icon_dir && (self.search.is_some() || self.ephemeral) {
+                            if self.search.is_some() {
+                                self.stop_search(rq);
+                                self.update(Some(UpdateMode::Partial), hub, rq, context);
+                            } else {
+                                self.quit(context);
+                                hub.send(Event::Back).ok();
+                            }
+                            return true;
+                        }
                         match diag_dir {
                             DiagDir::NorthWest => self.go_to_last_page(hub, rq, context),
-                            DiagDir::NorthEast =>
-                                if self.search.is_some() {
-                                    self.stop_search(rq);
-                                    self.update(Some(UpdateMode::Partial), hub, rq, context);
-                                } else if self.ephemeral {
-                                    self.quit(context);
-                                    hub.send(Event::Back).ok();
-                                } else {
-                                    self.toggle_bookmark(rq);
-                                },
+                            DiagDir::NorthEast => if !self.ephemeral {
+                                self.toggle_bookmark(rq);
+                            },
                             DiagDir::SouthEast =>
                                 if self.search.is_none() {
                                     match context.settings.reader.south_east_corner {
@@ -3956,11 +6673,11 @@ impl View for Reader {
                                             hub.send(Event::Toggle(ViewId::GoToPage)).ok();
                                         },
                                         SouthEastCornerAction::NextPage => {
-                                            self.go_to_neighbor(CycleDir::Next, hub, rq, context);
+                                            self.go_to_neighbor(self.page_dir(CycleDir::Next), hub, rq, context);
                                         },
                                     }
                                 } else {
-                                    self.go_to_neighbor(CycleDir::Next, hub, rq, context);
+                                    self.go_to_neighbor(self.page_dir(CycleDir::Next), hub, rq, context);
                                 },
                             DiagDir::SouthWest =>
                                 if self.search.is_none() {
@@ -3968,10 +6685,20 @@ impl View for Reader {
                                         self.quit(context);
                                         hub.send(Event::Back).ok();
                                     } else {
-                                        hub.send(Event::Show(ViewId::TableOfContents)).ok();
+                                        match context.settings.reader.south_west_corner {
+                                            SouthWestCornerAction::TableOfContents => {
+                                                hub.send(Event::Show(ViewId::TableOfContents)).ok();
+                                            },
+                                            SouthWestCornerAction::PreviousPage => {
+                                                self.go_to_neighbor(self.page_dir(CycleDir::Previous), hub, rq, context);
+                                            },
+                                            SouthWestCornerAction::Bookmarks => {
+                                                hub.send(Event::Select(EntryId::Bookmarks)).ok();
+                                            },
+                                        }
                                     }
                                 } else {
-                                    self.go_to_neighbor(CycleDir::Previous, hub, rq, context);
+                                    self.go_to_neighbor(self.page_dir(CycleDir::Previous), hub, rq, context);
                                 },
                         }
                     },
@@ -3981,30 +6708,30 @@ impl View for Reader {
                                 if self.search.is_none() {
                                     match context.settings.reader.west_strip {
                                         WestStripAction::PreviousPage => {
-                                            self.go_to_neighbor(CycleDir::Previous, hub, rq, context);
+                                            self.go_to_neighbor(self.page_dir(CycleDir::Previous), hub, rq, context);
                                         }
                                         WestStripAction::NextPage => {
-                                            self.go_to_neighbor(CycleDir::Next, hub, rq, context);
+                                            self.go_to_neighbor(self.page_dir(CycleDir::Next), hub, rq, context);
                                         }
                                         WestStripAction::None => (),
                                     }
                                 } else {
-                                    self.go_to_results_neighbor(CycleDir::Previous, hub, rq, context);
+                                    self.go_to_results_neighbor(self.page_dir(CycleDir::Previous), hub, rq, context);
                                 }
                             },
                             Dir::East => {
                                 if self.search.is_none() {
                                     match context.settings.reader.east_strip {
                                         EastStripAction::PreviousPage => {
-                                            self.go_to_neighbor(CycleDir::Previous, hub, rq, context);
+                                            self.go_to_neighbor(self.page_dir(CycleDir::Previous), hub, rq, context);
                                         }
                                         EastStripAction::NextPage => {
-                                            self.go_to_neighbor(CycleDir::Next, hub, rq, context);
+                                            self.go_to_neighbor(self.page_dir(CycleDir::Next), hub, rq, context);
                                         }
                                         EastStripAction::None => (),
                                     }
                                 } else {
-                                    self.go_to_results_neighbor(CycleDir::Next, hub, rq, context);
+                                    self.go_to_results_neighbor(self.page_dir(CycleDir::Next), hub, rq, context);
                                 }
                             },
                             Dir::South => if self.synthetic
@@ -4042,13 +6769,33 @@ impl View for Reader {
                     return true;
                 }
 
-                let mut found = None;
-                let mut dmin = u32::MAX;
+                if self.shows_bookmark() && self.bookmark_rect().includes(center) {
+                    self.remove_bookmark(hub, rq, context);
+                    return true;
+                }
+
                 let dmax = (scale_by_dpi(RECT_DIST_JITTER, CURRENT_DEVICE.dpi) as i32).pow(2) as u32;
 
+                if let Some((sel, stashed)) = self.last_selection.take() {
+                    if stashed.elapsed() < SELECTION_STASH_DELAY {
+                        if let Some(rect) = self.text_rect([sel.start, sel.end]).filter(|r| center.rdist2(r) < dmax) {
+                            self.selection = Some(sel);
+                            self.state = State::Idle;
+                            rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+                            let radius = scale_by_dpi(24.0, CURRENT_DEVICE.dpi) as i32;
+                            self.toggle_selection_menu(Rectangle::from_disk(center, radius), Some(true), rq, context);
+                            return true;
+                        }
+                    }
+                }
+
                 if let Some(rect) = self.selection_rect() {
                     let d = center.rdist2(&rect);
                     if d < dmax {
+                        // Erase the handles.
+                        if self.shows_handles() {
+                            rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+                        }
                         self.state = State::Idle;
                         let radius = scale_by_dpi(24.0, CURRENT_DEVICE.dpi) as i32;
                         self.toggle_selection_menu(Rectangle::from_disk(center, radius), Some(true), rq, context);
@@ -4056,18 +6803,7 @@ impl View for Reader {
                     return true;
                 }
 
-                for chunk in &self.chunks {
-                    for word in &self.text[&chunk.location] {
-                        let rect = (word.rect * chunk.scale).to_rect() - chunk.frame.min + chunk.position;
-                        let d = center.rdist2(&rect);
-                        if d < dmax && d < dmin {
-                            dmin = d;
-                            found = Some((word.clone(), rect));
-                        }
-                    }
-                }
-
-                if let Some((nearest_word, rect)) = found {
+                if let Some((nearest_word, rect)) = self.nearest_word(center) {
                     let anchor = nearest_word.location;
                     if let Some(annot) = self.annotations.values().flatten()
                                              .find(|annot| anchor >= annot.selection[0] && anchor <= annot.selection[1]).cloned() {
@@ -4080,6 +6816,7 @@ impl View for Reader {
                             anchor,
                         });
                         self.state = State::Selection(id);
+                        self.hide_ruler(rq);
                         rq.add(RenderData::new(self.id, rect, UpdateMode::Fast));
                     }
                 }
@@ -4088,7 +6825,7 @@ impl View for Reader {
             },
             Event::Gesture(GestureEvent::HoldFingerLong(center, _)) if self.rect.includes(center) => {
                 if let Some(text) = self.selected_text() {
-                    let query = trim_non_alphanumeric(&text);
+                    let query = dictionary_query(&text);
                     let language = self.info.language.clone();
                     hub.send(Event::Select(EntryId::Launch(AppCmd::Dictionary { query, language }))).ok();
                 }
@@ -4106,10 +6843,11 @@ impl View for Reader {
             },
             Event::Submit(ViewId::GoToPageInput, ref text) => {
                 let re = Regex::new(r#"^([-+'])?(.+)$"#).unwrap();
+                let chapter_re = Regex::new(r#"^(?i)ch?\s*(\d+)$"#).unwrap();
                 if let Some(caps) = re.captures(text) {
                     let prefix = caps.get(1).map(|m| m.as_str());
                     if prefix == Some("'") {
-                        if let Some(location) = self.find_page_by_name(&caps[2]) {
+                        if let Some(location) = self.find_page_by_name_with_offset(&caps[2]) {
                             self.go_to_page(location, true, hub, rq, context);
                         }
                     } else {
@@ -4137,6 +6875,16 @@ impl View for Reader {
                                 index
                             };
                             self.go_to_page(location, true, hub, rq, context);
+                        } else if let Some(caps) = chapter_re.captures(text) {
+                            let number = caps[1].parse::<usize>().unwrap_or(0);
+                            if let Some(location) = self.find_chapter(number) {
+                                self.go_to_page(location, true, hub, rq, context);
+                            } else {
+                                let notif = Notification::new(format!("No chapter {}.", number), hub, rq, context);
+                                push_notification(&mut self.children, notif, rq, context);
+                            }
+                        } else if let Some(location) = self.find_page_by_label(text) {
+                            self.go_to_page(location, true, hub, rq, context);
                         }
                     }
                 }
@@ -4153,8 +6901,24 @@ impl View for Reader {
                     if let Some(ref mut r) = self.info.reader {
                         r.page_names.insert(self.current_page, text.to_string());
                     }
+                    self.chapter_ticks.replace(None);
+                }
+                self.toggle_keyboard(false, None, hub, rq, context);
+                true
+            },
+            Event::Submit(ViewId::LabelBookmarkInput, ref text) => {
+                if let Some(ref mut r) = self.info.reader {
+                    r.bookmarks.insert(self.current_page, text.trim().to_string());
                 }
                 self.toggle_keyboard(false, None, hub, rq, context);
+                let w = self.rect.width() as i32 / 25;
+                rq.add(RenderData::new(self.id, rect![self.rect.max.x - w, self.rect.min.y,
+                                                      self.rect.max.x, self.rect.min.y + w], UpdateMode::Gui));
+                true
+            },
+            Event::Submit(ViewId::SearchAnnotationsInput, ref text) => {
+                self.toggle_keyboard(false, None, hub, rq, context);
+                self.search_annotations(text, hub, rq, context);
                 true
             },
             Event::Submit(ViewId::EditNoteInput, ref note) => {
@@ -4168,6 +6932,7 @@ impl View for Reader {
                             note: note.to_string(),
                             text,
                             modified: Local::now().naive_local(),
+                            color: ANNOTATION_DRIFT,
                         });
                     }
                     if let Some(rect) = self.text_rect(sel) {
@@ -4189,17 +6954,65 @@ impl View for Reader {
                 self.toggle_keyboard(false, None, hub, rq, context);
                 true
             },
+            Event::TextChanged(ViewId::ReaderSearchInput, ref text) if context.settings.reader.incremental_search => {
+                let current_page = self.current_page;
+                let sp = self.search_preview.get_or_insert_with(|| SearchPreview {
+                    text: String::new(),
+                    changed: Instant::now(),
+                    origin: current_page,
+                    armed: false,
+                    running: Arc::new(AtomicBool::new(false)),
+                    thread: None,
+                });
+                if !sp.armed {
+                    arm_search_preview(SEARCH_PREVIEW_DELAY, hub);
+                }
+                sp.text = text.clone();
+                sp.changed = Instant::now();
+                sp.armed = true;
+                true
+            },
+            Event::SearchPreview => {
+                if let Some(sp) = self.search_preview.as_mut() {
+                    let elapsed = sp.changed.elapsed();
+                    if elapsed < SEARCH_PREVIEW_DELAY {
+                        arm_search_preview(SEARCH_PREVIEW_DELAY - elapsed, hub);
+                    } else {
+                        sp.armed = false;
+                        let (text, origin) = (sp.text.clone(), sp.origin);
+                        self.preview_search(&text, origin, hub);
+                    }
+                }
+                true
+            },
+            Event::SearchPreviewResult(ref text, ref result) => {
+                // Results of outdated queries are dropped.
+                if let Some(sp) = self.search_preview.as_mut().filter(|sp| sp.text == *text) {
+                    if let Some(handle) = sp.thread.take() {
+                        handle.join().ok();
+                    }
+                    let origin = sp.origin;
+                    self.show_search_preview(text, origin, result.clone(), hub, rq, context);
+                }
+                true
+            },
             Event::Submit(ViewId::ReaderSearchInput, ref text) => {
-                match make_query(text) {
+                self.stop_search_preview();
+                let text = if text.trim().is_empty() && context.settings.reader.repeat_last_search {
+                    self.last_query.clone().unwrap_or_default()
+                } else {
+                    text.to_string()
+                };
+                match self.search_query(&text) {
                     Some(query) => {
-                        self.search(text, query, hub, rq);
+                        self.search(&text, query, context.settings.reader.max_search_results, hub, rq);
                         self.toggle_keyboard(false, None, hub, rq, context);
                         self.toggle_results_bar(true, rq, context);
                     },
                     None => {
                         let notif = Notification::new("Invalid search query.".to_string(),
                                                       hub, rq, context);
-                        self.children.push(Box::new(notif) as Box<dyn View>);
+                        push_notification(&mut self.children, notif, rq, context);
                     },
                 }
                 true
@@ -4227,7 +7040,7 @@ impl View for Reader {
                 true
             },
             Event::ResultsPage(dir) => {
-                self.go_to_results_neighbor(dir, hub, rq, context);
+                self.go_to_results_match(dir, hub, rq, context);
                 true
             },
             Event::CropMargins(ref margin) => {
@@ -4240,11 +7053,17 @@ impl View for Reader {
                 true
             },
             Event::Toggle(ViewId::GoToPage) => {
-                self.toggle_go_to_page(None, ViewId::GoToPage, hub, rq, context);
+                self.toggle_go_to_page(None, ViewId::GoToPage, None, hub, rq, context);
+                true
+            },
+            Event::Show(ViewId::GoToPage) => {
+                // Synthetic locations are byte offsets, which is what the `%` input expects.
+                let percent = format!("{:.1}%", 100.0 * self.current_page as f64 / self.pages_count.max(1) as f64);
+                self.toggle_go_to_page(Some(true), ViewId::GoToPage, Some(&percent), hub, rq, context);
                 true
             },
             Event::Toggle(ViewId::GoToResultsPage) => {
-                self.toggle_go_to_page(None, ViewId::GoToResultsPage, hub, rq, context);
+                self.toggle_go_to_page(None, ViewId::GoToResultsPage, None, hub, rq, context);
                 true
             },
             Event::Slider(SliderId::FontSize, font_size, FingerStatus::Up) => {
@@ -4348,6 +7167,42 @@ impl View for Reader {
                 self.toggle_theme_dialog(false, None, hub, rq, context);
                 true
             },
+            Event::Select(EntryId::NextVisibleAnnotation) => {
+                self.focus_visible_annotation(CycleDir::Next, rq);
+                true
+            },
+            Event::Select(EntryId::PreviousVisibleAnnotation) => {
+                self.focus_visible_annotation(CycleDir::Previous, rq);
+                true
+            },
+            Event::Select(EntryId::ToggleScaledLineHeight) => {
+                self.toggle_scale_line_height(hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::TogglePublisherFonts) => {
+                self.toggle_publisher_fonts(hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::ToggleHyphenation) => {
+                self.toggle_hyphenation(hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::ShowTypography) => {
+                self.toggle_typography_dialog(true, hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::EditTouchZones) => {
+                self.toggle_touch_zones(true, hub, rq, context);
+                true
+            },
+            Event::Close(ViewId::TouchZones) => {
+                self.toggle_touch_zones(false, hub, rq, context);
+                true
+            },
+            Event::Close(ViewId::TypographyDialog) => {
+                self.toggle_typography_dialog(false, hub, rq, context);
+                true
+            },
             Event::SaveTheme => {
                 self.stash_theme(context);
                 self.toggle_theme_dialog(false, None, hub, rq, context);
@@ -4403,6 +7258,7 @@ impl View for Reader {
                 true
             },
             Event::Close(ViewId::SearchBar) => {
+                self.stop_search_preview();
                 self.stop_search(rq);
                 if self.search.is_none() {
                     self.toggle_results_bar(false, rq, context);
@@ -4412,17 +7268,17 @@ impl View for Reader {
                 true
             },
             Event::Close(ViewId::GoToPage) => {
-                self.toggle_go_to_page(Some(false), ViewId::GoToPage, hub, rq, context);
+                self.toggle_go_to_page(Some(false), ViewId::GoToPage, None, hub, rq, context);
                 true
             },
             Event::Close(ViewId::GoToResultsPage) => {
-                self.toggle_go_to_page(Some(false), ViewId::GoToResultsPage, hub, rq, context);
+                self.toggle_go_to_page(Some(false), ViewId::GoToResultsPage, None, hub, rq, context);
                 true
             },
             Event::Close(ViewId::SelectionMenu) => {
                 if self.state == State::Idle && self.target_annotation.is_none() {
                     if let Some(rect) = self.selection_rect() {
-                        self.selection = None;
+                        self.last_selection = self.selection.take().map(|sel| (sel, Instant::now()));
                         rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
                     }
                 }
@@ -4441,27 +7297,25 @@ impl View for Reader {
                 self.toggle_keyboard(false, None, hub, rq, context);
                 false
             },
+            Event::Close(ViewId::LabelBookmark) => {
+                self.toggle_keyboard(false, None, hub, rq, context);
+                false
+            },
+            Event::Close(ViewId::SearchAnnotations) => {
+                self.toggle_keyboard(false, None, hub, rq, context);
+                false
+            },
             Event::Show(ViewId::TableOfContents) => {
-                {
-                    self.toggle_bars(Some(false), hub, rq, context);
-                }
-                let mut doc = self.doc.lock().unwrap();
-                if let Some(toc) = self.toc()
-                                       .or_else(|| doc.toc())
-                                       .filter(|toc| !toc.is_empty()) {
-                    let chap = doc.chapter(self.current_page, &toc)
-                                  .map(|(c, _, _)| c);
-                    let chap_index = chap.map_or(usize::MAX, |chap| chap.index);
-                    let html = toc_as_html(&toc, chap_index);
-                    let link_uri = chap.and_then(|chap| {
-                        match chap.location {
-                            Location::Uri(ref uri) => Some(format!("@{}", uri)),
-                            Location::Exact(offset) => Some(format!("@{}", offset)),
-                            _ => None,
-                        }
-                    });
-                    hub.send(Event::OpenHtml(html, link_uri)).ok();
+                self.show_table_of_contents(None, hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::ToggleTocEntry(index)) => {
+                if let Some(collapsed) = self.toc_collapsed.as_mut() {
+                    if !collapsed.remove(&index) {
+                        collapsed.insert(index);
+                    }
                 }
+                self.show_table_of_contents(Some(index), hub, rq, context);
                 true
             },
             Event::Select(EntryId::Annotations) => {
@@ -4481,12 +7335,62 @@ impl View for Reader {
                 }
                 true
             },
+            Event::Select(EntryId::SearchAnnotations) => {
+                self.toggle_search_annotations(Some(true), hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::LabelBookmark) => {
+                self.toggle_label_bookmark(Some(true), hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::JumpHistory) => {
+                self.toggle_jump_history_menu(Some(true), rq, context);
+                true
+            },
+            Event::Select(EntryId::JumpToHistory(index)) => {
+                self.jump_to_history(index, hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::ExportAnnotations) => {
+                self.export_annotations(hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::RefreshMetadata) => {
+                self.refresh_metadata(hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::ExportBookmarks) => {
+                self.export_bookmarks(hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::ImportBookmarks) => {
+                self.import_bookmarks(hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::ToggleAutoTurn) => {
+                self.toggle_auto_turn(hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::WordPosition) => {
+                self.show_word_position(hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::ReadingStats) => {
+                self.toggle_bars(Some(false), hub, rq, context);
+                if let Some(r) = self.info.reader.as_ref() {
+                    let mut stats = ReaderInfo { reading_sessions: r.reading_sessions.clone(), .. Default::default() };
+                    stats.record_session(self.session.start, Local::now().naive_local(), self.session.page_turns);
+                    let html = reading_stats_as_html(&stats.reading_sessions);
+                    hub.send(Event::OpenHtml(html, None)).ok();
+                }
+                true
+            },
             Event::Select(EntryId::Bookmarks) => {
                 self.toggle_bars(Some(false), hub, rq, context);
                 if let Some(bookmarks) = self.info.reader.as_ref().map(|r| &r.bookmarks) {
                     let html = bookmarks_as_html(bookmarks, self.current_page, self.synthetic);
                     let link_uri = bookmarks.range(..= self.current_page).next_back()
-                                            .map(|index| format!("@{}", index));
+                                            .map(|(index, _)| format!("@{}", index));
                     hub.send(Event::OpenHtml(html, link_uri)).ok();
                 }
                 true
@@ -4509,11 +7413,14 @@ impl View for Reader {
                     s.highlights.entry(location).or_insert_with(Vec::new).push(rects.clone());
                     s.results_count += 1;
                     let results_count = s.results_count;
-                    if results_count > 1 && location <= self.current_page && s.highlights.len() > pages_count {
+                    let new_page = s.highlights.len() > pages_count;
+                    if results_count > 1 && location <= self.current_page && new_page {
                         s.current_page += 1;
                     }
 
-                    self.update_results_bar(rq);
+                    if new_page || results_count % RESULTS_BAR_STEP == 0 {
+                        self.update_results_bar(rq);
+                    }
 
                     if results_count == 1 {
                         self.toggle_results_bar(false, rq, context);
@@ -4531,6 +7438,7 @@ impl View for Reader {
                 }
                 let (results_count, pages_count) =
                      self.search.as_ref().map(|s| (s.results_count, s.highlights.len())).unwrap();
+                self.update_results_bar(rq);
                 if results_count == 0 {
                     self.toggle_search_bar(true, hub, rq, context);
                     hub.send(Event::Focus(Some(ViewId::ReaderSearchInput))).ok();
@@ -4544,10 +7452,26 @@ impl View for Reader {
                     msg += &format!(" in {} page{}", pages_count, if pages_count > 1 {"s"} else {""});
                 }
                 let notif = Notification::new(msg, hub, rq, context);
-                self.children.push(Box::new(notif) as Box<dyn View>);
+                push_notification(&mut self.children, notif, rq, context);
+                // Offer to look for the query in the library instead.
+                if results_count == 0 && !self.ephemeral {
+                    let query = self.search.as_ref().map(|s| s.query.clone()).unwrap();
+                    let dialog = Dialog::new(ViewId::SearchLibraryDialog,
+                                             Some(Event::SearchLibrary(query.clone(), self.search_direction)),
+                                             format!("Search the library for “{}”?", query),
+                                             context);
+                    rq.add(RenderData::new(dialog.id(), *dialog.rect(), UpdateMode::Gui));
+                    self.children.push(Box::new(dialog) as Box<dyn View>);
+                }
                 self.update(Some(UpdateMode::Partial), hub, rq, context);
                 true
             },
+            Event::SearchLibrary(ref query, dir) => {
+                self.quit(context);
+                hub.send(Event::Back).ok();
+                hub.send(Event::SearchLibrary(query.clone(), dir)).ok();
+                true
+            },
             Event::Select(EntryId::AnnotateSelection) => {
                 self.toggle_edit_note(None, Some(true), hub, rq, context);
                 true
@@ -4561,6 +7485,7 @@ impl View for Reader {
                             note: String::new(),
                             text,
                             modified: Local::now().naive_local(),
+                            color: HIGHLIGHT_DRIFT,
                         });
                     }
                     if let Some(rect) = self.text_rect([sel.start, sel.end]) {
@@ -4573,13 +7498,31 @@ impl View for Reader {
             },
             Event::Select(EntryId::DefineSelection) => {
                 if let Some(text) = self.selected_text() {
-                    let query = trim_non_alphanumeric(&first_n_words(&text, 5));
+                    let query = selection_words(&text, context.settings.reader.selection_words.define);
                     let language = self.info.language.clone();
                     hub.send(Event::Select(EntryId::Launch(AppCmd::Dictionary { query, language }))).ok();
                 }
                 self.selection = None;
                 true
             },
+            Event::Select(EntryId::CopySelection) => {
+                if let Some(text) = self.selected_text() {
+                    if let Some(path) = context.settings.clipboard_file.clone() {
+                        let line = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+                        let message = match append_line(&path, &line) {
+                            Ok(()) => format!("Copied to {}.", path.display()),
+                            Err(e) => format!("Couldn't write to {}: {}.", path.display(), e),
+                        };
+                        let notif = Notification::new(message, hub, rq, context);
+                        push_notification(&mut self.children, notif, rq, context);
+                    }
+                }
+                if let Some(rect) = self.selection_rect() {
+                    rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+                }
+                self.selection = None;
+                true
+            },
             Event::Select(EntryId::TranslateSelection) => {
                 if let Some(text) = self.selected_text() {
                     let query = text.trim().to_string();
@@ -4592,7 +7535,7 @@ impl View for Reader {
             },
             Event::Select(EntryId::WikiSelection) => {
                 if let Some(text) = self.selected_text() {
-                    let query = trim_non_alphanumeric(&first_n_words(&text, 8));
+                    let query = selection_words(&text, context.settings.reader.selection_words.wiki);
                     hub.send(Event::Select(EntryId::Launch(AppCmd::Wiki { query }))).ok();
                 }
                 self.selection = None;
@@ -4604,8 +7547,14 @@ impl View for Reader {
                 self.update(Some(UpdateMode::Partial), hub, rq, context);
                 true
             },
-            Event::Select(EntryId::SetCssTweakEx(ref selector, index)) => {
-                self.apply_css_tweak_aux(selector, index, hub, context);
+            Event::Select(EntryId::SetCssTweakEx(ref selector, style, index)) => {
+                self.apply_css_tweak_aux(selector, style, index, hub, context);
+                self.selection = None;
+                self.update(Some(UpdateMode::Partial), hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::RepeatCssTweak) => {
+                self.repeat_css_tweak(hub, context);
                 self.selection = None;
                 self.update(Some(UpdateMode::Partial), hub, rq, context);
                 true
@@ -4630,26 +7579,34 @@ impl View for Reader {
                     doc.set_extra_css("");
                 }
                 if let Some(ref mut r) = self.info.reader {
-                    r.extra_css = None;
+                    if let Some(css) = r.extra_css.take() {
+                        self.css_stash = Some(css);
+                    }
                 }
                 hub.send(Event::Notify("All tweaks removed".to_string())).ok();
                 self.selection = None;
                 self.cache.clear();
-                self.text.clear();
+                self.clear_text();
+                self.update(Some(UpdateMode::Partial), hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::RestoreCssTweaks) => {
+                self.restore_css_tweaks(hub, context);
+                self.selection = None;
                 self.update(Some(UpdateMode::Partial), hub, rq, context);
                 true
             },
             Event::Select(EntryId::SearchForSelection) => {
                 if let Some(text) = self.selected_text() {
-                    let text = &trim_non_alphanumeric(&first_n_words(&text, 5));
-                    match make_query(text) {
+                    let text = &selection_words(&text, context.settings.reader.selection_words.search);
+                    match self.search_query(text) {
                         Some(query) => {
-                            self.search(text, query, hub, rq);
+                            self.search(text, query, context.settings.reader.max_search_results, hub, rq);
                         },
                         None => {
                             let notif = Notification::new("Invalid search query.".to_string(),
                                                           hub, rq, context);
-                            self.children.push(Box::new(notif) as Box<dyn View>);
+                            push_notification(&mut self.children, notif, rq, context);
                         },
                     }
                 }
@@ -4677,6 +7634,21 @@ impl View for Reader {
             },
             Event::Select(EntryId::AdjustSelection) => {
                 self.state = State::AdjustSelection;
+                for rect in self.handle_rects() {
+                    rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+                }
+                true
+            },
+            Event::Select(EntryId::ExtendToSentence) => {
+                self.extend_selection_to_sentence(rq);
+                true
+            },
+            Event::Select(EntryId::AddVocabulary) => {
+                self.add_vocabulary(hub, rq, context);
+                if let Some(rect) = self.selection_rect() {
+                    rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+                }
+                self.selection = None;
                 true
             },
             Event::Select(EntryId::EditAnnotationNote(sel)) => {
@@ -4696,6 +7668,17 @@ impl View for Reader {
                 }
                 true
             },
+            Event::Select(EntryId::SetAnnotationColor(sel, color)) => {
+                if let Some(annot) = self.find_annotation_mut(sel) {
+                    annot.color = color;
+                    annot.modified = Local::now().naive_local();
+                    self.update_annotations();
+                }
+                if let Some(rect) = self.text_rect(sel) {
+                    rq.add(RenderData::new(self.id, rect, UpdateMode::Gui));
+                }
+                true
+            },
             Event::Select(EntryId::RemoveAnnotation(sel)) => {
                 if let Some(annotations) = self.info.reader.as_mut().map(|r| &mut r.annotations) {
                     annotations.retain(|annot| annot.selection[0] != sel[0] || annot.selection[1] != sel[1]);
@@ -4710,6 +7693,36 @@ impl View for Reader {
                 self.set_zoom_mode(zoom_mode, true, hub, rq, context);
                 true
             },
+            Event::Select(EntryId::SetZoomPreset(index)) => {
+                if let Some(factor) = context.settings.reader.zoom_presets.get(index).cloned() {
+                    self.set_zoom_preset(factor, hub, rq, context);
+                }
+                true
+            },
+            Event::Select(EntryId::CycleZoomPreset) => {
+                self.cycle_zoom_preset(hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::ToggleSpread) => {
+                self.toggle_spread(hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::SetPageAlignment(page_alignment)) => {
+                self.set_page_alignment(page_alignment, hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::ToggleImagePreserve) => {
+                self.toggle_image_preserve(rq, context);
+                true
+            },
+            Event::Select(EntryId::ToggleReadingRuler) => {
+                self.toggle_reading_ruler(hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::ToggleReadingDirection) => {
+                self.toggle_reading_direction(hub, rq, context);
+                true
+            },
             Event::Select(EntryId::SetScrollMode(scroll_mode)) => {
                 self.set_scroll_mode(scroll_mode, hub, rq, context);
                 true
@@ -4727,7 +7740,7 @@ impl View for Reader {
                     },
                 };
                 let notif = Notification::new(msg, hub, rq, context);
-                self.children.push(Box::new(notif) as Box<dyn View>);
+                push_notification(&mut self.children, notif, rq, context);
                 true
             },
             Event::Select(EntryId::ApplyCroppings(index, scheme)) => {
@@ -4739,6 +7752,20 @@ impl View for Reader {
                 });
                 true
             },
+            Event::Select(EntryId::DetectCroppings) => {
+                self.detect_croppings(hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::AutoCropMargins(scheme)) => {
+                self.auto_crop_margins(scheme, hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::RefreshPages) => {
+                self.cache.clear();
+                self.clear_text();
+                self.update(Some(UpdateMode::Full), hub, rq, context);
+                true
+            },
             Event::Select(EntryId::RemoveCroppings) => {
                 if let Some(r) = self.info.reader.as_mut() {
                     r.cropping_margins = None;
@@ -4751,17 +7778,44 @@ impl View for Reader {
                 self.search_direction = dir;
                 true
             },
+            Event::Select(EntryId::ToggleSearchCaseSensitive) => {
+                self.search_case_sensitive = !self.search_case_sensitive;
+                context.settings.reader.search_case_sensitive = self.search_case_sensitive;
+                true
+            },
+            Event::Select(EntryId::ToggleSearchWholeWord) => {
+                self.search_whole_word = !self.search_whole_word;
+                context.settings.reader.search_whole_word = self.search_whole_word;
+                true
+            },
             Event::Select(EntryId::SetFontFamily(ref font_family)) => {
                 self.set_font_family(font_family, true, hub, rq, context);
                 true
             },
+            Event::Select(EntryId::SwapFontFamily) => {
+                if let Some(font_family) = self.previous_font_family.clone() {
+                    self.set_font_family(&font_family, true, hub, rq, context);
+                } else {
+                    let notif = Notification::new("No previous font family.".to_string(), hub, rq, context);
+                    push_notification(&mut self.children, notif, rq, context);
+                }
+                true
+            },
             Event::Select(EntryId::SetTextAlign(text_align)) => {
                 self.set_text_align(text_align, true, hub, rq, context);
                 true
             },
+            Event::Select(EntryId::ToggleRelativeFontSize) => {
+                let default = context.settings.reader.font_size;
+                if let Some(ref mut r) = self.info.reader {
+                    let relative = r.font_size_relative == Some(true);
+                    r.set_font_size_relative(!relative, default);
+                }
+                true
+            },
             Event::Select(EntryId::SetFontSize(v)) => {
                 let font_size = self.info.reader.as_ref()
-                                    .and_then(|r| r.font_size)
+                                    .and_then(|r| r.font_size(context.settings.reader.font_size))
                                     .unwrap_or(context.settings.reader.font_size);
                 let font_size = font_size - 1.0 + v as f32 / 10.0;
                 self.set_font_size(font_size, true, hub, rq, context);
@@ -4774,6 +7828,12 @@ impl View for Reader {
             Event::Select(EntryId::SetLineHeight(v)) => {
                 let lh_gradient = context.settings.reader.line_height_gradient.clamp(MIN_LINE_HEIGHT_GRADIENT, MAX_LINE_HEIGHT_GRADIENT);
                 let line_height = 1.0 + v as f32 * lh_gradient;
+                // An explicit line height stops the scaling.
+                if self.scale_line_height(context) {
+                    if let Some(ref mut r) = self.info.reader {
+                        r.scale_line_height = Some(false);
+                    }
+                }
                 self.set_line_height(line_height, true, hub, rq, context);
                 true
             },
@@ -4787,6 +7847,14 @@ impl View for Reader {
                 self.set_contrast_gray(gray, hub, rq, context);
                 true
             },
+            Event::Select(EntryId::SetBackgroundGray(gray)) => {
+                self.set_background_gray(gray, hub, rq, context);
+                true
+            },
+            Event::Select(EntryId::ApplyContrastPreset(index)) => {
+                self.apply_contrast_preset(index, hub, rq, context);
+                true
+            },
             Event::Select(EntryId::SetPageName) => {
                 self.toggle_name_page(None, hub, rq, context);
                 true
@@ -4795,6 +7863,7 @@ impl View for Reader {
                 if let Some(ref mut r) = self.info.reader {
                     r.page_names.remove(&self.current_page);
                 }
+                self.chapter_ticks.replace(None);
                 true
             },
             Event::Select(EntryId::ToggleInverted) => {
@@ -4828,12 +7897,17 @@ impl View for Reader {
                 self.quit(context);
                 false
             },
+            // The time spent asleep isn't reading time.
+            Event::Resume => {
+                self.session = ReadingSession::new(self.current_page);
+                false
+            },
             Event::Focus(v) => {
                 if self.focus != v {
                     if let Some(ViewId::ReaderSearchInput) = v {
                         self.toggle_results_bar(false, rq, context);
-                        if let Some(ref mut s) = self.search {
-                            s.running.store(false, AtomicOrdering::Relaxed);
+                        if let Some(ref s) = self.search {
+                            stop_thread(&s.running, self.search_thread.take());
                         }
                         self.render_results(rq);
                         self.search = None;
@@ -4846,6 +7920,7 @@ impl View for Reader {
                 true
             },
             Event::ClockTick => {
+                self.dim_frontlight(context);
                 if self.has_progress_bar() && self.progress_bar.show_clock {
                     *self.dirty_clock.borrow_mut() = false;
                     self.update(Some(UpdateMode::Gui), hub, rq, context);
@@ -4862,16 +7937,33 @@ impl View for Reader {
             return;
         }
 
-        fb.draw_rectangle(&rect, WHITE);
+        // When only the text is inverted, the pages are inverted once their highlights are drawn,
+        // and the images, inverted beforehand, get back their natural tone.
+        let invert_text = self.image_preserve && !fb.inverted();
+
+        let background = self.contrast.background.map_or(WHITE, |gray| gray as u8);
+        fb.draw_rectangle(&rect, if invert_text { BLACK } else { background });
 
         for chunk in &self.chunks {
-            let Resource { ref pixmap, scale, .. } = self.cache[&chunk.location];
+            let Resource { ref pixmap, scale, unavailable, .. } = self.cache[&chunk.location];
             let chunk_rect = chunk.frame - chunk.frame.min + chunk.position;
 
             if let Some(region_rect) = rect.intersection(&chunk_rect) {
                 let chunk_frame = region_rect - chunk.position + chunk.frame.min;
                 let chunk_position = region_rect.min;
-                fb.draw_framed_pixmap_contrast(pixmap, &chunk_frame, chunk_position, self.contrast.exponent, self.contrast.gray);
+                fb.draw_framed_pixmap_contrast(pixmap, &chunk_frame, chunk_position,
+                                               self.contrast.exponent, self.contrast.gray, self.contrast.background);
+
+                if unavailable {
+                    let font = font_from_style(fonts, &NORMAL_STYLE, CURRENT_DEVICE.dpi);
+                    let mut plan = font.plan("Page unavailable", None, None);
+                    let padding = font.em() as i32;
+                    font.crop_right(&mut plan, chunk_rect.width() as i32 - 2 * padding);
+                    let x_height = font.x_heights.0 as i32;
+                    let pt = pt!(chunk_rect.min.x + (chunk_rect.width() as i32 - plan.width) / 2,
+                                 chunk_rect.min.y + (chunk_rect.height() as i32 + x_height) / 2);
+                    font.render(fb, GRAY08, &plan, pt);
+                }
 
                 if let Some(rects) = self.noninverted_regions.get(&chunk.location) {
                     for r in rects {
@@ -4912,7 +8004,11 @@ impl View for Reader {
 
                 if let Some(annotations) = self.annotations.get(&chunk.location) {
                     for annot in annotations {
-                        let drift = if annot.note.is_empty() { HIGHLIGHT_DRIFT } else { ANNOTATION_DRIFT };
+                        let drift = if self.focused_annotation == Some(annot.selection) {
+                            annot.color.saturating_add(FOCUSED_ANNOTATION_BOOST)
+                        } else {
+                            annot.color
+                        };
                         let [start, end] = annot.selection;
                         if let Some(text) = self.text.get(&chunk.location) {
                             let mut last_rect: Option<Rectangle> = None;
@@ -4943,6 +8039,11 @@ impl View for Reader {
                     }
                 }
 
+                // The highlights are drawn on the natural page, so that they stay visible once inverted.
+                if invert_text {
+                    fb.invert_region(&region_rect);
+                }
+
                 if let Some(sel) = self.selection.as_ref() {
                     if let Some(text) = self.text.get(&chunk.location) {
                         let mut last_rect: Option<Rectangle> = None;
@@ -4973,8 +8074,45 @@ impl View for Reader {
             }
         }
 
+        let radius = scale_by_dpi(SELECTION_HANDLE_RADIUS, CURRENT_DEVICE.dpi) as i32;
+        for center in self.handle_centers().iter().flatten() {
+            if Rectangle::from_disk(*center, radius).overlaps(&rect) {
+                fb.draw_disk(*center, radius, BLACK);
+            }
+        }
+
+        if let Some(band) = self.ruler.and_then(|y| self.ruler_band(y)) {
+            let above = rect![self.rect.min.x, self.rect.min.y, self.rect.max.x, band.min.y];
+            let below = rect![self.rect.min.x, band.max.y, self.rect.max.x, self.rect.max.y];
+            for dim_rect in [above, below].iter().filter_map(|r| r.intersection(&rect)) {
+                fb.draw_blended_rectangle(&dim_rect, GRAY12, 0.4);
+            }
+        }
+
+        if let Some((update_mode, refresh_rate)) = self.refresh_debug {
+            let dpi = CURRENT_DEVICE.dpi;
+            let font = font_from_style(fonts, &SMALL_STYLE, dpi);
+            let padding = font.em() as i32 / 2;
+            let plan = font.plan(format!("{}/{} {:?}", self.page_turns, refresh_rate, update_mode),
+                                 None, None);
+            let width = plan.width + 2 * padding;
+            // Keep clear of the close icon when it sits in the top left corner.
+            let x_min = if self.shows_close_icon() && self.close_icon_corner == CloseIconCorner::TopLeft {
+                self.rect.max.x - width
+            } else {
+                self.rect.min.x
+            };
+            let background = rect![pt!(x_min, self.rect.min.y),
+                                   pt!(x_min + width,
+                                       self.rect.min.y + font.x_heights.1 as i32 + 2 * padding)];
+            fb.draw_rectangle(&background, WHITE);
+            font.render(fb, BLACK, &plan, pt!(x_min + padding,
+                                              background.max.y - padding));
+        }
+
         // stop / close button
-        if self.ephemeral || self.search.is_some() && locate::<SearchBar>(self).is_none() {
+        let show_close = self.shows_close_icon();
+        if show_close {
             let dpi = CURRENT_DEVICE.dpi;
             let margin = scale_by_dpi(30.0, dpi) as i32;
             let icon = if let Some(ref s) = self.search {
@@ -4988,20 +8126,23 @@ impl View for Reader {
             };
             let pixmap = ICONS_PIXMAPS.get(icon).unwrap();
             let pw = pixmap.width as i32;
-            let background = rect![pt!(self.rect.max.x - 2 * margin - pw,
-                                       self.rect.min.y),
-                                   pt!(self.rect.max.x,
+            let x_min = match self.close_icon_corner {
+                CloseIconCorner::TopRight => self.rect.max.x - 2 * margin - pw,
+                CloseIconCorner::TopLeft => self.rect.min.x,
+            };
+            let background = rect![pt!(x_min, self.rect.min.y),
+                                   pt!(x_min + 2 * margin + pw,
                                        self.rect.min.y + 2 * margin + pw)];
             fb.draw_rectangle(&background, WHITE);
-            fb.draw_pixmap(pixmap, pt!(self.rect.max.x - margin - pw,
+            fb.draw_pixmap(pixmap, pt!(x_min + margin,
                                        self.rect.min.y + margin));
-        } else
+        }
 
-        if self.info.reader.as_ref().map_or(false, |r| r.bookmarks.contains(&self.current_page)) {
-            let w = self.rect.width() as i32 / 25;
-            let a = pt!(self.rect.max.x - w, self.rect.min.y);
-            let b = pt!(self.rect.max.x, self.rect.min.y);
-            let c = pt!(self.rect.max.x, self.rect.min.y + w);
+        if self.shows_bookmark() {
+            let rect = self.bookmark_rect();
+            let a = rect.min;
+            let b = pt!(rect.max.x, rect.min.y);
+            let c = rect.max;
             fb.draw_triangle(&[a, b, c], GRAY03);
         }
 
@@ -5030,12 +8171,26 @@ impl View for Reader {
                     &CornerSpec::Uniform(bar_height / 2),
                     &BorderSpec { thickness: 0, color: GRAY10 },
                     &|x, _| if x < page_size { GRAY03 } else { GRAY10 });
-            let plan = font.plan(&format!("{:.1} ➤", self.chapter().remain),
-                                          Some(label_width + margin), // allow text to exceed margin
-                                          None);
+            let time_left = if pb.show_time_left { self.book_time_left() } else { None };
+            let label = time_left.or_else(|| if pb.show_word_position { self.chapter().word_position.clone() } else { None })
+                                 .unwrap_or_else(|| format!("{:.1} ➤", self.chapter().remain));
+            let plan = font.plan(&label,
+                                 Some(label_width + margin), // allow text to exceed margin
+                                 None);
             x += bar_width + gap;
             font.render(fb, BLACK, &plan, pt!(x, y));
             *self.dirty_clock.borrow_mut() = false;
+        } else if self.chapter_progress_tick && locate::<BottomBar>(self).is_none() {
+            let chapter = self.chapter();
+            if !chapter.title.is_empty() {
+                let dpi = CURRENT_DEVICE.dpi;
+                let thickness = scale_by_dpi(THICKNESS_MEDIUM, dpi) as i32;
+                let tick_height = self.rect.height() as i32 / 20;
+                let x = self.rect.max.x - 3 * thickness;
+                let y = self.rect.min.y + (chapter.progress * (self.rect.height() as i32 - tick_height) as f32) as i32;
+                fb.draw_rectangle(&rect![x + thickness, self.rect.min.y, x + 2 * thickness, self.rect.max.y], GRAY10);
+                fb.draw_rectangle(&rect![x, y, self.rect.max.x, y + tick_height], GRAY03);
+            }
         }
     }
 
@@ -5045,7 +8200,7 @@ impl View for Reader {
     }
 
     fn resize(&mut self, rect: Rectangle, hub: &Hub, rq: &mut RenderQueue, context: &mut Context) {
-        self.toggle_bars(Some(false), hub, rq, context);
+        self.remove_bars(hub, rq);
 
         match self.view_port.zoom_mode {
             ZoomMode::FitToWidth => {
@@ -5066,19 +8221,24 @@ impl View for Reader {
 
         if self.reflowable {
             let font_size = self.info.reader.as_ref()
-                                .and_then(|r| r.font_size)
+                                .and_then(|r| r.font_size(context.settings.reader.font_size))
                                 .unwrap_or(context.settings.reader.font_size);
             let mut doc = self.doc.lock().unwrap();
-            doc.layout(rect.width(), rect.height(), font_size, CURRENT_DEVICE.dpi);
+            doc.layout(rect.width(), layout_height(rect.height(), self.pinned_bars), font_size, CURRENT_DEVICE.dpi);
             let current_page = self.current_page.min(doc.pages_count() - 1);
             if let Some(location) = doc.resolve_location(Location::Exact(current_page)) {
                 self.current_page = location;
             }
-            self.text.clear();
+            drop(doc);
+            self.clear_text();
         }
 
         self.cache.clear();
         self.update(Some(UpdateMode::Full), hub, rq, context);
+
+        if self.pinned_bars {
+            self.toggle_bars(Some(true), hub, rq, context);
+        }
     }
 
     fn might_rotate(&self) -> bool {
@@ -5089,6 +8249,10 @@ impl View for Reader {
         true
     }
 
+    fn handles_double_tap(&self) -> bool {
+        true
+    }
+
     fn rect(&self) -> &Rectangle {
         &self.rect
     }
@@ -5109,3 +8273,104 @@ impl View for Reader {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, offset: usize) -> BoundedText {
+        BoundedText {
+            text: text.to_string(),
+            rect: bndr![0.0, 0.0, 1.0, 1.0],
+            location: TextLocation::Dynamic(offset),
+        }
+    }
+
+    #[test]
+    fn anchor_skips_carried_over_fragment() {
+        // The page starts at 100, in the middle of a hyphenated word that began at 96.
+        let words = vec![word("para-", 96), word("graph", 100), word("stays", 106), word("visible", 112)];
+        assert_eq!(page_anchor(&words, 100), Some(100));
+        assert_eq!(page_anchor(&words, 101), Some(106));
+        assert_eq!(page_anchor(&words, 200), None);
+    }
+
+    // The fonts are opened from the root of the repository, whatever the current directory.
+    fn html_document(body: &str) -> HtmlDocument {
+        let mut doc = HtmlDocument::new_from_memory(&format!("<html><body>{}</body></html>", body));
+        doc.load_fonts_from(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../..")));
+        doc
+    }
+
+    #[test]
+    fn position_survives_font_size_round_trip() {
+        let body = (0..60).map(|i| format!("<p>Paragraph {}: {}</p>", i,
+                                           "the quick brown fox jumps over the lazy dog ".repeat(12)))
+                          .collect::<String>();
+        let mut doc = html_document(&body);
+        let (width, height, dpi) = (600, 800, 300);
+        doc.layout(width, height, 8.0, dpi);
+        let middle = doc.pages_count() / 2;
+        let page = doc.resolve_location(Location::Exact(middle)).unwrap();
+        let anchor = first_word_offset(&mut doc, page);
+
+        for font_size in [12.0, 8.0] {
+            doc.layout(width, height, font_size, dpi);
+            let location = page_of_offset(&mut doc, anchor).unwrap();
+            let (words, _) = doc.words(Location::Exact(location)).unwrap();
+            assert!(words.iter().any(|w| matches!(w.location, TextLocation::Dynamic(offset) if offset == anchor)));
+        }
+    }
+
+    #[test]
+    fn css_rules_keep_pseudo_elements_and_media_queries() {
+        assert_eq!(css_rule("p.note", "font-weight: bold"), "\np.note {font-weight: bold}");
+        assert_eq!(css_rule("div.a, span.b", "::first-letter { font-size: 2em }"),
+                   "\ndiv.a::first-letter, span.b::first-letter { font-size: 2em }");
+        let media = css_rule("p.note", "@media (min-width: 600px) {\n  p { margin: 0 }\n}");
+        assert_eq!(media, "\n@media (min-width: 600px) { p { margin: 0 } }");
+
+        let extra_css = css_rule("p.note", "font-weight: bold") +
+                        &css_rule("p.note", "::first-letter { font-size: 2em }") +
+                        &media;
+        let css = peel_last_rule(&extra_css);
+        assert_eq!(css, "p.note {font-weight: bold}\np.note::first-letter { font-size: 2em }");
+        assert_eq!(peel_last_rule(&css), "p.note {font-weight: bold}");
+        assert_eq!(peel_last_rule("p.note {font-weight: bold}"), "");
+    }
+
+    #[test]
+    fn css_selectors_follow_ancestor_chain() {
+        let ancestors = vec!["div.chapter".to_string(), "body".to_string()];
+        let selectors = css_selectors("p.note", "", &ancestors);
+        assert_eq!(selectors, vec![(0, "p.note".to_string()),
+                                   (CSS_ANCESTOR_STYLE, "div.chapter p.note".to_string()),
+                                   (CSS_ANCESTOR_STYLE + 1, "body div.chapter p.note".to_string())]);
+        let selectors = css_selectors("p", "span.sc", &ancestors[..1]);
+        assert_eq!(selectors.len(), 6);
+        assert_eq!(selectors[5], (CSS_ANCESTOR_STYLE, "div.chapter p span.sc".to_string()));
+    }
+
+    #[test]
+    fn stopped_search_threads_release_the_document() {
+        let mut html = html_document(&"<p>Searching for the needle in the haystack.</p>".repeat(200));
+        html.layout(600, 800, 10.0, 300);
+        let pages_count = html.pages_count();
+        let doc: Arc<Mutex<Box<dyn Document>>> = Arc::new(Mutex::new(Box::new(html)));
+        let (hub, _receiver) = std::sync::mpsc::channel();
+
+        // Toggle the search on and off rapidly, as the search bar does.
+        for _ in 0..16 {
+            let running = Arc::new(AtomicBool::new(true));
+            let handle = spawn_search(Arc::clone(&doc), Regex::new("needle").unwrap(), Arc::clone(&running),
+                                      LinearDir::Forward, pages_count, 0, hub.clone());
+            stop_thread(&running, Some(handle));
+            assert_eq!(Arc::strong_count(&doc), 1);
+        }
+
+        // The font size can then be changed.
+        let mut doc = doc.lock().unwrap();
+        doc.layout(600, 800, 14.0, 300);
+        assert!(doc.pages_count() > 0);
+    }
+}
@@ -2,7 +2,7 @@ use crate::color::WHITE;
 use crate::device::CURRENT_DEVICE;
 use crate::document::BYTES_PER_PAGE;
 use crate::framebuffer::{Framebuffer, UpdateMode};
-use crate::view::{View, Event, Hub, Bus, Id, ID_FEEDER, RenderQueue, RenderData, SliderId, THICKNESS_MEDIUM, Align};
+use crate::view::{View, Event, Hub, Bus, Id, ID_FEEDER, RenderQueue, RenderData, SliderId, ViewId, THICKNESS_MEDIUM, Align};
 use crate::view::filler::Filler;
 use crate::view::slider::Slider;
 use crate::view::icon::Icon;
@@ -29,7 +29,8 @@ pub struct Scrubber {
 }
 
 impl Scrubber {
-    pub fn new(rect: Rectangle, current_loc: usize, pages_count: usize, synthetic: bool) -> Scrubber {
+    // The chapter locations are marked along the slider.
+    pub fn new(rect: Rectangle, current_loc: usize, pages_count: usize, synthetic: bool, rtl: bool, chapters: &[usize]) -> Scrubber {
         let id = ID_FEEDER.next();
         let mut children = Vec::new();
         let dpi = CURRENT_DEVICE.dpi;
@@ -64,12 +65,17 @@ impl Scrubber {
             } else {
                 0
             };
-        let slider = Slider::new(rect![rect.min.x + label_width, y,
-                                       rect.max.x - side, rect.max.y],
-                                 SliderId::Scrubber,
-                                 current_page,
-                                 min,
-                                 pcount);
+        let mut slider = Slider::new(rect![rect.min.x + label_width, y,
+                                           rect.max.x - side, rect.max.y],
+                                     SliderId::Scrubber,
+                                     current_page,
+                                     min,
+                                     pcount);
+        slider.set_reversed(rtl);
+        let ticks = chapters.iter().map(|&loc| {
+            if synthetic { loc as f32 / BYTES_PER_PAGE as f32 } else { loc as f32 + 1.0 }
+        }).filter(|&page| page > min && page < pcount).collect();
+        slider.set_ticks(ticks);
         children.push(Box::new(slider) as Box<dyn View>);
 
         let back_rect = rect![pt!(rect.max.x - side, y),
@@ -137,8 +143,13 @@ impl Scrubber {
 
 impl View for Scrubber {
 
-    fn handle_event(&mut self, evt: &Event, _hub: &Hub, _bus: &mut Bus, _rq: &mut RenderQueue, _context: &mut Context) -> bool {
+    fn handle_event(&mut self, evt: &Event, _hub: &Hub, bus: &mut Bus, _rq: &mut RenderQueue, _context: &mut Context) -> bool {
         match *evt {
+            // Holding the label asks for a precise location.
+            Event::Gesture(GestureEvent::HoldFingerShort(center, ..)) if self.child(1).rect().includes(center) => {
+                bus.push_back(Event::Show(ViewId::GoToPage));
+                true
+            },
             Event::Gesture(GestureEvent::Tap(center)) |
             Event::Gesture(GestureEvent::HoldFingerShort(center, ..)) if self.rect.includes(center) => true,
             Event::Gesture(GestureEvent::Swipe { start, .. }) if self.rect.includes(start) => true,
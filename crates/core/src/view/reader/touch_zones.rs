@@ -0,0 +1,212 @@
+use crate::device::CURRENT_DEVICE;
+use crate::framebuffer::{Framebuffer, UpdateMode};
+use crate::geom::{Rectangle, Point, Region, Dir, CornerSpec};
+use crate::font::Fonts;
+use crate::color::{BLACK, GRAY05, GRAY10};
+use crate::view::{View, Event, Hub, Bus, Id, ID_FEEDER, RenderQueue, RenderData, ViewId, Align};
+use crate::view::SMALL_BAR_HEIGHT;
+use crate::view::icon::Icon;
+use crate::view::label::Label;
+use crate::gesture::GestureEvent;
+use crate::unit::scale_by_dpi;
+use crate::context::Context;
+
+// Distance, in pixels at 300 DPI, within which a swipe grabs a boundary.
+const GRAB_DISTANCE: f32 = 36.0;
+const ZONES_ALPHA: f32 = 0.5;
+const MIN_WIDTH: f32 = 0.05;
+const MAX_WIDTH: f32 = 0.95;
+
+// Shows the touch regions over the page. Swiping from the inner boundary of a corner or a strip
+// moves it to the end point of the swipe. The widths are written to the settings on close.
+pub struct TouchZones {
+    id: Id,
+    rect: Rectangle,
+    children: Vec<Box<dyn View>>,
+    strip_width: f32,
+    corner_width: f32,
+}
+
+impl TouchZones {
+    pub fn new(rect: Rectangle, rq: &mut RenderQueue, context: &mut Context) -> TouchZones {
+        let id = ID_FEEDER.next();
+        let mut children = Vec::new();
+        let dpi = CURRENT_DEVICE.dpi;
+        let small_height = scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32;
+        let strip_width = context.settings.reader.strip_width;
+        let corner_width = context.settings.reader.corner_width;
+
+        let dx = (rect.width() as i32 - small_height) / 2;
+        let dy = (rect.height() as i32 - small_height) / 2;
+        let icon_rect = rect![rect.min.x + dx, rect.min.y + dy,
+                              rect.min.x + dx + small_height, rect.min.y + dy + small_height];
+        let icon = Icon::new("close",
+                             icon_rect,
+                             Event::Close(ViewId::TouchZones))
+                        .corners(Some(CornerSpec::Uniform(small_height / 2)));
+        children.push(Box::new(icon) as Box<dyn View>);
+
+        let label_width = 6 * small_height;
+        let label = Label::new(rect![rect.center().x - label_width / 2, icon_rect.max.y,
+                                     rect.center().x + label_width / 2, icon_rect.max.y + small_height],
+                               widths_text(strip_width, corner_width),
+                               Align::Center);
+        children.push(Box::new(label) as Box<dyn View>);
+
+        rq.add(RenderData::expose(rect, UpdateMode::Gui));
+
+        TouchZones {
+            id,
+            rect,
+            children,
+            strip_width,
+            corner_width,
+        }
+    }
+
+    // Half of the smallest dimension, the unit of the widths.
+    fn unit(&self) -> f32 {
+        self.rect.width().min(self.rect.height()) as f32 / 2.0
+    }
+
+    fn corner_distance(&self, pt: Point, corner: Point) -> f32 {
+        ((pt.x - corner.x).abs() + (pt.y - corner.y).abs()) as f32
+    }
+
+    fn nearest_corner(&self, pt: Point) -> Point {
+        let x = if pt.x - self.rect.min.x < self.rect.max.x - pt.x { self.rect.min.x } else { self.rect.max.x };
+        let y = if pt.y - self.rect.min.y < self.rect.max.y - pt.y { self.rect.min.y } else { self.rect.max.y };
+        pt!(x, y)
+    }
+
+    fn edge_distance(&self, pt: Point, edge: Dir) -> f32 {
+        let d = match edge {
+            Dir::West => pt.x - self.rect.min.x,
+            Dir::East => self.rect.max.x - pt.x,
+            Dir::North => pt.y - self.rect.min.y,
+            Dir::South => self.rect.max.y - pt.y,
+        };
+        d as f32
+    }
+
+    fn nearest_edge(&self, pt: Point) -> Dir {
+        [Dir::West, Dir::East, Dir::North, Dir::South].iter().cloned()
+            .min_by_key(|edge| self.edge_distance(pt, *edge) as i32)
+            .unwrap()
+    }
+
+    fn region(&self, pt: Point) -> Region {
+        Region::from_point(pt, self.rect, self.strip_width, self.corner_width)
+    }
+
+    // The end of the run of pixels of `region` that starts at `x` on the row `y`, found by bisection.
+    fn run_end(&self, region: Region, x: i32, y: i32, max_x: i32) -> i32 {
+        let (mut lo, mut hi) = (x + 1, max_x);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.region(pt!(mid, y)) == region {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+
+    fn drag_boundary(&mut self, start: Point, end: Point, rq: &mut RenderQueue) {
+        let grab = scale_by_dpi(GRAB_DISTANCE, CURRENT_DEVICE.dpi);
+        let unit = self.unit();
+        let corner = self.nearest_corner(start);
+        let edge = self.nearest_edge(start);
+
+        if (self.corner_distance(start, corner) - unit * self.corner_width).abs() <= grab {
+            self.corner_width = (self.corner_distance(end, corner) / unit).clamp(MIN_WIDTH, MAX_WIDTH);
+        } else if (self.edge_distance(start, edge) - unit * self.strip_width).abs() <= grab {
+            self.strip_width = (self.edge_distance(end, edge) / unit).clamp(MIN_WIDTH, MAX_WIDTH);
+        } else {
+            return;
+        }
+
+        if let Some(label) = self.children[1].downcast_mut::<Label>() {
+            label.update(&widths_text(self.strip_width, self.corner_width), rq);
+        }
+        rq.add(RenderData::expose(self.rect, UpdateMode::Gui));
+    }
+}
+
+fn widths_text(strip_width: f32, corner_width: f32) -> String {
+    format!("Strip {:.2} · Corner {:.2}", strip_width, corner_width)
+}
+
+impl View for TouchZones {
+    fn handle_event(&mut self, evt: &Event, _hub: &Hub, _bus: &mut Bus, rq: &mut RenderQueue, context: &mut Context) -> bool {
+        match *evt {
+            Event::Gesture(GestureEvent::Swipe { start, end, .. }) => {
+                self.drag_boundary(start, end, rq);
+                true
+            },
+            Event::Gesture(..) => true,
+            // Passed on to the reader, which removes the overlay.
+            Event::Close(ViewId::TouchZones) => {
+                context.settings.reader.strip_width = self.strip_width;
+                context.settings.reader.corner_width = self.corner_width;
+                false
+            },
+            _ => false,
+        }
+    }
+
+    // Each region covers a single run of pixels on every row, which is drawn at once.
+    fn render(&self, fb: &mut dyn Framebuffer, rect: Rectangle, _fonts: &mut Fonts) {
+        for y in rect.min.y..rect.max.y {
+            let mut x = rect.min.x;
+            while x < rect.max.x {
+                let region = self.region(pt!(x, y));
+                let end = self.run_end(region, x, y, rect.max.x);
+                let color = match region {
+                    Region::Corner(..) => Some(BLACK),
+                    Region::Strip(Dir::West) | Region::Strip(Dir::East) => Some(GRAY05),
+                    Region::Strip(Dir::South) | Region::Strip(Dir::North) => Some(GRAY10),
+                    Region::Center => None,
+                };
+                if let Some(color) = color {
+                    fb.draw_blended_rectangle(&rect![x, y, end, y + 1], color, ZONES_ALPHA);
+                }
+                x = end;
+            }
+        }
+    }
+
+    fn render_rect(&self, rect: &Rectangle) -> Rectangle {
+        rect.intersection(&self.rect)
+            .unwrap_or(self.rect)
+    }
+
+    fn is_background(&self) -> bool {
+        true
+    }
+
+    fn rect(&self) -> &Rectangle {
+        &self.rect
+    }
+
+    fn rect_mut(&mut self) -> &mut Rectangle {
+        &mut self.rect
+    }
+
+    fn children(&self) -> &Vec<Box<dyn View>> {
+        &self.children
+    }
+
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+        &mut self.children
+    }
+
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    fn view_id(&self) -> Option<ViewId> {
+        Some(ViewId::TouchZones)
+    }
+}
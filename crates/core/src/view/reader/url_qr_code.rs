@@ -0,0 +1,118 @@
+use crate::device::CURRENT_DEVICE;
+use crate::framebuffer::{Framebuffer, UpdateMode, Pixmap};
+use crate::geom::{Rectangle, CornerSpec, BorderSpec};
+use crate::font::{Fonts, RenderPlan, font_from_style, NORMAL_STYLE};
+use crate::color::{BLACK, WHITE, TEXT_NORMAL};
+use crate::view::{View, Event, Hub, Bus, Id, ID_FEEDER, RenderQueue, RenderData, ViewId};
+use crate::view::{THICKNESS_LARGE, BORDER_RADIUS_MEDIUM};
+use crate::gesture::GestureEvent;
+use crate::qrcode::{QrCode, QUIET_ZONE};
+use crate::unit::scale_by_dpi;
+use crate::context::Context;
+
+// Shows an URL as a QR code, above the URL itself, to open it on another device.
+pub struct UrlQrCode {
+    id: Id,
+    rect: Rectangle,
+    children: Vec<Box<dyn View>>,
+    pixmap: Pixmap,
+    plan: RenderPlan,
+    padding: i32,
+}
+
+impl UrlQrCode {
+    // Returns `None` when the URL is too long to be encoded.
+    pub fn new(rect: Rectangle, url: &str, rq: &mut RenderQueue, context: &mut Context) -> Option<UrlQrCode> {
+        let code = QrCode::encode(url.as_bytes())?;
+        let id = ID_FEEDER.next();
+        let dpi = CURRENT_DEVICE.dpi;
+        let font = font_from_style(&mut context.fonts, &NORMAL_STYLE, dpi);
+        let padding = font.em() as i32;
+        let line_height = font.line_height();
+
+        // The symbol and its quiet zone span at most two thirds of the smallest dimension.
+        let side = 2 * rect.width().min(rect.height()) / 3;
+        let module_size = (side / (code.size() + 2 * QUIET_ZONE) as u32).max(1);
+        let pixmap = code.to_pixmap(module_size);
+
+        let mut plan = font.plan(url, None, None);
+        font.crop_right(&mut plan, pixmap.width as i32);
+
+        let width = pixmap.width as i32 + 2 * padding;
+        let height = pixmap.height as i32 + line_height + 2 * padding;
+        let x_min = rect.min.x + (rect.width() as i32 - width) / 2;
+        let y_min = rect.min.y + (rect.height() as i32 - height) / 2;
+        let rect = rect![x_min, y_min, x_min + width, y_min + height];
+        rq.add(RenderData::new(id, rect, UpdateMode::Gui));
+
+        Some(UrlQrCode {
+            id,
+            rect,
+            children: Vec::new(),
+            pixmap,
+            plan,
+            padding,
+        })
+    }
+}
+
+impl View for UrlQrCode {
+    fn handle_event(&mut self, evt: &Event, _hub: &Hub, bus: &mut Bus, _rq: &mut RenderQueue, _context: &mut Context) -> bool {
+        match *evt {
+            Event::Gesture(GestureEvent::Tap(..)) => {
+                bus.push_back(Event::Close(ViewId::UrlQrCode));
+                true
+            },
+            Event::Gesture(..) => true,
+            _ => false,
+        }
+    }
+
+    fn render(&self, fb: &mut dyn Framebuffer, _rect: Rectangle, fonts: &mut Fonts) {
+        let dpi = CURRENT_DEVICE.dpi;
+
+        let border_radius = scale_by_dpi(BORDER_RADIUS_MEDIUM, dpi) as i32;
+        let border_thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as u16;
+
+        fb.draw_rounded_rectangle_with_border(&self.rect,
+                                              &CornerSpec::Uniform(border_radius),
+                                              &BorderSpec { thickness: border_thickness,
+                                                            color: BLACK },
+                                              &WHITE);
+
+        let pt = pt!(self.rect.min.x + self.padding, self.rect.min.y + self.padding);
+        fb.draw_pixmap(&self.pixmap, pt);
+
+        let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+        let x_height = font.x_heights.0 as i32;
+        let line_height = font.line_height();
+        let dx = (self.pixmap.width as i32 - self.plan.width) / 2;
+        let pt = pt!(self.rect.min.x + self.padding + dx,
+                     self.rect.min.y + self.padding + self.pixmap.height as i32 + (line_height + x_height) / 2);
+        font.render(fb, TEXT_NORMAL[1], &self.plan, pt);
+    }
+
+    fn rect(&self) -> &Rectangle {
+        &self.rect
+    }
+
+    fn rect_mut(&mut self) -> &mut Rectangle {
+        &mut self.rect
+    }
+
+    fn children(&self) -> &Vec<Box<dyn View>> {
+        &self.children
+    }
+
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+        &mut self.children
+    }
+
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    fn view_id(&self) -> Option<ViewId> {
+        Some(ViewId::UrlQrCode)
+    }
+}
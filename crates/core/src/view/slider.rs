@@ -19,6 +19,9 @@ pub struct Slider {
     value: f32,
     min_value: f32,
     max_value: f32,
+    reversed: bool,
+    // Values marked along the progress bar.
+    ticks: Vec<f32>,
     active: bool,
     last_x: i32,
 }
@@ -33,20 +36,34 @@ impl Slider {
             value,
             min_value,
             max_value,
+            reversed: false,
+            ticks: Vec::new(),
             active: false,
             last_x: -1,
         }
     }
 
+    // A reversed slider has its minimum on the right.
+    pub fn set_reversed(&mut self, reversed: bool) {
+        self.reversed = reversed;
+    }
+
+    pub fn set_ticks(&mut self, ticks: Vec<f32>) {
+        self.ticks = ticks;
+    }
+
     pub fn update_value(&mut self, x_hit: i32) {
         let dpi = CURRENT_DEVICE.dpi;
         let button_diameter = scale_by_dpi(BUTTON_DIAMETER, dpi) as i32;
         let (small_radius, big_radius) = halves(button_diameter);
         let x_offset = x_hit.max(self.rect.min.x + small_radius)
                             .min(self.rect.max.x - big_radius);
-        let progress = ((x_offset - self.rect.min.x - small_radius) as f32 /
-                        (self.rect.width() as i32 - button_diameter) as f32)
-                       .clamp(0.0, 1.0);
+        let mut progress = ((x_offset - self.rect.min.x - small_radius) as f32 /
+                            (self.rect.width() as i32 - button_diameter) as f32)
+                           .clamp(0.0, 1.0);
+        if self.reversed {
+            progress = 1.0 - progress;
+        }
         self.value = self.min_value + progress * (self.max_value - self.min_value);
     }
 
@@ -105,7 +122,10 @@ impl View for Slider {
         let button_diameter = scale_by_dpi(BUTTON_DIAMETER, dpi) as i32;
         let border_thickness = scale_by_dpi(THICKNESS_SMALL, dpi) as u16;
 
-        let progress = (self.value - self.min_value) / (self.max_value - self.min_value);
+        let mut progress = (self.value - self.min_value) / (self.max_value - self.min_value);
+        if self.reversed {
+            progress = 1.0 - progress;
+        }
         let (small_radius, big_radius) = halves(button_diameter);
         let x_offset = self.rect.min.x + small_radius +
                        ((self.rect.width() as f32 - button_diameter as f32) * progress) as i32;
@@ -121,9 +141,22 @@ impl View for Slider {
                                               &CornerSpec::Uniform(small_mini_radius),
                                               &BorderSpec { thickness: border_thickness,
                                                             color: BLACK },
-                                              &|x, _| if x < x_offset { PROGRESS_FULL }
+                                              &|x, _| if (x < x_offset) != self.reversed { PROGRESS_FULL }
                                                       else { PROGRESS_EMPTY });
 
+        let tick_width = border_thickness as i32;
+        for value in &self.ticks {
+            let mut progress = (value - self.min_value) / (self.max_value - self.min_value);
+            if self.reversed {
+                progress = 1.0 - progress;
+            }
+            let x = self.rect.min.x + small_radius +
+                    ((self.rect.width() as f32 - button_diameter as f32) * progress) as i32;
+            let tick_rect = rect![x - tick_width / 2, rect.min.y - progress_height / 2,
+                                  x - tick_width / 2 + tick_width, rect.max.y + progress_height / 2];
+            fb.draw_rectangle(&tick_rect, BLACK);
+        }
+
         let (small_padding, big_padding) = halves(self.rect.height() as i32 - button_diameter);
         let rect = rect![x_offset - small_radius, self.rect.min.y + small_padding,
                          x_offset + big_radius, self.rect.max.y - big_padding];
@@ -139,7 +172,7 @@ impl View for Slider {
         let plan = font.plan(&format!("{:.1}", self.value), None, None);
         let x_height = font.x_heights.1 as i32;
 
-        let x_drift = if self.value > (self.min_value + self.max_value) / 2.0 {
+        let x_drift = if progress > 0.5 {
             -(small_radius + plan.width)
         } else {
             small_radius
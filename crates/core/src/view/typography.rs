@@ -0,0 +1,265 @@
+use crate::device::CURRENT_DEVICE;
+use crate::framebuffer::{Framebuffer, UpdateMode};
+use crate::geom::{Rectangle, CornerSpec, BorderSpec};
+use crate::font::{Fonts, font_from_style, NORMAL_STYLE};
+use crate::metadata::TextAlign;
+use super::{View, Event, Hub, Bus, Id, ID_FEEDER, RenderQueue, RenderData, ViewId, EntryId, Align};
+use super::{SMALL_BAR_HEIGHT, THICKNESS_LARGE, BORDER_RADIUS_MEDIUM};
+use super::label::Label;
+use super::button::Button;
+use super::icon::Icon;
+use crate::gesture::GestureEvent;
+use crate::color::{BLACK, WHITE};
+use crate::unit::scale_by_dpi;
+use crate::context::Context;
+
+const WIDGET_OFFSET: usize = 2;
+const TEXT_ALIGNS: [TextAlign; 4] = [TextAlign::Justify, TextAlign::Left, TextAlign::Right, TextAlign::Center];
+// Number of line height steps offered, as in the line height menu.
+const LINE_HEIGHT_STEPS: i32 = 10;
+
+// Adjusts the font size, the margin width, the line height and the text alignment
+// of a reflowable document from a single place. The controls emit the same events
+// as the corresponding menus, so the reader applies each change as it happens.
+pub struct TypographyDialog {
+    id: Id,
+    rect: Rectangle,
+    children: Vec<Box<dyn View>>,
+    font_size: f32,
+    font_size_range: (f32, f32),
+    margin_width: i32,
+    margin_width_range: (i32, i32),
+    line_height: i32,
+    line_height_gradient: f32,
+    text_align: TextAlign,
+    padding: i32,
+    row_height: i32,
+}
+
+impl TypographyDialog {
+    pub fn new(font_size: f32, margin_width: i32, line_height: f32, text_align: TextAlign,
+               line_height_gradient: f32, context: &mut Context) -> TypographyDialog {
+        let id = ID_FEEDER.next();
+        let fonts = &mut context.fonts;
+        let mut children = Vec::new();
+        let dpi = CURRENT_DEVICE.dpi;
+        let (width, height) = context.display.dims;
+        let window_width = width as i32 * if height > width {9} else {7} / 10;
+        let small_height = scale_by_dpi(SMALL_BAR_HEIGHT, dpi) as i32;
+        let thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as i32;
+        let border_radius = scale_by_dpi(BORDER_RADIUS_MEDIUM, dpi) as i32;
+
+        let padding = {
+            let font = font_from_style(fonts, &NORMAL_STYLE, dpi);
+            font.em() as i32
+        };
+
+        let row_height = small_height;
+        let window_height = small_height + 4 * (row_height + padding / 2) + 2 * padding;
+
+        // Keep the upper part of the page visible to judge the changes.
+        let dx = (width as i32 - window_width) / 2;
+        let dy = height as i32 - window_height - small_height;
+
+        let rect = rect![dx, dy, dx + window_width, dy + window_height];
+
+        let corners = CornerSpec::Detailed {
+            north_west: 0,
+            north_east: border_radius - thickness,
+            south_east: 0,
+            south_west: 0,
+        };
+
+        let close_icon = Icon::new("close",
+                                   rect![rect.max.x - small_height,
+                                         rect.min.y + thickness,
+                                         rect.max.x - thickness,
+                                         rect.min.y + small_height],
+                                   Event::Close(ViewId::TypographyDialog))
+                              .corners(Some(corners));
+
+        children.push(Box::new(close_icon) as Box<dyn View>);
+
+        let label = Label::new(rect![rect.min.x + small_height,
+                                     rect.min.y + thickness + padding / 2,
+                                     rect.max.x - small_height,
+                                     rect.min.y + small_height + padding / 2],
+                               "Typography".to_string(),
+                               Align::Center);
+
+        children.push(Box::new(label) as Box<dyn View>);
+
+        let settings = &context.settings.reader;
+        let line_height = ((line_height - 1.0) / line_height_gradient).round() as i32;
+
+        let mut typography = TypographyDialog {
+            id,
+            rect,
+            children,
+            font_size,
+            font_size_range: (settings.font_size / 2.0, 3.0 * settings.font_size / 2.0),
+            margin_width,
+            margin_width_range: (settings.min_margin_width, settings.max_margin_width),
+            line_height: line_height.clamp(0, LINE_HEIGHT_STEPS),
+            line_height_gradient,
+            text_align,
+            padding,
+            row_height,
+        };
+
+        let rows = typography.rows();
+        typography.children.extend(rows);
+        typography
+    }
+
+    fn rows(&self) -> Vec<Box<dyn View>> {
+        let mut rows = Vec::new();
+        let padding = self.padding;
+        let name_width = (self.rect.width() as i32 - 3 * padding) / 3;
+        let x_min = self.rect.min.x + padding;
+        let controls_min = x_min + name_width + padding;
+        let controls_width = self.rect.max.x - padding - controls_min;
+        let mut y = self.rect.min.y + self.row_height + 3 * padding / 2;
+
+        let (min_fs, max_fs) = self.font_size_range;
+        let (min_mw, max_mw) = self.margin_width_range;
+        let steppers = [
+            ("Font size", format!("{:.1}", self.font_size),
+             (self.font_size - 0.1 >= min_fs, EntryId::SetFontSize(9)),
+             (self.font_size + 0.1 <= max_fs, EntryId::SetFontSize(11))),
+            ("Margin width", format!("{}", self.margin_width),
+             (self.margin_width > min_mw, EntryId::SetMarginWidth(self.margin_width - 1)),
+             (self.margin_width < max_mw, EntryId::SetMarginWidth(self.margin_width + 1))),
+            ("Line height", format!("{:.3}", 1.0 + self.line_height as f32 * self.line_height_gradient),
+             (self.line_height > 0, EntryId::SetLineHeight(self.line_height - 1)),
+             (self.line_height < LINE_HEIGHT_STEPS, EntryId::SetLineHeight(self.line_height + 1))),
+        ];
+
+        let button_width = controls_width / 4;
+
+        for (name, value, (dec_enabled, dec_id), (inc_enabled, inc_id)) in steppers {
+            let label = Label::new(rect![x_min, y, x_min + name_width, y + self.row_height],
+                                   name.to_string(),
+                                   Align::Left(0));
+            rows.push(Box::new(label) as Box<dyn View>);
+            let decrease = Button::new(rect![controls_min, y,
+                                             controls_min + button_width, y + self.row_height],
+                                       Event::Select(dec_id),
+                                       "-".to_string())
+                                  .disabled(!dec_enabled);
+            rows.push(Box::new(decrease) as Box<dyn View>);
+            let value = Label::new(rect![controls_min + button_width, y,
+                                         controls_min + controls_width - button_width, y + self.row_height],
+                                   value,
+                                   Align::Center);
+            rows.push(Box::new(value) as Box<dyn View>);
+            let increase = Button::new(rect![controls_min + controls_width - button_width, y,
+                                             controls_min + controls_width, y + self.row_height],
+                                       Event::Select(inc_id),
+                                       "+".to_string())
+                                  .disabled(!inc_enabled);
+            rows.push(Box::new(increase) as Box<dyn View>);
+            y += self.row_height + padding / 2;
+        }
+
+        let label = Label::new(rect![x_min, y, x_min + name_width, y + self.row_height],
+                               "Alignment".to_string(),
+                               Align::Left(0));
+        rows.push(Box::new(label) as Box<dyn View>);
+        let gap = padding / 4;
+        let align_width = (controls_width - 3 * gap) / 4;
+        let mut x = controls_min;
+        for text_align in TEXT_ALIGNS.iter() {
+            let button = Button::new(rect![x, y, x + align_width, y + self.row_height],
+                                     Event::Select(EntryId::SetTextAlign(*text_align)),
+                                     text_align.to_string())
+                                .toggle(*text_align == self.text_align);
+            rows.push(Box::new(button) as Box<dyn View>);
+            x += align_width + gap;
+        }
+
+        rows
+    }
+
+    fn refresh(&mut self, rq: &mut RenderQueue) {
+        self.children.truncate(WIDGET_OFFSET);
+        let rows = self.rows();
+        self.children.extend(rows);
+        rq.add(RenderData::new(self.id, self.rect, UpdateMode::Gui));
+    }
+}
+
+impl View for TypographyDialog {
+    fn handle_event(&mut self, evt: &Event, _hub: &Hub, bus: &mut Bus, rq: &mut RenderQueue, _context: &mut Context) -> bool {
+        // The selections are passed on to the reader, which applies them.
+        match *evt {
+            Event::Select(EntryId::SetFontSize(v)) => {
+                self.font_size += (v - 10) as f32 / 10.0;
+                self.refresh(rq);
+                false
+            },
+            Event::Select(EntryId::SetMarginWidth(width)) => {
+                self.margin_width = width;
+                self.refresh(rq);
+                false
+            },
+            Event::Select(EntryId::SetLineHeight(v)) => {
+                self.line_height = v;
+                self.refresh(rq);
+                false
+            },
+            Event::Select(EntryId::SetTextAlign(text_align)) => {
+                self.text_align = text_align;
+                self.refresh(rq);
+                false
+            },
+            Event::Gesture(GestureEvent::Tap(center)) if !self.rect.includes(center) => {
+                bus.push_back(Event::Close(ViewId::TypographyDialog));
+                true
+            },
+            Event::Gesture(..) => true,
+            _ => false,
+        }
+    }
+
+    fn render(&self, fb: &mut dyn Framebuffer, _rect: Rectangle, _fonts: &mut Fonts) {
+        let dpi = CURRENT_DEVICE.dpi;
+
+        let border_radius = scale_by_dpi(BORDER_RADIUS_MEDIUM, dpi) as i32;
+        let border_thickness = scale_by_dpi(THICKNESS_LARGE, dpi) as u16;
+
+        fb.draw_rounded_rectangle_with_border(&self.rect,
+                                              &CornerSpec::Uniform(border_radius),
+                                              &BorderSpec { thickness: border_thickness,
+                                                            color: BLACK },
+                                              &WHITE);
+    }
+
+    fn is_background(&self) -> bool {
+        true
+    }
+
+    fn rect(&self) -> &Rectangle {
+        &self.rect
+    }
+
+    fn rect_mut(&mut self) -> &mut Rectangle {
+        &mut self.rect
+    }
+
+    fn children(&self) -> &Vec<Box<dyn View>> {
+        &self.children
+    }
+
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn View>> {
+        &mut self.children
+    }
+
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    fn view_id(&self) -> Option<ViewId> {
+        Some(ViewId::TypographyDialog)
+    }
+}
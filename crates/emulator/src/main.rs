@@ -21,7 +21,7 @@ use plato_core::view::{View, Event, ViewId, EntryId, AppCmd, EntryKind};
 use plato_core::view::{process_render_queue, wait_for_all, handle_event, RenderQueue, RenderData};
 use plato_core::view::home::Home;
 use plato_core::view::reader::Reader;
-use plato_core::view::notification::Notification;
+use plato_core::view::notification::{Notification, push_notification};
 use plato_core::view::dialog::Dialog;
 use plato_core::view::frontlight::FrontlightWindow;
 use plato_core::view::menu::{Menu, MenuKind};
@@ -388,6 +388,13 @@ fn main() -> Result<(), Error> {
 
         while let Ok(evt) = rx.recv_timeout(Duration::from_millis(20)) {
             match evt {
+                Event::OpenNextInSeries(ref path) => {
+                    if let Some(info) = context.library.next_sibling(path) {
+                        tx.send(Event::Open(Box::new(info))).ok();
+                    } else {
+                        tx.send(Event::Notify("No next book in the series.".to_string())).ok();
+                    }
+                },
                 Event::Open(info) => {
                     let rotation = context.display.rotation;
                     if let Some(n) = info.reader.as_ref()
@@ -560,7 +567,7 @@ fn main() -> Result<(), Error> {
                 },
                 Event::Notify(msg) => {
                     let notif = Notification::new(msg, &tx, &mut rq, &mut context);
-                    view.children_mut().push(Box::new(notif) as Box<dyn View>);
+                    push_notification(view.children_mut(), notif, &mut rq, &context);
                 },
                 Event::Device(DeviceEvent::NetUp) |
                 Event::CheckFetcher(..) |
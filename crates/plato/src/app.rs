@@ -34,7 +34,7 @@ use plato_core::view::home::Home;
 use plato_core::view::reader::Reader;
 use plato_core::view::dialog::Dialog;
 use plato_core::view::intermission::Intermission;
-use plato_core::view::notification::Notification;
+use plato_core::view::notification::{Notification, push_notification};
 use plato_core::device::{CURRENT_DEVICE, Orientation, FrontlightKind};
 use plato_core::library::Library;
 use plato_core::font::Fonts;
@@ -166,6 +166,7 @@ fn resume(id: TaskId, tasks: &mut Vec<Task>, view: &mut dyn View, hub: &Sender<E
             view.children_mut().remove(index);
             rq.add(RenderData::expose(rect, UpdateMode::Full));
         }
+        hub.send(Event::Resume).ok();
         hub.send(Event::ClockTick).ok();
         hub.send(Event::BatteryTick).ok();
     }
@@ -704,7 +705,9 @@ pub fn run() -> Result<(), Error> {
                             (Region::Corner(DiagDir::NorthWest), Region::Corner(DiagDir::SouthEast)) => {
                                 tx.send(Event::Select(EntryId::TakeScreenshot)).ok();
                             },
-                            _ => (),
+                            _ => {
+                                handle_event(view.as_mut(), &evt, &tx, &mut bus, &mut rq, &mut context);
+                            },
                         }
                     },
                     _ => {
@@ -716,6 +719,13 @@ pub fn run() -> Result<(), Error> {
                 context.set_frontlight(!context.settings.frontlight);
                 view.handle_event(&Event::ToggleFrontlight, &tx, &mut bus, &mut rq, &mut context);
             },
+            Event::OpenNextInSeries(ref path) => {
+                if let Some(info) = context.library.next_sibling(path) {
+                    tx.send(Event::Open(Box::new(info))).ok();
+                } else {
+                    tx.send(Event::Notify("No next book in the series.".to_string())).ok();
+                }
+            },
             Event::Open(info) => {
                 let rotation = context.display.rotation;
                 let dithered = context.fb.dithered();
@@ -730,9 +740,6 @@ pub fn run() -> Result<(), Error> {
                             }
                         }
                     }
-                    context.fb.set_dithered(reader_info.dithered);
-                } else {
-                    context.fb.set_dithered(context.settings.reader.dithered_kinds.contains(&info.file.kind));
                 }
                 let path = info.file.path.clone();
                 if let Some(r) = Reader::new(context.fb.rect(), *info, &tx, &mut context) {
@@ -944,7 +951,7 @@ pub fn run() -> Result<(), Error> {
                     Ok(_) => format!("Saved {}.", name),
                 };
                 let notif = Notification::new(msg, &tx, &mut rq, &mut context);
-                view.children_mut().push(Box::new(notif) as Box<dyn View>);
+                push_notification(view.children_mut(), notif, &mut rq, &context);
             },
             Event::CheckFetcher(..) |
             Event::FetcherAddDocument(..) |